@@ -2,19 +2,50 @@
 //!
 //! Run with: cargo run --bin generate-wallet
 //!
+//! With `--keystore <DIR>` the key is written to an encrypted Web3 Secret
+//! Storage (scrypt) keystore instead of being printed. The passphrase is
+//! read from `KEYSTORE_PASSWORD_FD` when set, otherwise prompted for twice.
+//!
 //! This generates a new Ethereum wallet for use as:
 //! - Flashbots bundle signer
 //! - Profit wallet (NOT RECOMMENDED - use hardware wallet for large amounts)
 
 use alloy_signer_local::PrivateKeySigner;
+use clap::Parser;
+use std::path::{Path, PathBuf};
+
+#[path = "../executor/passphrase_fd.rs"]
+mod passphrase_fd;
+
+#[derive(Parser)]
+#[command(about = "Generate a new Ethereum wallet")]
+struct Args {
+    /// Write an encrypted keystore into this directory instead of printing the key
+    #[arg(long, value_name = "DIR")]
+    keystore: Option<PathBuf>,
+    
+    /// Keystore file name (default: <address>.json)
+    #[arg(long, requires = "keystore")]
+    name: Option<String>,
+}
 
 fn main() {
+    let args = Args::parse();
+    
     println!();
     println!("╔════════════════════════════════════════════════════════════╗");
     println!("║          SNIPER WALLET GENERATOR                           ║");
     println!("╚════════════════════════════════════════════════════════════╝");
     println!();
     
+    if let Some(dir) = args.keystore {
+        if let Err(e) = write_keystore(&dir, args.name) {
+            eprintln!("❌ {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    
     // Generate a new random wallet
     let signer = PrivateKeySigner::random();
     let address = signer.address();
@@ -58,3 +89,62 @@ fn main() {
     println!("   4. Profits go to your executor contract, then you withdraw");
     println!();
 }
+
+/// Generate a wallet straight into an encrypted keystore; the key is never shown
+fn write_keystore(dir: &Path, name: Option<String>) -> Result<(), String> {
+    let passphrase = read_passphrase()?;
+    
+    std::fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    
+    let signer = PrivateKeySigner::random();
+    let address = signer.address();
+    let name = name.unwrap_or_else(|| format!("{:?}.json", address));
+    let path = dir.join(&name);
+    if path.exists() {
+        return Err(format!("{} already exists", path.display()));
+    }
+    
+    PrivateKeySigner::encrypt_keystore(dir, &mut rand::thread_rng(), signer.credential().to_bytes(), &passphrase, Some(&name))
+        .map_err(|e| format!("Cannot write keystore: {}", e))?;
+    
+    println!("🔐 NEW WALLET WRITTEN TO ENCRYPTED KEYSTORE");
+    println!();
+    println!("   Address:  {:?}", address);
+    println!("   Keystore: {}", path.display());
+    println!();
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    println!();
+    println!("📝 TO USE THIS WALLET (add to .env):");
+    println!();
+    println!("   FLASHBOTS_SIGNER_KEY={}", path.display());
+    println!("   # or PROFIT_WALLET_PRIVATE_KEY={}", path.display());
+    println!();
+    println!("   The bot asks for the passphrase at startup, or reads it from");
+    println!("   the descriptor named by KEYSTORE_PASSWORD_FD.");
+    println!();
+    println!("⚠️  Without the passphrase the key cannot be recovered - back up both.");
+    println!();
+    Ok(())
+}
+
+/// First line of `KEYSTORE_PASSWORD_FD`, or a confirmed prompt
+fn read_passphrase() -> Result<String, String> {
+    if let Ok(fd) = std::env::var("KEYSTORE_PASSWORD_FD") {
+        let fd: i32 = fd.trim().parse().map_err(|e| format!("Invalid KEYSTORE_PASSWORD_FD: {}", e))?;
+        let content = passphrase_fd::read_fd(fd).map_err(|e| format!("Cannot read fd {}: {}", fd, e))?;
+        return content.lines().next()
+            .map(|l| l.trim_end_matches('\r').to_string())
+            .filter(|p| !p.is_empty())
+            .ok_or_else(|| "No passphrase on KEYSTORE_PASSWORD_FD".to_string());
+    }
+    
+    let passphrase = rpassword::prompt_password("New keystore passphrase: ").map_err(|e| e.to_string())?;
+    if passphrase.is_empty() {
+        return Err("Empty passphrase".to_string());
+    }
+    let confirm = rpassword::prompt_password("Repeat passphrase: ").map_err(|e| e.to_string())?;
+    if passphrase != confirm {
+        return Err("Passphrases do not match".to_string());
+    }
+    Ok(passphrase)
+}
@@ -1,11 +1,12 @@
 //! Bounded Bellman-Ford Algorithm - QUIET Edition
 
 use alloy_primitives::Address;
-use petgraph::graph::NodeIndex;
+use petgraph::graph::{EdgeIndex, NodeIndex};
 use petgraph::visit::EdgeRef;
 use std::collections::{HashMap, HashSet};
-use tracing::{debug, trace, warn};  // Changed from info
+use tracing::{debug, trace};  // Changed from info
 
+use super::RiskPolicy;
 use crate::cartographer::{ArbitrageGraph, Dex, EdgeData};
 
 #[derive(Debug, Clone)]
@@ -74,14 +75,57 @@ impl ArbitrageCycle {
     }
 }
 
+/// Best partial walks kept per (hop, token). Higher = more near-duplicate
+/// cycles found, cost grows linearly.
+const LABELS_PER_NODE: usize = 4;
+
+/// Cycles returning less than this are not reported (by default)
+pub const MIN_REPORTED_RETURN: f64 = 0.95;
+
+/// A partial walk from the start token: total weight so far, the last edge
+/// taken, the label it extends in the previous hop layer, and the longest
+/// cycle the walk's tokens allow
+#[derive(Debug, Clone, Copy)]
+struct Label {
+    weight: f64,
+    edge: EdgeIndex,
+    parent: usize,
+    hop_limit: usize,
+}
+
+/// Hop-bounded Bellman-Ford with predecessor walking
+///
+/// Layer `k` holds, for every token, the `LABELS_PER_NODE` lowest-weight simple
+/// walks of exactly `k` edges from the start token. Each layer relaxes only the
+/// out-edges of tokens reached in the previous one, so a search costs
+/// O(max_hops × edges × LABELS_PER_NODE) instead of enumerating every path.
+/// A cycle is closed whenever an edge leads back to the start token; it is
+/// recovered by walking the labels' predecessors.
+///
+/// With a `RiskPolicy`, denied tokens and too-shallow pools are never
+/// relaxed, and each walk carries the tightest hop limit of its tokens.
 pub struct BoundedBellmanFord<'a> {
     graph: &'a ArbitrageGraph,
     max_hops: usize,
+    min_return: f64,
+    policy: Option<&'a RiskPolicy>,
 }
 
 impl<'a> BoundedBellmanFord<'a> {
     pub fn new(graph: &'a ArbitrageGraph, max_hops: usize) -> Self {
-        Self { graph, max_hops }
+        Self { graph, max_hops, min_return: MIN_REPORTED_RETURN, policy: None }
+    }
+
+    /// Report cycles down to this return (e.g. to catalog near-misses)
+    pub fn with_min_return(mut self, min_return: f64) -> Self {
+        self.min_return = min_return;
+        self
+    }
+
+    /// Enforce a risk policy during traversal
+    pub fn with_policy(mut self, policy: &'a RiskPolicy) -> Self {
+        self.policy = Some(policy);
+        self
     }
 
     pub fn find_cycles_from(&self, start_token: Address) -> Vec<ArbitrageCycle> {
@@ -89,98 +133,186 @@ impl<'a> BoundedBellmanFord<'a> {
         let Some(start_node) = self.graph.get_node(start_token) else {
             return cycles;
         };
+        if self.max_hops < 2 || self.policy.is_some_and(|p| !p.allows_token(&start_token)) {
+            return cycles;
+        }
+        let start_limit = self.policy
+            .map_or(self.max_hops, |p| p.max_hops(&start_token).min(self.max_hops));
+
+        let graph = &self.graph.graph;
+        let node_count = graph.node_count();
+
+        // layers[k] = labels of walks with k edges; buckets[v] = label indices at v
+        let mut layers: Vec<Vec<Label>> = vec![vec![Label {
+            weight: 0.0,
+            edge: EdgeIndex::end(),
+            parent: usize::MAX,
+            hop_limit: start_limit,
+        }]];
+        let mut frontier: Vec<(NodeIndex, Vec<usize>)> = vec![(start_node, vec![0])];
+
+        for hop in 1..=self.max_hops {
+            let prev = &layers[hop - 1];
+            let mut next: Vec<Label> = Vec::new();
+            let mut buckets: Vec<Vec<usize>> = vec![Vec::new(); node_count];
+            let mut touched: Vec<NodeIndex> = Vec::new();
+
+            for (node, label_ids) in &frontier {
+                for edge in graph.edges(*node) {
+                    let target = edge.target();
+                    let edge_weight = edge.weight().weight;
+                    if !edge_weight.is_finite() {
+                        continue;
+                    }
+                    let target_limit = match self.policy {
+                        Some(policy) => {
+                            if !policy.allows_hop(&graph[*node], &graph[target], &edge.weight().pool_address) {
+                                continue;
+                            }
+                            policy.max_hops(&graph[target])
+                        }
+                        None => self.max_hops,
+                    };
 
-        self.dfs_find_cycles(
-            start_node, start_node,
-            Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(),
-            HashSet::new(), 0.0, &mut cycles, 1,
+                    for &label_id in label_ids {
+                        let weight = prev[label_id].weight + edge_weight;
+                        let hop_limit = prev[label_id].hop_limit;
+
+                        if target == start_node {
+                            if hop >= 2 && hop <= hop_limit && (-weight).exp() > self.min_return {
+                                if let Some(cycle) = self.build_cycle(&layers, hop - 1, label_id, edge.id(), weight) {
+                                    cycles.push(cycle);
+                                }
+                            }
+                            continue;
+                        }
+
+                        // Only walks that can still close within their hop limit are worth keeping
+                        let hop_limit = hop_limit.min(target_limit);
+                        if hop >= hop_limit
+                            || self.walk_contains(&layers, hop - 1, label_id, target, edge.weight().pool_address)
+                        {
+                            continue;
+                        }
+
+                        let bucket = &mut buckets[target.index()];
+                        let label = Label { weight, edge: edge.id(), parent: label_id, hop_limit };
+                        if bucket.len() < LABELS_PER_NODE {
+                            if bucket.is_empty() {
+                                touched.push(target);
+                            }
+                            bucket.push(next.len());
+                            next.push(label);
+                        } else if let Some(&worst) = bucket.iter()
+                            .max_by(|a, b| next[**a].weight.total_cmp(&next[**b].weight))
+                        {
+                            if weight < next[worst].weight {
+                                next[worst] = label;
+                            }
+                        }
+                    }
+                }
+            }
+
+            if next.is_empty() {
+                break;
+            }
+
+            frontier = touched.into_iter()
+                .map(|n| (n, std::mem::take(&mut buckets[n.index()])))
+                .collect();
+            layers.push(next);
+        }
+
+        trace!(
+            "Bellman-Ford from {:?}: {} layers, {} cycles",
+            start_token, layers.len(), cycles.len()
         );
 
         cycles
     }
-    
-    #[allow(clippy::too_many_arguments)]
-    fn dfs_find_cycles(
+
+    /// Whether the walk ending at `layers[layer][label_id]` already visits
+    /// `node` (as an intermediate token) or uses `pool`
+    fn walk_contains(
         &self,
-        start_node: NodeIndex,
-        current_node: NodeIndex,
-        mut path: Vec<Address>,
-        mut pools: Vec<Address>,
-        mut dexes: Vec<Dex>,
-        mut prices: Vec<f64>,
-        mut fees: Vec<u32>,
-        mut visited: HashSet<NodeIndex>,
-        total_weight: f64,
-        cycles: &mut Vec<ArbitrageCycle>,
-        depth: usize,
-    ) {
-        let current_token = match self.graph.get_token(current_node) {
-            Some(t) => t,
-            None => return,
-        };
-        
-        path.push(current_token);
-        if depth > 1 { visited.insert(current_node); }
-        if depth > self.max_hops { return; }
-        
-        for edge in self.graph.graph.edges(current_node) {
-            let target = edge.target();
-            let edge_data = edge.weight();
-            let new_weight = total_weight + edge_data.weight;
-            
-            if target == start_node && depth >= 2 {
-                let expected_return = (-new_weight).exp();
-                
-                if expected_return > 0.95 {
-                    let mut final_path = path.clone();
-                    final_path.push(self.graph.get_token(start_node).unwrap());
-                    
-                    let mut final_pools = pools.clone();
-                    final_pools.push(edge_data.pool_address);
-                    
-                    let mut final_dexes = dexes.clone();
-                    final_dexes.push(edge_data.dex);
-                    
-                    let mut final_prices = prices.clone();
-                    final_prices.push(edge_data.price);
-                    
-                    let mut final_fees = fees.clone();
-                    final_fees.push(edge_data.fee);
-                    
-                    let cycle = ArbitrageCycle {
-                        path: final_path,
-                        pools: final_pools,
-                        dexes: final_dexes,
-                        total_weight: new_weight,
-                        expected_return,
-                        prices: final_prices,
-                        fees: final_fees,
-                    };
-                    
-                    if cycle.is_valid() {
-                        cycles.push(cycle);
-                    }
-                }
-            } else if !visited.contains(&target) && depth < self.max_hops {
-                let mut new_pools = pools.clone();
-                new_pools.push(edge_data.pool_address);
-                
-                let mut new_dexes = dexes.clone();
-                new_dexes.push(edge_data.dex);
-                
-                let mut new_prices = prices.clone();
-                new_prices.push(edge_data.price);
-                
-                let mut new_fees = fees.clone();
-                new_fees.push(edge_data.fee);
-                
-                self.dfs_find_cycles(
-                    start_node, target,
-                    path.clone(), new_pools, new_dexes, new_prices, new_fees,
-                    visited.clone(), new_weight, cycles, depth + 1,
-                );
+        layers: &[Vec<Label>],
+        layer: usize,
+        label_id: usize,
+        node: NodeIndex,
+        pool: Address,
+    ) -> bool {
+        let graph = &self.graph.graph;
+        let mut layer = layer;
+        let mut id = label_id;
+
+        while layer > 0 {
+            let label = layers[layer][id];
+            let Some((_, target)) = graph.edge_endpoints(label.edge) else {
+                return true;
+            };
+            if target == node || graph[label.edge].pool_address == pool {
+                return true;
             }
+            id = label.parent;
+            layer -= 1;
+        }
+        false
+    }
+
+    /// Reconstruct a cycle from a closing edge and the label it extends
+    fn build_cycle(
+        &self,
+        layers: &[Vec<Label>],
+        layer: usize,
+        label_id: usize,
+        closing_edge: EdgeIndex,
+        total_weight: f64,
+    ) -> Option<ArbitrageCycle> {
+        let graph = &self.graph.graph;
+
+        let mut edges = Vec::with_capacity(layer + 1);
+        edges.push(closing_edge);
+        let mut layer = layer;
+        let mut id = label_id;
+        while layer > 0 {
+            let label = layers[layer][id];
+            edges.push(label.edge);
+            id = label.parent;
+            layer -= 1;
+        }
+        edges.reverse();
+
+        let (first_source, _) = graph.edge_endpoints(edges[0])?;
+        let mut path = Vec::with_capacity(edges.len() + 1);
+        path.push(self.graph.get_token(first_source)?);
+
+        let mut pools = Vec::with_capacity(edges.len());
+        let mut dexes = Vec::with_capacity(edges.len());
+        let mut prices = Vec::with_capacity(edges.len());
+        let mut fees = Vec::with_capacity(edges.len());
+
+        for edge in &edges {
+            let (_, target) = graph.edge_endpoints(*edge)?;
+            let data: &EdgeData = &graph[*edge];
+            path.push(self.graph.get_token(target)?);
+            pools.push(data.pool_address);
+            dexes.push(data.dex);
+            prices.push(data.price);
+            fees.push(data.fee);
         }
+
+        let cycle = ArbitrageCycle {
+            path,
+            pools,
+            dexes,
+            total_weight,
+            expected_return: (-total_weight).exp(),
+            prices,
+            fees,
+        };
+
+        cycle.is_valid().then_some(cycle)
     }
 
     pub fn find_all_cycles(&self, base_tokens: &[Address]) -> Vec<ArbitrageCycle> {
@@ -218,7 +350,7 @@ impl<'a> BoundedBellmanFord<'a> {
     }
 }
 
-fn create_cycle_signature(cycle: &ArbitrageCycle) -> String {
+pub(super) fn create_cycle_signature(cycle: &ArbitrageCycle) -> String {
     let mut pool_strs: Vec<String> = cycle.pools.iter()
         .map(|p| format!("{:?}", p))
         .collect();
@@ -236,3 +368,64 @@ pub fn format_cycle_path(cycle: &ArbitrageCycle, symbols: &HashMap<Address, &str
         .collect::<Vec<_>>()
         .join(" → ")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartographer::test_fixtures::add_edge;
+
+    #[test]
+    fn test_finds_profitable_triangle() {
+        let (a, b, c, d) = (
+            Address::repeat_byte(1), Address::repeat_byte(2),
+            Address::repeat_byte(3), Address::repeat_byte(4),
+        );
+        let mut graph = ArbitrageGraph::new();
+        // a -> b -> c -> a returns 1.02; a <-> d is a plain round trip
+        add_edge(&mut graph, a, b, 0xa1, 2.0);
+        add_edge(&mut graph, b, c, 0xa2, 3.0);
+        add_edge(&mut graph, c, a, 0xa3, 1.02 / 6.0);
+        add_edge(&mut graph, b, a, 0xa1, 0.5);
+        add_edge(&mut graph, a, d, 0xa4, 10.0);
+        add_edge(&mut graph, d, a, 0xa4, 0.099);
+
+        let cycles = BoundedBellmanFord::new(&graph, 4).find_all_cycles(&[a]);
+
+        let best = &cycles[0];
+        assert_eq!(best.path, vec![a, b, c, a]);
+        assert!((best.expected_return - 1.02).abs() < 1e-9);
+        // Round trips through the same pool are never valid cycles
+        assert!(cycles.iter().all(|c| c.is_valid()));
+        assert!(cycles.iter().all(|c| !c.pools.contains(&Address::repeat_byte(0xa4))));
+    }
+
+    #[test]
+    fn test_policy_prunes_during_traversal() {
+        use crate::tokens::{all_tokens, TokenCategory};
+
+        let token = |category: TokenCategory| all_tokens().into_iter()
+            .find(|t| t.category == category)
+            .unwrap()
+            .address;
+        let (weth, meme, defi) = (
+            token(TokenCategory::BaseVolatile),
+            token(TokenCategory::Meme),
+            token(TokenCategory::DeFi),
+        );
+        let mut graph = ArbitrageGraph::new();
+        // weth -> meme -> defi -> weth returns 1.02
+        add_edge(&mut graph, weth, meme, 0xa1, 2.0);
+        add_edge(&mut graph, meme, defi, 0xa2, 3.0);
+        add_edge(&mut graph, defi, weth, 0xa3, 1.02 / 6.0);
+
+        let mut policy = RiskPolicy::default();
+        let cycles = BoundedBellmanFord::new(&graph, 4).with_policy(&policy).find_all_cycles(&[weth]);
+        assert_eq!(cycles.len(), 1);
+
+        // Meme tokens capped at 2 hops: the 3-hop cycle is never completed
+        let meme_rule = policy.rule_for(TokenCategory::Meme);
+        policy.set_rule(TokenCategory::Meme, crate::brain::CategoryRule { max_hops: 2, ..meme_rule });
+        let cycles = BoundedBellmanFord::new(&graph, 4).with_policy(&policy).find_all_cycles(&[weth]);
+        assert!(cycles.is_empty());
+    }
+}
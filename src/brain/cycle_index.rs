@@ -0,0 +1,273 @@
+//! Cycle Index - persistent cycle catalog, re-scored per changed pool
+//!
+//! Enumerating cycles is the expensive part of a scan, but the set of cycles
+//! only changes when the set of pools does. The index keeps every cycle found
+//! (down to a loose return threshold) keyed by pool address. On each block
+//! only the cycles touching pools whose state changed are re-priced from the
+//! graph; everything else is reused as-is.
+
+use alloy_primitives::Address;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::time::Instant;
+use tracing::debug;
+
+use super::{ArbitrageCycle, BoundedBellmanFord, CycleEnumerator, RiskPolicy};
+use crate::cartographer::{ArbitrageGraph, PoolState};
+
+/// Catalog cycles down to this return, so cycles can drift into profit
+/// without a re-enumeration
+const CATALOG_MIN_RETURN: f64 = 0.90;
+
+/// Graphs up to this many edges are enumerated exhaustively; larger ones
+/// fall back to the label-capped Bellman-Ford search
+const EXHAUSTIVE_MAX_EDGES: usize = 5_000;
+
+/// Re-enumerate at least this often even if the pool set is unchanged
+/// (picks up cycles that drifted in from below `CATALOG_MIN_RETURN`)
+const REBUILD_EVERY_REFRESHES: u64 = 100;
+
+/// What a refresh did
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RefreshStats {
+    /// Whether the catalog was rebuilt from scratch
+    pub rebuilt: bool,
+    /// Pools whose state changed since the last refresh
+    pub changed_pools: usize,
+    /// Cycles re-priced from the graph
+    pub rescored_cycles: usize,
+    /// Cycles in the catalog
+    pub total_cycles: usize,
+    /// Wall time of the refresh in microseconds
+    pub elapsed_us: u64,
+}
+
+pub struct CycleIndex {
+    max_hops: usize,
+    cycles: Vec<ArbitrageCycle>,
+    by_pool: HashMap<Address, Vec<usize>>,
+    fingerprints: HashMap<Address, u64>,
+    base_tokens: Vec<Address>,
+    refreshes_since_rebuild: u64,
+    policy: Option<RiskPolicy>,
+}
+
+impl CycleIndex {
+    pub fn new(max_hops: usize) -> Self {
+        Self {
+            max_hops,
+            cycles: Vec::new(),
+            by_pool: HashMap::new(),
+            fingerprints: HashMap::new(),
+            base_tokens: Vec::new(),
+            refreshes_since_rebuild: 0,
+            policy: None,
+        }
+    }
+
+    /// Enumerate only cycles `policy` allows
+    pub fn with_policy(mut self, policy: RiskPolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    pub fn policy(&self) -> Option<&RiskPolicy> {
+        self.policy.as_ref()
+    }
+
+    /// Policy access for per-scan updates (pool depths); takes effect at
+    /// the next rebuild
+    pub fn policy_mut(&mut self) -> Option<&mut RiskPolicy> {
+        self.policy.as_mut()
+    }
+
+    /// All cataloged cycles with their latest scores
+    pub fn cycles(&self) -> &[ArbitrageCycle] {
+        &self.cycles
+    }
+
+    /// Cycles that use `pool`
+    pub fn cycles_for_pool(&self, pool: &Address) -> impl Iterator<Item = &ArbitrageCycle> {
+        self.by_pool.get(pool)
+            .into_iter()
+            .flatten()
+            .map(|&i| &self.cycles[i])
+    }
+
+    /// Bring the catalog up to date with the latest pool states
+    ///
+    /// Rebuilds when the pool set or base tokens changed (or periodically);
+    /// otherwise re-scores only the cycles touching changed pools.
+    pub fn refresh(
+        &mut self,
+        graph: &ArbitrageGraph,
+        pools: &[PoolState],
+        base_tokens: &[Address],
+    ) -> RefreshStats {
+        let start = Instant::now();
+
+        let fingerprints: HashMap<Address, u64> = pools.iter()
+            .map(|p| (p.address, pool_fingerprint(p)))
+            .collect();
+
+        let pool_set_changed = fingerprints.len() != self.fingerprints.len()
+            || fingerprints.keys().any(|a| !self.fingerprints.contains_key(a));
+        let needs_rebuild = pool_set_changed
+            || self.base_tokens != base_tokens
+            || self.refreshes_since_rebuild >= REBUILD_EVERY_REFRESHES;
+
+        let mut stats = if needs_rebuild {
+            self.rebuild(graph, base_tokens);
+            RefreshStats {
+                rebuilt: true,
+                changed_pools: fingerprints.len(),
+                rescored_cycles: self.cycles.len(),
+                ..Default::default()
+            }
+        } else {
+            self.refreshes_since_rebuild += 1;
+
+            let changed: Vec<Address> = fingerprints.iter()
+                .filter(|(addr, fp)| self.fingerprints.get(*addr) != Some(*fp))
+                .map(|(addr, _)| *addr)
+                .collect();
+
+            let affected: HashSet<usize> = changed.iter()
+                .filter_map(|p| self.by_pool.get(p))
+                .flatten()
+                .copied()
+                .collect();
+
+            for &i in &affected {
+                rescore(graph, &mut self.cycles[i]);
+            }
+
+            RefreshStats {
+                rebuilt: false,
+                changed_pools: changed.len(),
+                rescored_cycles: affected.len(),
+                ..Default::default()
+            }
+        };
+
+        self.fingerprints = fingerprints;
+        stats.total_cycles = self.cycles.len();
+        stats.elapsed_us = start.elapsed().as_micros() as u64;
+
+        debug!(
+            "Cycle index: {} ({} pools changed, {} / {} cycles re-scored in {}µs)",
+            if stats.rebuilt { "rebuilt" } else { "incremental" },
+            stats.changed_pools, stats.rescored_cycles, stats.total_cycles, stats.elapsed_us
+        );
+
+        stats
+    }
+
+    fn rebuild(&mut self, graph: &ArbitrageGraph, base_tokens: &[Address]) {
+        self.cycles = if graph.graph.edge_count() <= EXHAUSTIVE_MAX_EDGES {
+            let mut search = CycleEnumerator::new(graph, self.max_hops)
+                .with_min_return(CATALOG_MIN_RETURN);
+            if let Some(policy) = &self.policy {
+                search = search.with_policy(policy);
+            }
+            search.find_all_cycles(base_tokens)
+        } else {
+            let mut search = BoundedBellmanFord::new(graph, self.max_hops)
+                .with_min_return(CATALOG_MIN_RETURN);
+            if let Some(policy) = &self.policy {
+                search = search.with_policy(policy);
+            }
+            search.find_all_cycles(base_tokens)
+        };
+
+        self.by_pool.clear();
+        for (i, cycle) in self.cycles.iter().enumerate() {
+            for pool in &cycle.pools {
+                self.by_pool.entry(*pool).or_default().push(i);
+            }
+        }
+
+        self.base_tokens = base_tokens.to_vec();
+        self.refreshes_since_rebuild = 0;
+    }
+}
+
+/// Hash of the pool fields that move its price
+fn pool_fingerprint(pool: &PoolState) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    pool.sqrt_price_x96.hash(&mut hasher);
+    pool.tick.hash(&mut hasher);
+    pool.liquidity.hash(&mut hasher);
+    pool.reserve1.hash(&mut hasher);
+    pool.fee.hash(&mut hasher);
+    pool.weight0.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Re-price a cycle from the current graph edges
+///
+/// A hop whose pool no longer has an edge (e.g. invalid price this block)
+/// scores the cycle as dead until the next rebuild.
+fn rescore(graph: &ArbitrageGraph, cycle: &mut ArbitrageCycle) {
+    let mut total_weight = 0.0;
+
+    for i in 0..cycle.pools.len() {
+        let edge = graph.parallel_edges(cycle.path[i], cycle.path[i + 1])
+            .into_iter()
+            .find(|e| e.pool_address == cycle.pools[i]);
+
+        match edge {
+            Some(edge) => {
+                total_weight += edge.weight;
+                cycle.prices[i] = edge.price;
+                cycle.fees[i] = edge.fee;
+            }
+            None => {
+                total_weight = f64::INFINITY;
+                break;
+            }
+        }
+    }
+
+    cycle.total_weight = total_weight;
+    cycle.expected_return = (-total_weight).exp();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartographer::test_fixtures::v2_pool;
+
+    #[test]
+    fn test_incremental_rescore_only_touches_changed_pools() {
+        let (a, b, c) = (Address::repeat_byte(1), Address::repeat_byte(2), Address::repeat_byte(3));
+        let mut pools = vec![
+            v2_pool(0xa1, a, b, 1_000_000, 1_000_000),
+            v2_pool(0xa2, b, c, 1_000_000, 1_000_000),
+            v2_pool(0xa3, c, a, 1_000_000, 1_000_000),
+        ];
+
+        let mut index = CycleIndex::new(3);
+        let graph = ArbitrageGraph::from_pools(&pools);
+        let first = index.refresh(&graph, &pools, &[a]);
+        assert!(first.rebuilt);
+        assert!(first.total_cycles > 0);
+
+        // Nothing moved: nothing re-scored
+        let idle = index.refresh(&graph, &pools, &[a]);
+        assert!(!idle.rebuilt);
+        assert_eq!(idle.rescored_cycles, 0);
+
+        // One pool moved: only its cycles are re-scored, with the new price
+        let before: Vec<f64> = index.cycles().iter().map(|c| c.expected_return).collect();
+        pools[0].reserve1 = 1_100_000;
+        let graph = ArbitrageGraph::from_pools(&pools);
+        let moved = index.refresh(&graph, &pools, &[a]);
+        assert!(!moved.rebuilt);
+        assert_eq!(moved.changed_pools, 1);
+        assert_eq!(moved.rescored_cycles, index.cycles_for_pool(&pools[0].address).count());
+        let after: Vec<f64> = index.cycles().iter().map(|c| c.expected_return).collect();
+        assert_ne!(before, after);
+    }
+}
@@ -0,0 +1,302 @@
+//! Exhaustive Cycle Enumerator - allocation-free backtracking DFS
+//!
+//! Complements the hop-bounded Bellman-Ford: where that keeps only the best
+//! few walks per token, this lists every simple cycle above a return
+//! threshold. It backtracks over reusable stacks with a bitset of visited
+//! tokens, prunes a branch as soon as no way of closing it can reach the
+//! threshold, and searches the base tokens on parallel threads.
+
+use alloy_primitives::Address;
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use std::collections::HashSet;
+use tracing::debug;
+
+use super::bellman_ford::{create_cycle_signature, MIN_REPORTED_RETURN};
+use super::{ArbitrageCycle, RiskPolicy};
+use crate::cartographer::ArbitrageGraph;
+
+/// Reusable per-thread search state
+struct Scratch {
+    visited: Vec<u64>,
+    edges: Vec<EdgeIndex>,
+    pools: Vec<Address>,
+    /// Tightest hop limit of the tokens on the path so far
+    limits: Vec<usize>,
+    /// Cheapest edge from each token back to the current start token
+    close_weight: Vec<f64>,
+    /// Cheapest edge back to the start token from anywhere
+    min_close: f64,
+}
+
+impl Scratch {
+    fn new(node_count: usize, max_hops: usize) -> Self {
+        Self {
+            visited: vec![0; node_count.div_ceil(64)],
+            edges: Vec::with_capacity(max_hops),
+            pools: Vec::with_capacity(max_hops),
+            limits: Vec::with_capacity(max_hops + 1),
+            close_weight: vec![f64::INFINITY; node_count],
+            min_close: f64::INFINITY,
+        }
+    }
+
+    fn is_visited(&self, node: NodeIndex) -> bool {
+        let i = node.index();
+        self.visited[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    fn set_visited(&mut self, node: NodeIndex, on: bool) {
+        let i = node.index();
+        if on {
+            self.visited[i / 64] |= 1 << (i % 64);
+        } else {
+            self.visited[i / 64] &= !(1 << (i % 64));
+        }
+    }
+}
+
+pub struct CycleEnumerator<'a> {
+    graph: &'a ArbitrageGraph,
+    max_hops: usize,
+    /// Largest total weight a reported cycle may have (-ln(min_return))
+    max_weight: f64,
+    /// Most negative edge weight in the graph (0 if none), for bounding
+    min_edge_weight: f64,
+    policy: Option<&'a RiskPolicy>,
+}
+
+impl<'a> CycleEnumerator<'a> {
+    pub fn new(graph: &'a ArbitrageGraph, max_hops: usize) -> Self {
+        let min_edge_weight = graph.graph.edge_weights()
+            .map(|e| e.weight)
+            .filter(|w| w.is_finite())
+            .fold(0.0_f64, f64::min);
+
+        Self {
+            graph,
+            max_hops,
+            max_weight: -MIN_REPORTED_RETURN.ln(),
+            min_edge_weight,
+            policy: None,
+        }
+    }
+
+    /// Report cycles down to this return
+    pub fn with_min_return(mut self, min_return: f64) -> Self {
+        self.max_weight = -min_return.ln();
+        self
+    }
+
+    /// Enforce a risk policy during traversal
+    pub fn with_policy(mut self, policy: &'a RiskPolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Every simple cycle through one of `base_tokens`, deduplicated and
+    /// sorted by expected return (base tokens are searched in parallel)
+    pub fn find_all_cycles(&self, base_tokens: &[Address]) -> Vec<ArbitrageCycle> {
+        let threads = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .min(base_tokens.len().max(1));
+        let chunk_size = base_tokens.len().div_ceil(threads).max(1);
+
+        let per_thread: Vec<Vec<ArbitrageCycle>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = base_tokens.chunks(chunk_size)
+                .map(|chunk| scope.spawn(move || {
+                    let mut scratch = Scratch::new(self.graph.node_count(), self.max_hops);
+                    let mut cycles = Vec::new();
+                    for &token in chunk {
+                        self.find_cycles_from(token, &mut scratch, &mut cycles);
+                    }
+                    cycles
+                }))
+                .collect();
+
+            // A panicking search thread fails the scan rather than silently
+            // dropping its base tokens' cycles
+            handles.into_iter()
+                .map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+                .collect()
+        });
+
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut all_cycles: Vec<ArbitrageCycle> = per_thread.into_iter()
+            .flatten()
+            .filter(|c| seen.insert(create_cycle_signature(c)))
+            .collect();
+
+        all_cycles.sort_by(|a, b| {
+            b.expected_return.partial_cmp(&a.expected_return)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        debug!("Enumerated {} cycles from {} base tokens", all_cycles.len(), base_tokens.len());
+        all_cycles
+    }
+
+    fn find_cycles_from(&self, start_token: Address, scratch: &mut Scratch, out: &mut Vec<ArbitrageCycle>) {
+        let Some(start) = self.graph.get_node(start_token) else {
+            return;
+        };
+        if self.max_hops < 2 || self.policy.is_some_and(|p| !p.allows_token(&start_token)) {
+            return;
+        }
+
+        // Closing-edge bound for this start token
+        scratch.close_weight.fill(f64::INFINITY);
+        for edge in self.graph.graph.edges_directed(start, Direction::Incoming) {
+            let w = &mut scratch.close_weight[edge.source().index()];
+            *w = w.min(edge.weight().weight);
+        }
+        scratch.min_close = scratch.close_weight.iter().copied().fold(f64::INFINITY, f64::min);
+        if !scratch.min_close.is_finite() {
+            return;
+        }
+
+        scratch.limits.clear();
+        scratch.limits.push(self.policy.map_or(self.max_hops, |p| p.max_hops(&start_token).min(self.max_hops)));
+        self.dfs(start, start, 0.0, scratch, out);
+    }
+
+    fn dfs(
+        &self,
+        start: NodeIndex,
+        node: NodeIndex,
+        weight: f64,
+        scratch: &mut Scratch,
+        out: &mut Vec<ArbitrageCycle>,
+    ) {
+        let graph = &self.graph.graph;
+        let depth = scratch.edges.len();
+        let limit = scratch.limits[depth];
+
+        for edge in graph.edges(node) {
+            let target = edge.target();
+            let data = edge.weight();
+            let new_weight = weight + data.weight;
+            if !new_weight.is_finite() || scratch.pools.contains(&data.pool_address) {
+                continue;
+            }
+            if self.policy.is_some_and(|p| !p.allows_hop(&graph[node], &graph[target], &data.pool_address)) {
+                continue;
+            }
+
+            if target == start {
+                if depth >= 1 && depth < limit && new_weight <= self.max_weight {
+                    scratch.edges.push(edge.id());
+                    if let Some(cycle) = self.build_cycle(&scratch.edges, new_weight) {
+                        out.push(cycle);
+                    }
+                    scratch.edges.pop();
+                }
+                continue;
+            }
+
+            // Need room for this edge plus a closing edge
+            let new_limit = self.policy.map_or(limit, |p| limit.min(p.max_hops(&graph[target])));
+            if depth + 2 > new_limit || scratch.is_visited(target) {
+                continue;
+            }
+
+            // Best completion from `target`: close directly, or take the
+            // most negative edges for the remaining hops before closing
+            let remaining = new_limit - depth - 1;
+            let best_completion = if remaining == 1 {
+                scratch.close_weight[target.index()]
+            } else {
+                scratch.close_weight[target.index()]
+                    .min((remaining - 1) as f64 * self.min_edge_weight + scratch.min_close)
+            };
+            if new_weight + best_completion > self.max_weight {
+                continue;
+            }
+
+            scratch.edges.push(edge.id());
+            scratch.pools.push(data.pool_address);
+            scratch.limits.push(new_limit);
+            scratch.set_visited(target, true);
+
+            self.dfs(start, target, new_weight, scratch, out);
+
+            scratch.set_visited(target, false);
+            scratch.limits.pop();
+            scratch.pools.pop();
+            scratch.edges.pop();
+        }
+    }
+
+    fn build_cycle(&self, edges: &[EdgeIndex], total_weight: f64) -> Option<ArbitrageCycle> {
+        let graph = &self.graph.graph;
+        let (first, _) = graph.edge_endpoints(edges[0])?;
+
+        let mut path = Vec::with_capacity(edges.len() + 1);
+        path.push(self.graph.get_token(first)?);
+        let mut pools = Vec::with_capacity(edges.len());
+        let mut dexes = Vec::with_capacity(edges.len());
+        let mut prices = Vec::with_capacity(edges.len());
+        let mut fees = Vec::with_capacity(edges.len());
+
+        for &edge in edges {
+            let (_, target) = graph.edge_endpoints(edge)?;
+            let data = &graph[edge];
+            path.push(self.graph.get_token(target)?);
+            pools.push(data.pool_address);
+            dexes.push(data.dex);
+            prices.push(data.price);
+            fees.push(data.fee);
+        }
+
+        let cycle = ArbitrageCycle {
+            path,
+            pools,
+            dexes,
+            total_weight,
+            expected_return: (-total_weight).exp(),
+            prices,
+            fees,
+        };
+
+        cycle.is_valid().then_some(cycle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::brain::BoundedBellmanFord;
+    use crate::cartographer::test_fixtures::add_edge;
+
+    #[test]
+    fn test_enumerates_every_cycle_above_threshold() {
+        let (a, b, c, d) = (
+            Address::repeat_byte(1), Address::repeat_byte(2),
+            Address::repeat_byte(3), Address::repeat_byte(4),
+        );
+        let mut graph = ArbitrageGraph::new();
+        // a -> b -> c -> a returns 1.02, a -> b -> d -> a returns 0.97,
+        // a -> c -> d -> a returns 0.5 and must be pruned
+        add_edge(&mut graph, a, b, 0xa1, 2.0);
+        add_edge(&mut graph, b, c, 0xa2, 3.0);
+        add_edge(&mut graph, c, a, 0xa3, 1.02 / 6.0);
+        add_edge(&mut graph, b, d, 0xa4, 4.0);
+        add_edge(&mut graph, d, a, 0xa5, 0.97 / 8.0);
+        add_edge(&mut graph, a, c, 0xa6, 0.5 / (0.97 / 8.0));
+        add_edge(&mut graph, c, d, 0xa7, 1.0);
+
+        let cycles = CycleEnumerator::new(&graph, 4)
+            .with_min_return(0.95)
+            .find_all_cycles(&[a, b]);
+
+        let paths: Vec<Vec<Address>> = cycles.iter().map(|c| c.path.clone()).collect();
+        assert_eq!(paths, vec![vec![a, b, c, a], vec![a, b, d, a]]);
+        assert!((cycles[0].expected_return - 1.02).abs() < 1e-9);
+
+        // Agrees with Bellman-Ford on the profitable cycle
+        let bf = BoundedBellmanFord::new(&graph, 4).find_all_cycles(&[a]);
+        assert_eq!(bf[0].path, cycles[0].path);
+    }
+}
@@ -5,7 +5,7 @@ use std::collections::HashMap;
 use tracing::{debug, trace};  // No info!
 
 use super::ArbitrageCycle;
-use crate::cartographer::Dex;
+use crate::swap_gas::SwapGasModel;
 
 #[derive(Debug, Clone)]
 pub struct ProfitAnalysis {
@@ -34,10 +34,7 @@ impl ProfitAnalysis {
 
 pub struct ProfitFilter {
     min_profit_usd: f64,
-    gas_per_swap_v3: u64,
-    gas_per_swap_v2: u64,
-    gas_per_swap_balancer: u64,
-    gas_per_swap_curve: u64,
+    gas_model: SwapGasModel,
     gas_price_gwei: f64,
     eth_price_usd: f64,
     default_input_usd: f64,
@@ -49,10 +46,7 @@ impl ProfitFilter {
     pub fn new(min_profit_usd: f64) -> Self {
         Self {
             min_profit_usd,
-            gas_per_swap_v3: 150_000,
-            gas_per_swap_v2: 100_000,
-            gas_per_swap_balancer: 120_000,
-            gas_per_swap_curve: 200_000,
+            gas_model: SwapGasModel::default(),
             gas_price_gwei: 0.5,
             eth_price_usd: 3000.0,
             default_input_usd: 10_000.0,
@@ -73,18 +67,14 @@ impl ProfitFilter {
         self.default_input_usd = input_usd;
     }
 
+    /// Use a (shared, calibrated) gas model instead of the defaults
+    pub fn set_gas_model(&mut self, gas_model: SwapGasModel) {
+        self.gas_model = gas_model;
+    }
+
     fn calculate_gas_cost(&self, cycle: &ArbitrageCycle) -> f64 {
-        let mut total_gas_units: u64 = 0;
-        for dex in &cycle.dexes {
-            let gas = match dex {
-                Dex::UniswapV3 | Dex::SushiswapV3 | Dex::PancakeSwapV3 => self.gas_per_swap_v3,
-                Dex::UniswapV2 | Dex::SushiswapV2 => self.gas_per_swap_v2,
-                Dex::BalancerV2 => self.gas_per_swap_balancer,
-                Dex::Curve => self.gas_per_swap_curve,
-            };
-            total_gas_units += gas;
-        }
-        total_gas_units += 50_000;
+        // Tick crossings aren't known before simulation
+        let total_gas_units = self.gas_model.cycle_gas(cycle, &[]);
         let gas_cost_eth = (total_gas_units as f64) * self.gas_price_gwei * 1e-9;
         gas_cost_eth * self.eth_price_usd
     }
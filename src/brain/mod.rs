@@ -1,5 +1,13 @@
 mod bellman_ford;
+mod cycle_index;
+mod enumerator;
 mod filter;
+mod risk;
+mod selection;
 
-pub use bellman_ford::{BoundedBellmanFord, ArbitrageCycle, format_cycle_path};
+pub use bellman_ford::{BoundedBellmanFord, ArbitrageCycle, format_cycle_path, MIN_REPORTED_RETURN};
+pub use cycle_index::{CycleIndex, RefreshStats};
+pub use enumerator::CycleEnumerator;
 pub use filter::{ProfitFilter, ProfitAnalysis};
+pub use risk::{CategoryRule, RiskPolicy};
+pub use selection::{CandidateSelector, best_disjoint_set};
@@ -0,0 +1,252 @@
+//! Risk Policy - per-category rules applied during cycle search
+//!
+//! Every token the search may touch is classified by its `TokenCategory`
+//! (tokens we don't know fall under a conservative default). Each category
+//! has a rule: whether it may be traded at all, the longest cycle it may
+//! appear in, the largest notional a cycle through it may carry, and the
+//! shallowest pool it may be traded through. The config's token whitelist
+//! and blacklist are enforced here as well.
+
+use alloy_primitives::Address;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use tracing::{debug, info};
+
+use super::ArbitrageCycle;
+use crate::cartographer::{PoolState, PoolType};
+use crate::config::Config;
+use crate::tokens::{all_tokens, TokenCategory};
+
+/// Limits for tokens of one category
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CategoryRule {
+    /// Whether tokens of this category may appear in a cycle
+    pub allowed: bool,
+
+    /// Longest cycle (in hops) a token of this category may appear in
+    pub max_hops: usize,
+
+    /// Largest input (USD) for a cycle through this category
+    pub max_notional_usd: f64,
+
+    /// Pools trading this category must be at least this deep (USD)
+    pub min_pool_depth_usd: f64,
+}
+
+impl CategoryRule {
+    const fn new(max_hops: usize, max_notional_usd: f64, min_pool_depth_usd: f64) -> Self {
+        Self { allowed: true, max_hops, max_notional_usd, min_pool_depth_usd }
+    }
+}
+
+/// Built-in rule for a category
+fn default_rule(category: TokenCategory) -> CategoryRule {
+    match category {
+        TokenCategory::BaseStable | TokenCategory::BaseVolatile => {
+            CategoryRule::new(6, f64::INFINITY, 0.0)
+        }
+        TokenCategory::YieldBearing
+        | TokenCategory::AlgoStable
+        | TokenCategory::BasketBacked
+        | TokenCategory::LiquidStaking
+        | TokenCategory::DeFi
+        | TokenCategory::Governance => CategoryRule::new(4, 250_000.0, 100_000.0),
+        TokenCategory::Restaking | TokenCategory::RWA => CategoryRule::new(3, 100_000.0, 250_000.0),
+        TokenCategory::AICompute | TokenCategory::Gaming => CategoryRule::new(3, 50_000.0, 250_000.0),
+        TokenCategory::Meme => CategoryRule::new(3, 25_000.0, 500_000.0),
+    }
+}
+
+/// Rule for tokens outside the known token list
+const UNKNOWN_TOKEN_RULE: CategoryRule = CategoryRule::new(3, 25_000.0, 250_000.0);
+
+#[derive(Debug, Clone)]
+pub struct RiskPolicy {
+    rules: HashMap<TokenCategory, CategoryRule>,
+    unknown_rule: CategoryRule,
+    categories: HashMap<Address, TokenCategory>,
+    whitelist: HashSet<Address>,
+    blacklist: HashSet<Address>,
+    pool_depth_usd: HashMap<Address, f64>,
+}
+
+impl Default for RiskPolicy {
+    fn default() -> Self {
+        Self {
+            rules: HashMap::new(),
+            unknown_rule: UNKNOWN_TOKEN_RULE,
+            categories: all_tokens().into_iter().map(|t| (t.address, t.category)).collect(),
+            whitelist: HashSet::new(),
+            blacklist: HashSet::new(),
+            pool_depth_usd: HashMap::new(),
+        }
+    }
+}
+
+impl RiskPolicy {
+    /// Policy from config: token lists, denied categories and the optional
+    /// rule overrides file (JSON map of category name to `CategoryRule`)
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let parse = |list: &[String]| -> HashSet<Address> {
+            list.iter()
+                .filter_map(|s| Address::from_str(s.trim()).ok())
+                .collect()
+        };
+
+        let mut policy = Self {
+            whitelist: parse(&config.whitelisted_tokens),
+            blacklist: parse(&config.blacklisted_tokens),
+            ..Default::default()
+        };
+
+        if let Some(path) = &config.risk_policy_path {
+            let overrides: HashMap<String, CategoryRule> =
+                serde_json::from_str(&std::fs::read_to_string(path)?)?;
+            for (name, rule) in overrides {
+                match TokenCategory::from_name(&name) {
+                    Some(category) => { policy.rules.insert(category, rule); }
+                    None if name.eq_ignore_ascii_case("unknown") => policy.unknown_rule = rule,
+                    None => return Err(eyre::eyre!("Unknown token category in risk policy: {}", name)),
+                }
+            }
+            info!("Loaded risk policy overrides from {}", path);
+        }
+
+        for name in &config.risk_denied_categories {
+            let category = TokenCategory::from_name(name)
+                .ok_or_else(|| eyre::eyre!("Unknown token category in RISK_DENIED_CATEGORIES: {}", name))?;
+            policy.set_rule(category, CategoryRule { allowed: false, ..policy.rule_for(category) });
+        }
+
+        Ok(policy)
+    }
+
+    pub fn set_rule(&mut self, category: TokenCategory, rule: CategoryRule) {
+        self.rules.insert(category, rule);
+    }
+
+    pub fn rule_for(&self, category: TokenCategory) -> CategoryRule {
+        self.rules.get(&category).copied().unwrap_or_else(|| default_rule(category))
+    }
+
+    /// Rule governing `token`
+    pub fn token_rule(&self, token: &Address) -> CategoryRule {
+        self.categories.get(token)
+            .map(|c| self.rule_for(*c))
+            .unwrap_or(self.unknown_rule)
+    }
+
+    /// Whether `token` may appear in a cycle at all
+    pub fn allows_token(&self, token: &Address) -> bool {
+        if self.blacklist.contains(token) {
+            return false;
+        }
+        if !self.whitelist.is_empty() && !self.whitelist.contains(token) {
+            return false;
+        }
+        self.token_rule(token).allowed
+    }
+
+    /// Whether a hop `token_in` → `token_out` through `pool` may be taken
+    /// (pools of unknown depth pass)
+    pub fn allows_hop(&self, token_in: &Address, token_out: &Address, pool: &Address) -> bool {
+        if !self.allows_token(token_out) {
+            return false;
+        }
+        let Some(depth) = self.pool_depth_usd.get(pool) else {
+            return true;
+        };
+        let required = self.token_rule(token_in).min_pool_depth_usd
+            .max(self.token_rule(token_out).min_pool_depth_usd);
+        *depth >= required
+    }
+
+    /// Longest cycle `token` may appear in
+    pub fn max_hops(&self, token: &Address) -> usize {
+        self.token_rule(token).max_hops
+    }
+
+    /// Largest input (USD) allowed for `cycle`
+    pub fn max_notional_usd(&self, cycle: &ArbitrageCycle) -> f64 {
+        cycle.path.iter()
+            .map(|t| self.token_rule(t).max_notional_usd)
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    /// Whether a finished cycle satisfies the policy
+    pub fn allows_cycle(&self, cycle: &ArbitrageCycle) -> bool {
+        let Some(start) = cycle.path.first() else {
+            return false;
+        };
+        self.allows_token(start)
+            && cycle.path.iter().all(|t| cycle.hop_count() <= self.max_hops(t))
+            && cycle.pools.iter().enumerate()
+                .all(|(i, pool)| self.allows_hop(&cycle.path[i], &cycle.path[i + 1], pool))
+    }
+
+    /// Record each pool's depth (USD value of both sides) for the depth rules
+    pub fn set_pool_depths(&mut self, pools: &[PoolState], prices_usd: &HashMap<Address, f64>) {
+        self.pool_depth_usd = pools.iter()
+            .filter_map(|p| pool_depth_usd(p, prices_usd).map(|d| (p.address, d)))
+            .collect();
+        debug!("Risk policy: depth known for {} / {} pools", self.pool_depth_usd.len(), pools.len());
+    }
+}
+
+/// USD value held by a pool (in-range virtual reserves for V3). With one
+/// side unpriced the other counts double; `None` if neither is priced.
+fn pool_depth_usd(pool: &PoolState, prices_usd: &HashMap<Address, f64>) -> Option<f64> {
+    let scale0 = 10_f64.powi(pool.token0_decimals as i32);
+    let scale1 = 10_f64.powi(pool.token1_decimals as i32);
+
+    let (amount0, amount1) = match pool.pool_type {
+        PoolType::V3 => {
+            let sqrt_price = pool.sqrt_price_x96.to::<u128>() as f64 / 2_f64.powi(96);
+            if sqrt_price == 0.0 {
+                return None;
+            }
+            let liquidity = pool.liquidity as f64;
+            (liquidity / sqrt_price / scale0, liquidity * sqrt_price / scale1)
+        }
+        _ => (pool.liquidity as f64 / scale0, pool.reserve1 as f64 / scale1),
+    };
+
+    match (prices_usd.get(&pool.token0), prices_usd.get(&pool.token1)) {
+        (Some(p0), Some(p1)) => Some(amount0 * p0 + amount1 * p1),
+        (Some(p0), None) => Some(2.0 * amount0 * p0),
+        (None, Some(p1)) => Some(2.0 * amount1 * p1),
+        (None, None) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lists_and_categories() {
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let meme = all_tokens().into_iter()
+            .find(|t| t.category == TokenCategory::Meme)
+            .unwrap()
+            .address;
+        let unknown = Address::repeat_byte(0x42);
+
+        let mut policy = RiskPolicy::default();
+        assert!(policy.allows_token(&weth) && policy.allows_token(&meme));
+        assert_eq!(policy.max_hops(&meme), 3);
+        assert_eq!(policy.token_rule(&unknown), UNKNOWN_TOKEN_RULE);
+
+        policy.set_rule(TokenCategory::Meme, CategoryRule { allowed: false, ..default_rule(TokenCategory::Meme) });
+        assert!(!policy.allows_token(&meme));
+
+        policy.blacklist.insert(weth);
+        assert!(!policy.allows_token(&weth));
+
+        policy.whitelist.insert(unknown);
+        assert!(policy.allows_token(&unknown));
+        assert!(!policy.allows_token(&Address::repeat_byte(0x43)));
+    }
+}
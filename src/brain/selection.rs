@@ -0,0 +1,194 @@
+//! Candidate Selection - diversified top-K and pool-disjoint execution sets
+//!
+//! A single mispriced pool shows up in dozens of cycles, so the top of the
+//! ranking is usually one opportunity repeated. Selection caps how many
+//! candidates may share a pool before simulation, and after simulation picks
+//! the most profitable set of cycles that touch no pool twice (and so can
+//! all land in the same block without trading against each other).
+
+use alloy_primitives::Address;
+use std::collections::{HashMap, HashSet};
+
+use super::ArbitrageCycle;
+
+/// Above this many profitable cycles the disjoint set is chosen greedily
+const EXACT_DISJOINT_MAX: usize = 24;
+
+#[derive(Debug, Clone)]
+pub struct CandidateSelector {
+    /// Number of candidates to simulate
+    pub top_k: usize,
+
+    /// Maximum candidates that may use the same pool
+    pub max_per_pool: usize,
+}
+
+impl Default for CandidateSelector {
+    fn default() -> Self {
+        Self {
+            top_k: 5,
+            max_per_pool: 2,
+        }
+    }
+}
+
+impl CandidateSelector {
+    pub fn new(top_k: usize, max_per_pool: usize) -> Self {
+        Self {
+            top_k,
+            max_per_pool: max_per_pool.max(1),
+        }
+    }
+
+    /// The best `top_k` cycles (in the given order) with no pool used by
+    /// more than `max_per_pool` of them
+    pub fn select(&self, ranked: &[ArbitrageCycle]) -> Vec<ArbitrageCycle> {
+        let mut pool_uses: HashMap<Address, usize> = HashMap::new();
+        let mut selected = Vec::with_capacity(self.top_k);
+
+        for cycle in ranked {
+            if selected.len() >= self.top_k {
+                break;
+            }
+            if cycle.pools.iter().any(|p| pool_uses.get(p).copied().unwrap_or(0) >= self.max_per_pool) {
+                continue;
+            }
+            for pool in &cycle.pools {
+                *pool_uses.entry(*pool).or_insert(0) += 1;
+            }
+            selected.push(cycle.clone());
+        }
+
+        selected
+    }
+}
+
+/// Indices of the maximum-profit subset of `candidates` (pools, profit)
+/// in which no two share a pool, in descending profit order
+pub fn best_disjoint_set(candidates: &[(&[Address], f64)]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..candidates.len())
+        .filter(|&i| candidates[i].1 > 0.0)
+        .collect();
+    order.sort_by(|&a, &b| {
+        candidates[b].1.partial_cmp(&candidates[a].1)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    if order.len() > EXACT_DISJOINT_MAX {
+        return greedy_disjoint(candidates, &order);
+    }
+
+    // Branch and bound: include / exclude each candidate in profit order,
+    // pruning when even every remaining profit can't beat the best found
+    let suffix: Vec<f64> = {
+        let mut acc = 0.0;
+        let mut sums: Vec<f64> = order.iter().rev().map(|&i| { acc += candidates[i].1; acc }).collect();
+        sums.reverse();
+        sums.push(0.0);
+        sums
+    };
+
+    struct Search<'a> {
+        candidates: &'a [(&'a [Address], f64)],
+        order: &'a [usize],
+        suffix: &'a [f64],
+        used: HashSet<Address>,
+        chosen: Vec<usize>,
+        best: (f64, Vec<usize>),
+    }
+
+    impl Search<'_> {
+        fn run(&mut self, pos: usize, profit: f64) {
+            if profit > self.best.0 {
+                self.best = (profit, self.chosen.clone());
+            }
+            if pos == self.order.len() || profit + self.suffix[pos] <= self.best.0 {
+                return;
+            }
+
+            let i = self.order[pos];
+            let (pools, gain) = self.candidates[i];
+            if pools.iter().all(|p| !self.used.contains(p)) {
+                self.used.extend(pools.iter().copied());
+                self.chosen.push(i);
+                self.run(pos + 1, profit + gain);
+                self.chosen.pop();
+                for p in pools {
+                    self.used.remove(p);
+                }
+            }
+            self.run(pos + 1, profit);
+        }
+    }
+
+    let mut search = Search {
+        candidates,
+        order: &order,
+        suffix: &suffix,
+        used: HashSet::new(),
+        chosen: Vec::new(),
+        best: (0.0, Vec::new()),
+    };
+    search.run(0, 0.0);
+    search.best.1
+}
+
+fn greedy_disjoint(candidates: &[(&[Address], f64)], order: &[usize]) -> Vec<usize> {
+    let mut used: HashSet<Address> = HashSet::new();
+    order.iter()
+        .copied()
+        .filter(|&i| {
+            let pools = candidates[i].0;
+            if pools.iter().any(|p| used.contains(p)) {
+                return false;
+            }
+            used.extend(pools.iter().copied());
+            true
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartographer::Dex;
+
+    fn cycle(pools: &[u8], expected_return: f64) -> ArbitrageCycle {
+        let token = |i: usize| Address::repeat_byte(0x10 + i as u8);
+        ArbitrageCycle {
+            path: (0..=pools.len()).map(|i| token(i % pools.len())).collect(),
+            pools: pools.iter().map(|p| Address::repeat_byte(*p)).collect(),
+            dexes: vec![Dex::UniswapV2; pools.len()],
+            total_weight: -expected_return.ln(),
+            expected_return,
+            prices: vec![1.0; pools.len()],
+            fees: vec![3000; pools.len()],
+        }
+    }
+
+    #[test]
+    fn test_select_caps_cycles_per_pool() {
+        // Four cycles through pool 1, then an independent one
+        let ranked = vec![
+            cycle(&[1, 2, 3], 1.05),
+            cycle(&[1, 4, 5], 1.04),
+            cycle(&[1, 6, 7], 1.03),
+            cycle(&[1, 8, 9], 1.02),
+            cycle(&[10, 11, 12], 1.01),
+        ];
+
+        let selected = CandidateSelector::new(3, 2).select(&ranked);
+        let returns: Vec<f64> = selected.iter().map(|c| c.expected_return).collect();
+        assert_eq!(returns, vec![1.05, 1.04, 1.01]);
+    }
+
+    #[test]
+    fn test_disjoint_set_beats_greedy() {
+        let pools = |ids: &[u8]| ids.iter().map(|p| Address::repeat_byte(*p)).collect::<Vec<_>>();
+        let (a, b, c) = (pools(&[1, 2]), pools(&[2, 3]), pools(&[1, 4]));
+        // Greedy takes `a` (10) alone; `b` + `c` (7 + 6) is better
+        let candidates: Vec<(&[Address], f64)> = vec![(&a, 10.0), (&b, 7.0), (&c, 6.0)];
+
+        assert_eq!(best_disjoint_set(&candidates), vec![1, 2]);
+    }
+}
@@ -79,7 +79,7 @@ const MAX_CALLS_PER_BATCH: usize = 100;
 // TYPES
 // ============================================
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum Dex { UniswapV3, UniswapV2, SushiswapV3, SushiswapV2, PancakeSwapV3, BalancerV2, Curve }
 
 impl std::fmt::Display for Dex {
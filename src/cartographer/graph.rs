@@ -3,7 +3,7 @@
 use alloy_primitives::Address;
 use petgraph::graph::{DiGraph, NodeIndex};
 use petgraph::visit::EdgeRef;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use tracing::{debug, trace, warn};
 
 use super::{Dex, PoolState, PoolType};
@@ -66,7 +66,69 @@ impl ArbitrageGraph {
         graph
     }
 
+    /// Build a graph whose edge weights are the effective rate for a
+    /// `reference_usd` trade instead of the spot price
+    ///
+    /// Token USD prices are propagated through the spot graph from `anchors`;
+    /// directions whose input token can't be priced keep the spot weight.
+    pub fn from_pools_at_size(pools: &[PoolState], reference_usd: f64, anchors: &[(Address, f64)]) -> Self {
+        let prices = Self::from_pools(pools).token_prices_usd(anchors);
+        let reference = |token: &Address| prices.get(token).map(|p| reference_usd / p);
+
+        let mut graph = Self::new();
+        for pool in pools {
+            graph.add_pool_at(pool, reference(&pool.token0), reference(&pool.token1));
+        }
+
+        debug!(
+            "Graph @ ${:.0}: {} nodes, {} edges ({} / {} tokens priced)",
+            reference_usd,
+            graph.graph.node_count(),
+            graph.graph.edge_count(),
+            prices.len(),
+            graph.graph.node_count()
+        );
+
+        graph
+    }
+
+    /// USD price of every token reachable from `anchors` (token, usd) over
+    /// spot edges
+    pub fn token_prices_usd(&self, anchors: &[(Address, f64)]) -> HashMap<Address, f64> {
+        let mut prices: HashMap<Address, f64> = HashMap::new();
+        let mut queue = VecDeque::new();
+
+        for &(token, usd) in anchors {
+            if let Some(node) = self.get_node(token) {
+                prices.insert(token, usd);
+                queue.push_back(node);
+            }
+        }
+
+        while let Some(node) = queue.pop_front() {
+            let Some(token) = self.get_token(node) else { continue };
+            let usd = prices[&token];
+
+            for edge in self.graph.edges(node) {
+                let Some(next) = self.get_token(edge.target()) else { continue };
+                if prices.contains_key(&next) || edge.weight().price <= 0.0 {
+                    continue;
+                }
+                prices.insert(next, usd / edge.weight().price);
+                queue.push_back(edge.target());
+            }
+        }
+
+        prices
+    }
+
     pub fn add_pool(&mut self, pool: &PoolState) -> bool {
+        self.add_pool_at(pool, None, None)
+    }
+
+    /// Add both directions of a pool; `reference0` / `reference1` are input
+    /// sizes (whole tokens) to weight each direction at, `None` for spot
+    fn add_pool_at(&mut self, pool: &PoolState, reference0: Option<f64>, reference1: Option<f64>) -> bool {
         if pool.liquidity == 0 {
             return false;
         }
@@ -100,8 +162,12 @@ impl ArbitrageGraph {
         }
         
         let fee_rate = pool.fee as f64 / 1_000_000.0;
-        let effective_price_0_to_1 = raw_price * (1.0 - fee_rate);
-        let effective_price_1_to_0 = (1.0 / raw_price) * (1.0 - fee_rate);
+        let effective_price_0_to_1 = reference0
+            .and_then(|amount| effective_rate(pool, true, amount))
+            .unwrap_or(raw_price * (1.0 - fee_rate));
+        let effective_price_1_to_0 = reference1
+            .and_then(|amount| effective_rate(pool, false, amount))
+            .unwrap_or((1.0 / raw_price) * (1.0 - fee_rate));
 
         if effective_price_0_to_1 > 0.0 && effective_price_0_to_1.ln().is_finite() {
             self.graph.add_edge(
@@ -138,6 +204,16 @@ impl ArbitrageGraph {
         true
     }
 
+    /// All pools trading `token_in` → `token_out` (parallel edges)
+    pub fn parallel_edges(&self, token_in: Address, token_out: Address) -> Vec<&EdgeData> {
+        let (Some(from), Some(to)) = (self.get_node(token_in), self.get_node(token_out)) else {
+            return Vec::new();
+        };
+        self.graph.edges_connecting(from, to)
+            .map(|e| e.weight())
+            .collect()
+    }
+
     fn get_or_create_node(&mut self, token: Address) -> NodeIndex {
         if let Some(&node) = self.token_to_node.get(&token) {
             return node;
@@ -165,8 +241,165 @@ impl ArbitrageGraph {
     }
 }
 
+/// Amplification assumed for Curve pools (the pool state doesn't carry A)
+const CURVE_REFERENCE_AMP: f64 = 200.0;
+
+/// Output per unit input (whole tokens, after fee) for swapping `amount_in`
+/// of token0 (`zero_for_one`) or token1 through `pool`
+///
+/// V2 uses the reserves and Balancer the weighted-product formula. V3 treats
+/// the in-range liquidity as a constant-product pool with virtual reserves,
+/// which ignores liquidity changes at the next initialized ticks. Curve
+/// applies the StableSwap invariant's slippage to the quoted spot price.
+fn effective_rate(pool: &PoolState, zero_for_one: bool, amount_in: f64) -> Option<f64> {
+    if amount_in <= 0.0 || !amount_in.is_finite() {
+        return None;
+    }
+
+    let fee_rate = pool.fee as f64 / 1_000_000.0;
+    let scale0 = 10_f64.powi(pool.token0_decimals as i32);
+    let scale1 = 10_f64.powi(pool.token1_decimals as i32);
+    let dx = amount_in * (1.0 - fee_rate);
+
+    let rate = match pool.pool_type {
+        PoolType::V2 => {
+            let (r0, r1) = (pool.liquidity as f64 / scale0, pool.reserve1 as f64 / scale1);
+            let (r_in, r_out) = if zero_for_one { (r0, r1) } else { (r1, r0) };
+            r_out * dx / (r_in + dx) / amount_in
+        }
+        PoolType::Balancer => {
+            let (r0, r1) = (pool.liquidity as f64 / scale0, pool.reserve1 as f64 / scale1);
+            let w0 = if pool.weight0 == 0 { 0.5 } else { pool.weight0 as f64 / 1e18 };
+            let (r_in, r_out, w_in, w_out) = if zero_for_one {
+                (r0, r1, w0, 1.0 - w0)
+            } else {
+                (r1, r0, 1.0 - w0, w0)
+            };
+            r_out * (1.0 - (r_in / (r_in + dx)).powf(w_in / w_out)) / amount_in
+        }
+        PoolType::V3 => {
+            let sqrt_price = pool.sqrt_price_x96.to::<u128>() as f64 / 2_f64.powi(96);
+            if sqrt_price == 0.0 {
+                return None;
+            }
+            let liquidity = pool.liquidity as f64;
+            let x = liquidity / sqrt_price / scale0;
+            let y = liquidity * sqrt_price / scale1;
+            let (r_in, r_out) = if zero_for_one { (x, y) } else { (y, x) };
+            r_out * dx / (r_in + dx) / amount_in
+        }
+        PoolType::Curve => {
+            let spot = pool.normalized_price();
+            if spot <= 0.0 {
+                return None;
+            }
+            // Balances in token0 units, so the invariant is centered on the quote
+            let b0 = pool.liquidity as f64 / scale0;
+            let b1 = pool.reserve1 as f64 / scale1 / spot;
+            let (b_in, b_out, dx_scaled, spot_out) = if zero_for_one {
+                (b0, b1, dx, spot)
+            } else {
+                (b1, b0, dx / spot, 1.0 / spot)
+            };
+            let slippage = stableswap_slippage(b_in, b_out, dx_scaled, CURVE_REFERENCE_AMP)?;
+            spot_out * (1.0 - fee_rate) * slippage
+        }
+    };
+
+    (rate > 0.0 && rate.is_finite()).then_some(rate)
+}
+
+/// Ratio of the StableSwap average rate for `dx` to its marginal rate
+fn stableswap_slippage(b_in: f64, b_out: f64, dx: f64, amp: f64) -> Option<f64> {
+    if b_in <= 0.0 || b_out <= 0.0 {
+        return None;
+    }
+    let d = stableswap_d(b_in, b_out, amp);
+    let dy = |dx: f64| b_out - stableswap_y(b_in + dx, d, amp);
+
+    let probe = b_in * 1e-9;
+    let marginal = dy(probe) / probe;
+    let average = dy(dx) / dx;
+    (marginal > 0.0).then(|| (average / marginal).clamp(0.0, 1.0))
+}
+
+/// StableSwap invariant D for two balances
+fn stableswap_d(x: f64, y: f64, amp: f64) -> f64 {
+    let ann = amp * 4.0;
+    let sum = x + y;
+    let mut d = sum;
+    for _ in 0..255 {
+        let d_p = d * d / (2.0 * x) * d / (2.0 * y);
+        let prev = d;
+        d = (ann * sum + 2.0 * d_p) * d / ((ann - 1.0) * d + 3.0 * d_p);
+        if (d - prev).abs() <= d * 1e-15 {
+            break;
+        }
+    }
+    d
+}
+
+/// Balance of the other coin keeping D constant when one coin holds `x`
+fn stableswap_y(x: f64, d: f64, amp: f64) -> f64 {
+    let ann = amp * 4.0;
+    let c = d * d / (2.0 * x) * d / (2.0 * ann);
+    let b = x + d / ann;
+    let mut y = d;
+    for _ in 0..255 {
+        let prev = y;
+        y = (y * y + c) / (2.0 * y + b - d);
+        if (y - prev).abs() <= y * 1e-15 {
+            break;
+        }
+    }
+    y
+}
+
 impl Default for ArbitrageGraph {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartographer::test_fixtures::v2_pool;
+
+    #[test]
+    fn test_impact_weights_penalize_shallow_pools() {
+        let (a, b) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let e18 = 10u128.pow(18);
+        // Same spot price, 10k vs 100M tokens deep
+        let pools = vec![
+            v2_pool(0xa1, a, b, 10_000 * e18, 20_000 * e18),
+            v2_pool(0xa2, a, b, 100_000_000 * e18, 200_000_000 * e18),
+        ];
+        let weight = |graph: &ArbitrageGraph, pool: u8| {
+            graph.parallel_edges(a, b).into_iter()
+                .find(|e| e.pool_address == Address::repeat_byte(pool))
+                .unwrap()
+                .weight
+        };
+
+        let spot = ArbitrageGraph::from_pools(&pools);
+        assert!((weight(&spot, 0xa1) - weight(&spot, 0xa2)).abs() < 1e-12);
+
+        // $10k of `a` at $1: 10k in, so the shallow pool returns about half
+        let sized = ArbitrageGraph::from_pools_at_size(&pools, 10_000.0, &[(a, 1.0)]);
+        let shallow = (-weight(&sized, 0xa1)).exp();
+        let deep = (-weight(&sized, 0xa2)).exp();
+        assert!((deep - 2.0 * 0.997).abs() < 1e-3);
+        assert!(shallow < 1.01 && shallow > 0.99);
+
+        // b was priced through the spot edge, at $0.50
+        assert!((spot.token_prices_usd(&[(a, 1.0)])[&b] - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_stableswap_slippage_grows_with_size() {
+        let small = stableswap_slippage(1e6, 1e6, 1e3, 200.0).unwrap();
+        let large = stableswap_slippage(1e6, 1e6, 5e5, 200.0).unwrap();
+        assert!(small > 0.9999);
+        assert!(large < small);
+    }
+}
@@ -11,6 +11,8 @@
 
 mod fetcher;
 mod graph;
+#[cfg(test)]
+pub(crate) mod test_fixtures;
 
 // NEW MODULES - Phase 1-4
 pub mod curve_ng;
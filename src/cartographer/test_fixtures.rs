@@ -0,0 +1,47 @@
+//! Pool and graph fixtures shared by the cartographer and brain tests
+
+use alloy_primitives::{Address, U256};
+
+use super::{ArbitrageGraph, Dex, EdgeData, PoolState, PoolType};
+
+/// A V2 pool at `0xaddress..` with 18-decimal tokens and a 0.3% fee
+pub fn v2_pool(address: u8, token0: Address, token1: Address, reserve0: u128, reserve1: u128) -> PoolState {
+    PoolState {
+        address: Address::repeat_byte(address),
+        token0,
+        token1,
+        token0_decimals: 18,
+        token1_decimals: 18,
+        sqrt_price_x96: U256::ZERO,
+        tick: 0,
+        liquidity: reserve0,
+        reserve1,
+        fee: 3000,
+        is_v4: false,
+        dex: Dex::UniswapV2,
+        pool_type: PoolType::V2,
+        weight0: 0,
+    }
+}
+
+/// Add a fee-free V2 edge trading `from` into `to` at `rate`, through pool `0xpool..`
+pub fn add_edge(graph: &mut ArbitrageGraph, from: Address, to: Address, pool: u8, rate: f64) {
+    let node = |token: Address, graph: &mut ArbitrageGraph| {
+        *graph.token_to_node.entry(token).or_insert_with(|| {
+            let idx = graph.graph.add_node(token);
+            graph.node_to_token.insert(idx, token);
+            idx
+        })
+    };
+    let a = node(from, graph);
+    let b = node(to, graph);
+    graph.graph.add_edge(a, b, EdgeData {
+        pool_address: Address::repeat_byte(pool),
+        weight: -rate.ln(),
+        price: rate,
+        fee: 0,
+        is_v4: false,
+        dex: Dex::UniswapV2,
+        pool_type: PoolType::V2,
+    });
+}
@@ -53,7 +53,7 @@ impl std::fmt::Display for ExecutionMode {
 // ============================================
 
 /// Available Flash Loan providers
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum FlashLoanProvider {
     /// Aave V3 - 0.05% fee, very reliable
     AaveV3,
@@ -81,6 +81,35 @@ impl std::fmt::Display for FlashLoanProvider {
     }
 }
 
+// ============================================
+// EDGE WEIGHTING
+// ============================================
+
+/// How graph edges are weighted for cycle search
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EdgeWeightMode {
+    /// Spot price after fee - ignores pool depth
+    Spot,
+    
+    /// Effective rate for a reference trade size (price impact included)
+    PriceImpact,
+}
+
+impl Default for EdgeWeightMode {
+    fn default() -> Self {
+        EdgeWeightMode::Spot
+    }
+}
+
+impl std::fmt::Display for EdgeWeightMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EdgeWeightMode::Spot => write!(f, "Spot"),
+            EdgeWeightMode::PriceImpact => write!(f, "Price impact"),
+        }
+    }
+}
+
 // ============================================
 // MAIN CONFIGURATION
 // ============================================
@@ -108,6 +137,15 @@ pub struct Config {
     /// Path to save profitable opportunity logs
     pub simulation_log_path: String,
     
+    /// Path to record submitted bundles' outcomes (expected vs realized PnL)
+    pub execution_log_path: String,
+    
+    /// Path of the persisted (calibrated) swap gas model
+    pub gas_model_path: String,
+    
+    /// Run executor transactions on a local EVM fork before signing
+    pub fork_simulation: bool,
+    
     // ========== Profit Thresholds ==========
     /// Minimum net profit in USD to consider an opportunity
     /// Set to $20+ to cover gas spikes and leave margin
@@ -131,6 +169,18 @@ pub struct Config {
     /// Minimum liquidity in USD for a pool to be considered
     pub min_pool_liquidity_usd: f64,
     
+    /// How edges are weighted for cycle search
+    pub edge_weight_mode: EdgeWeightMode,
+    
+    /// Trade size in USD that price-impact edge weights are evaluated at
+    pub reference_trade_usd: f64,
+    
+    /// Number of candidate cycles simulated per scan
+    pub max_candidates: usize,
+    
+    /// Maximum candidates per scan that may share a pool
+    pub max_cycles_per_pool: usize,
+    
     // ========== Token Filters ==========
     /// Tokens to ALWAYS start arbitrage from (high liquidity)
     pub base_tokens: Vec<String>,
@@ -144,6 +194,12 @@ pub struct Config {
     /// Only trade tokens in this whitelist (if non-empty)
     pub whitelisted_tokens: Vec<String>,
     
+    /// Token categories never traded (e.g. "meme,gaming")
+    pub risk_denied_categories: Vec<String>,
+    
+    /// Optional JSON file overriding the per-category risk rules
+    pub risk_policy_path: Option<String>,
+    
     // ========== Flash Loan Settings ==========
     /// Preferred flash loan provider
     pub flash_loan_provider: FlashLoanProvider,
@@ -151,6 +207,10 @@ pub struct Config {
     /// Maximum flash loan amount in USD
     pub max_flash_loan_usd: f64,
     
+    /// Tokens whose deepest flash lender holds at least this much (USD)
+    /// are used as cycle start tokens
+    pub min_flash_liquidity_usd: f64,
+    
     /// Default flash loan amount for simulation
     pub default_simulation_usd: f64,
     
@@ -158,14 +218,29 @@ pub struct Config {
     /// Flashbots RPC endpoint
     pub flashbots_rpc_url: String,
     
-    /// Flashbots bundle signing key (KEEP SECRET!)
-    /// This is separate from your profit wallet
+    /// Flashbots bundle signing key (KEEP SECRET!), or the path of an
+    /// encrypted keystore. This is separate from your profit wallet
     pub flashbots_signer_key: Option<String>,
     
     /// Miner bribe percentage (of profit to give to miner)
     /// 90% = keep 10% profit, give 90% to miner for inclusion
     pub miner_bribe_pct: f64,
     
+    /// Bribe percentage approached as rival bundles keep winning the same
+    /// opportunity (each lost block halves the gap from MINER_BRIBE_PCT)
+    pub max_bribe_pct: f64,
+    
+    /// Smallest coinbase payment worth bidding (ETH), as long as the trade
+    /// still clears MIN_PROFIT_USD after paying it
+    pub min_bribe_eth: f64,
+    
+    /// Extra block builder endpoints (eth_sendBundle) that receive every
+    /// bundle alongside the Flashbots relay
+    pub builder_urls: Vec<String>,
+    
+    /// Number of consecutive blocks each bundle is submitted for
+    pub bundle_target_blocks: usize,
+    
     // ========== Wallet Settings ==========
     /// Ethereum wallet address to receive profits
     pub profit_wallet_address: Option<String>,
@@ -173,6 +248,10 @@ pub struct Config {
     /// Executor contract address (deployed flash loan executor)
     pub executor_contract_address: Option<String>,
     
+    /// Executor runtime bytecode (hex file), injected on the fork when the
+    /// executor isn't deployed there
+    pub executor_bytecode_path: Option<String>,
+    
     // ========== Rate Limiting ==========
     /// Minimum seconds between scans
     pub scan_interval_secs: u64,
@@ -228,6 +307,14 @@ impl Config {
                 .unwrap_or(true),
             simulation_log_path: env::var("SIMULATION_LOG_PATH")
                 .unwrap_or_else(|_| "./logs/profitable_opportunities.log".to_string()),
+            execution_log_path: env::var("EXECUTION_LOG_PATH")
+                .unwrap_or_else(|_| "./logs/executions.log".to_string()),
+            gas_model_path: env::var("GAS_MODEL_PATH")
+                .unwrap_or_else(|_| "./data/gas_model.json".to_string()),
+            fork_simulation: env::var("FORK_SIMULATION")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .unwrap_or(true),
             
             // Profit thresholds
             min_profit_usd: env::var("MIN_PROFIT_USD")
@@ -256,6 +343,26 @@ impl Config {
                 .unwrap_or_else(|_| "50000.0".to_string())
                 .parse()
                 .unwrap_or(50000.0),
+            edge_weight_mode: match env::var("EDGE_WEIGHT_MODE")
+                .unwrap_or_else(|_| "spot".to_string())
+                .to_lowercase()
+                .as_str()
+            {
+                "impact" | "price_impact" => EdgeWeightMode::PriceImpact,
+                _ => EdgeWeightMode::Spot,
+            },
+            reference_trade_usd: env::var("REFERENCE_TRADE_USD")
+                .unwrap_or_else(|_| "10000.0".to_string())
+                .parse()
+                .unwrap_or(10000.0),
+            max_candidates: env::var("MAX_CANDIDATES")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .unwrap_or(5),
+            max_cycles_per_pool: env::var("MAX_CYCLES_PER_POOL")
+                .unwrap_or_else(|_| "2".to_string())
+                .parse()
+                .unwrap_or(2),
             
             // Token filters
             base_tokens: env::var("BASE_TOKENS")
@@ -268,6 +375,10 @@ impl Config {
             whitelisted_tokens: env::var("WHITELISTED_TOKENS")
                 .map(|s| s.split(',').map(String::from).collect())
                 .unwrap_or_default(),
+            risk_denied_categories: env::var("RISK_DENIED_CATEGORIES")
+                .map(|s| s.split(',').map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect())
+                .unwrap_or_default(),
+            risk_policy_path: env::var("RISK_POLICY_PATH").ok(),
             
             // Flash loan
             flash_loan_provider: match env::var("FLASH_LOAN_PROVIDER")
@@ -283,6 +394,10 @@ impl Config {
                 .unwrap_or_else(|_| "100000.0".to_string())
                 .parse()
                 .unwrap_or(100000.0),
+            min_flash_liquidity_usd: env::var("MIN_FLASH_LIQUIDITY_USD")
+                .unwrap_or_else(|_| "50000.0".to_string())
+                .parse()
+                .unwrap_or(50000.0),
             default_simulation_usd: env::var("DEFAULT_SIMULATION_USD")
                 .unwrap_or_else(|_| "10000.0".to_string())
                 .parse()
@@ -296,10 +411,26 @@ impl Config {
                 .unwrap_or_else(|_| "90.0".to_string())
                 .parse()
                 .unwrap_or(90.0),
+            max_bribe_pct: env::var("MAX_BRIBE_PCT")
+                .unwrap_or_else(|_| "99.0".to_string())
+                .parse()
+                .unwrap_or(99.0),
+            min_bribe_eth: env::var("MIN_BRIBE_ETH")
+                .unwrap_or_else(|_| "0.0".to_string())
+                .parse()
+                .unwrap_or(0.0),
+            builder_urls: env::var("BUILDER_URLS")
+                .map(|s| s.split(',').map(|u| u.trim().to_string()).filter(|u| !u.is_empty()).collect())
+                .unwrap_or_default(),
+            bundle_target_blocks: env::var("BUNDLE_TARGET_BLOCKS")
+                .unwrap_or_else(|_| "3".to_string())
+                .parse()
+                .unwrap_or(3),
             
             // Wallet
             profit_wallet_address: env::var("PROFIT_WALLET_ADDRESS").ok(),
             executor_contract_address: env::var("EXECUTOR_CONTRACT_ADDRESS").ok(),
+            executor_bytecode_path: env::var("EXECUTOR_BYTECODE_PATH").ok(),
             
             // Rate limiting
             scan_interval_secs: env::var("SCAN_INTERVAL_SECS")
@@ -465,6 +596,12 @@ impl Config {
                 self.miner_bribe_pct
             ));
         }
+        if self.max_bribe_pct < self.miner_bribe_pct || self.max_bribe_pct > 99.0 {
+            return Err(eyre::eyre!(
+                "MAX_BRIBE_PCT should be between MINER_BRIBE_PCT and 99% (currently {:.1}%)",
+                self.max_bribe_pct
+            ));
+        }
         
         Ok(())
     }
@@ -493,12 +630,14 @@ impl Config {
         println!("╠════════════════════════════════════════════════════════════╣");
         println!("║ PATH FINDING                                               ║");
         println!("║ • Max Hops:        {:^40} ║", self.max_hops);
+        println!("║ • Edge Weights:    {:^40} ║", format!("{} @ ${:.0}", self.edge_weight_mode, self.reference_trade_usd));
         println!("║ • Base Tokens:     {:^40} ║", self.base_tokens.len());
         println!("║ • Blacklisted Pairs: {:^38} ║", self.blacklisted_pairs.len());
         println!("╠════════════════════════════════════════════════════════════╣");
         println!("║ FLASH LOAN                                                 ║");
         println!("║ • Provider:        {:^40} ║", self.flash_loan_provider);
         println!("║ • Max Amount:      ${:<38.0} ║", self.max_flash_loan_usd);
+        println!("║ • Min Liquidity:   ${:<38.0} ║", self.min_flash_liquidity_usd);
         println!("╠════════════════════════════════════════════════════════════╣");
         println!("║ FLASHBOTS                                                  ║");
         println!("║ • Miner Bribe:     {:>38.0}% ║", self.miner_bribe_pct);
@@ -531,24 +670,39 @@ impl Default for Config {
             execution_mode: ExecutionMode::Simulation,
             simulation_log: true,
             simulation_log_path: "./logs/profitable_opportunities.log".to_string(),
+            execution_log_path: "./logs/executions.log".to_string(),
+            gas_model_path: "./data/gas_model.json".to_string(),
+            fork_simulation: true,
             min_profit_usd: 20.0,
             min_gross_profit_pct: 0.3,
             max_gas_gwei: 50,
             max_slippage: 0.005,
             max_hops: 4,
             min_pool_liquidity_usd: 50000.0,
+            edge_weight_mode: EdgeWeightMode::Spot,
+            reference_trade_usd: 10000.0,
+            max_candidates: 5,
+            max_cycles_per_pool: 2,
             base_tokens: Self::default_base_tokens(),
             blacklisted_pairs: Self::parse_blacklisted_pairs(),
             blacklisted_tokens: vec![],
             whitelisted_tokens: vec![],
+            risk_denied_categories: vec![],
+            risk_policy_path: None,
             flash_loan_provider: FlashLoanProvider::BalancerV2,
             max_flash_loan_usd: 100000.0,
+            min_flash_liquidity_usd: 50000.0,
             default_simulation_usd: 10000.0,
             flashbots_rpc_url: "https://relay.flashbots.net".to_string(),
             flashbots_signer_key: None,
             miner_bribe_pct: 90.0,
+            max_bribe_pct: 99.0,
+            min_bribe_eth: 0.0,
+            builder_urls: vec![],
+            bundle_target_blocks: 3,
             profit_wallet_address: None,
             executor_contract_address: None,
+            executor_bytecode_path: None,
             scan_interval_secs: 12,
             max_rpc_calls_per_sec: 25,
             emergency_stop: false,
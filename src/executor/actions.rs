@@ -0,0 +1,345 @@
+//! Executor Actions - per-leg parameters for Curve, Balancer and ERC-4626
+//!
+//! V2 / V3 legs only need the pool address. The other edge types need data
+//! the graph doesn't carry, resolved here (and cached, it never changes):
+//! - Curve: coin indices and which `exchange` flavour the pool speaks
+//!   (stable `int128` indices, crypto `uint256` indices, or stable-NG
+//!   `exchange_received`, which skips the approval)
+//! - Balancer: the pool id; consecutive single-pool Balancer hops are merged
+//!   into one `batchSwap` leg
+//! - ERC-4626 (Sky virtual pools): nothing, the vault is the pool and the
+//!   direction (`deposit` / `redeem`) follows from which side is the vault
+
+use alloy_primitives::{address, Address, Bytes, B256, U256};
+use alloy_provider::{Provider, ProviderBuilder};
+use alloy_rpc_types::TransactionRequest;
+use alloy_sol_types::{sol, SolCall, SolValue};
+use eyre::{eyre, Result};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tracing::debug;
+
+use super::flash_loan::DexType;
+use super::plan::{Plan, PlanHop};
+use crate::brain::ArbitrageCycle;
+
+/// Balancer V2 Vault (swaps and flash loans)
+pub const BALANCER_VAULT: Address = address!("BA12222222228d8Ba445958a75a0704d566BF2C8");
+
+/// Multicall3 address (same on all EVM chains)
+const MULTICALL3: Address = address!("cA11bde05977b3631167028862bE2a173976CA11");
+
+/// Most coins a Curve pool can hold
+const MAX_CURVE_COINS: usize = 8;
+
+/// Curve `exchange` flavours (first word of a Curve leg's data)
+pub const CURVE_STABLE: u8 = 0;
+pub const CURVE_CRYPTO: u8 = 1;
+pub const CURVE_STABLE_RECEIVED: u8 = 2;
+
+sol! {
+    interface IActionMulticall3 {
+        struct Call3 {
+            address target;
+            bool allowFailure;
+            bytes callData;
+        }
+
+        struct Result {
+            bool success;
+            bytes returnData;
+        }
+
+        function aggregate3(Call3[] calldata calls)
+            external payable returns (Result[] memory returnData);
+    }
+
+    interface ICurvePoolInfo {
+        function coins(uint256 i) external view returns (address);
+        /// Crypto pools only
+        function gamma() external view returns (uint256);
+        /// Stableswap-NG only (older pools keep it private)
+        function N_COINS() external view returns (uint256);
+    }
+
+    interface IBalancerPoolInfo {
+        function getPoolId() external view returns (bytes32);
+    }
+}
+
+/// What the executor needs to trade through a Curve pool
+#[derive(Debug, Clone, PartialEq)]
+struct CurvePoolInfo {
+    coins: Vec<Address>,
+    kind: u8,
+}
+
+/// Curve leg data: (kind, i, j)
+pub fn encode_curve_data(kind: u8, i: usize, j: usize) -> Bytes {
+    (U256::from(kind), U256::from(i), U256::from(j)).abi_encode_params().into()
+}
+
+/// Balancer leg data: pool ids in order and the assets they connect
+/// (`assets.len() == pool_ids.len() + 1`)
+pub fn encode_balancer_data(pool_ids: &[B256], assets: &[Address]) -> Bytes {
+    (pool_ids.to_vec(), assets.to_vec()).abi_encode_params().into()
+}
+
+/// Decode Balancer leg data back into (pool ids, assets)
+pub fn decode_balancer_data(data: &[u8]) -> Result<(Vec<B256>, Vec<Address>)> {
+    <(Vec<B256>, Vec<Address>)>::abi_decode_params(data)
+        .map_err(|e| eyre!("Invalid Balancer leg data: {}", e))
+}
+
+/// Whether a plan swaps through the Balancer Vault (which then can't also
+/// lend: its flash loan and swaps share one reentrancy lock)
+pub fn uses_balancer_vault(plan: &Plan) -> bool {
+    plan.hops.iter()
+        .flat_map(|h| &h.legs)
+        .any(|l| l.dexType == DexType::BalancerV2 as u8)
+}
+
+/// Lender sources that can't fund `plan`: every pool it trades through
+/// (a lending Uniswap pool is locked during `flash`) and the Balancer Vault
+/// when it swaps there
+pub fn lender_exclusions(plan: &Plan) -> Vec<Address> {
+    let mut exclude: Vec<Address> = plan.hops.iter()
+        .flat_map(|h| h.legs.iter().map(|l| l.pool))
+        .collect();
+    if uses_balancer_vault(plan) {
+        exclude.push(BALANCER_VAULT);
+    }
+    exclude
+}
+
+/// `lender_exclusions` for a cycle before its plan is built
+pub fn cycle_lender_exclusions(cycle: &ArbitrageCycle) -> Vec<Address> {
+    let mut exclude = cycle.pools.clone();
+    let swaps_on_vault = cycle.path.windows(2)
+        .zip(cycle.pools.iter().zip(&cycle.dexes))
+        .any(|(pair, (pool, dex))| DexType::for_edge(*dex, *pool, pair[0], pair[1]) == DexType::BalancerV2);
+    if swaps_on_vault {
+        exclude.push(BALANCER_VAULT);
+    }
+    exclude
+}
+
+/// Resolves and caches per-leg parameters
+#[derive(Clone)]
+pub struct ActionResolver {
+    rpc_url: String,
+    curve_pools: Arc<RwLock<HashMap<Address, CurvePoolInfo>>>,
+    balancer_pool_ids: Arc<RwLock<HashMap<Address, B256>>>,
+}
+
+impl ActionResolver {
+    pub fn new(rpc_url: String) -> Self {
+        Self {
+            rpc_url,
+            curve_pools: Arc::new(RwLock::new(HashMap::new())),
+            balancer_pool_ids: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Fill in Curve / Balancer leg data and merge Balancer hop runs
+    pub async fn resolve(&self, plan: &mut Plan) -> Result<()> {
+        self.load_pools(plan).await?;
+
+        {
+            let curve_pools = self.curve_pools.read().map_err(|_| eyre!("Curve pool cache poisoned"))?;
+            let balancer_ids = self.balancer_pool_ids.read().map_err(|_| eyre!("Balancer pool cache poisoned"))?;
+
+            for hop in plan.hops.iter_mut() {
+                for leg in hop.legs.iter_mut() {
+                    if leg.dexType == DexType::Curve as u8 {
+                        let info = curve_pools.get(&leg.pool)
+                            .ok_or_else(|| eyre!("Curve pool {:?} not resolved", leg.pool))?;
+                        let index = |token: Address| info.coins.iter()
+                            .position(|c| *c == token)
+                            .ok_or_else(|| eyre!("{:?} is not a coin of Curve pool {:?}", token, leg.pool));
+                        leg.data = encode_curve_data(info.kind, index(hop.tokenIn)?, index(hop.tokenOut)?);
+                    } else if leg.dexType == DexType::BalancerV2 as u8 {
+                        let pool_id = balancer_ids.get(&leg.pool)
+                            .ok_or_else(|| eyre!("Balancer pool {:?} has no pool id", leg.pool))?;
+                        leg.data = encode_balancer_data(&[*pool_id], &[hop.tokenIn, hop.tokenOut]);
+                    }
+                }
+            }
+        }
+
+        merge_balancer_hops(plan)?;
+        plan.validate()
+    }
+
+    /// Fetch coins / kind of unknown Curve pools and ids of unknown Balancer pools
+    async fn load_pools(&self, plan: &Plan) -> Result<()> {
+        let (mut curve, mut balancer) = (Vec::new(), Vec::new());
+        {
+            let curve_pools = self.curve_pools.read().map_err(|_| eyre!("Curve pool cache poisoned"))?;
+            let balancer_ids = self.balancer_pool_ids.read().map_err(|_| eyre!("Balancer pool cache poisoned"))?;
+            for leg in plan.hops.iter().flat_map(|h| &h.legs) {
+                if leg.dexType == DexType::Curve as u8 && !curve_pools.contains_key(&leg.pool) && !curve.contains(&leg.pool) {
+                    curve.push(leg.pool);
+                } else if leg.dexType == DexType::BalancerV2 as u8 && !balancer_ids.contains_key(&leg.pool) && !balancer.contains(&leg.pool) {
+                    balancer.push(leg.pool);
+                }
+            }
+        }
+        if curve.is_empty() && balancer.is_empty() {
+            return Ok(());
+        }
+
+        let per_curve = MAX_CURVE_COINS + 2;
+        let mut calls = Vec::with_capacity(curve.len() * per_curve + balancer.len());
+        for pool in &curve {
+            for i in 0..MAX_CURVE_COINS {
+                calls.push(call3(*pool, ICurvePoolInfo::coinsCall { i: U256::from(i) }.abi_encode()));
+            }
+            calls.push(call3(*pool, ICurvePoolInfo::gammaCall {}.abi_encode()));
+            calls.push(call3(*pool, ICurvePoolInfo::N_COINSCall {}.abi_encode()));
+        }
+        for pool in &balancer {
+            calls.push(call3(*pool, IBalancerPoolInfo::getPoolIdCall {}.abi_encode()));
+        }
+        let results = self.multicall(calls).await?;
+
+        let mut curve_pools = self.curve_pools.write().map_err(|_| eyre!("Curve pool cache poisoned"))?;
+        for (pool, chunk) in curve.iter().zip(results.chunks(per_curve)) {
+            let coins: Vec<Address> = chunk[..MAX_CURVE_COINS].iter()
+                .map_while(|r| r.success.then(|| ICurvePoolInfo::coinsCall::abi_decode_returns(&r.returnData).ok()).flatten())
+                .take_while(|c| *c != Address::ZERO)
+                .collect();
+            let kind = if chunk[MAX_CURVE_COINS].success && !chunk[MAX_CURVE_COINS].returnData.is_empty() {
+                CURVE_CRYPTO
+            } else if chunk[MAX_CURVE_COINS + 1].success && !chunk[MAX_CURVE_COINS + 1].returnData.is_empty() {
+                CURVE_STABLE_RECEIVED
+            } else {
+                CURVE_STABLE
+            };
+            debug!("Curve pool {:?}: {} coins, kind {}", pool, coins.len(), kind);
+            curve_pools.insert(*pool, CurvePoolInfo { coins, kind });
+        }
+
+        let mut balancer_ids = self.balancer_pool_ids.write().map_err(|_| eyre!("Balancer pool cache poisoned"))?;
+        for (pool, result) in balancer.iter().zip(&results[curve.len() * per_curve..]) {
+            if result.success {
+                if let Ok(pool_id) = IBalancerPoolInfo::getPoolIdCall::abi_decode_returns(&result.returnData) {
+                    balancer_ids.insert(*pool, pool_id);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn multicall(&self, calls: Vec<IActionMulticall3::Call3>) -> Result<Vec<IActionMulticall3::Result>> {
+        let provider = ProviderBuilder::new()
+            .on_http(self.rpc_url.parse()?);
+
+        let calldata = IActionMulticall3::aggregate3Call { calls }.abi_encode();
+        let tx = TransactionRequest::default()
+            .to(MULTICALL3)
+            .input(calldata.into());
+        let output = provider.call(tx).await
+            .map_err(|e| eyre!("Multicall3 failed: {}", e))?;
+        IActionMulticall3::aggregate3Call::abi_decode_returns(&output)
+            .map_err(|e| eyre!("Failed to decode multicall result: {}", e))
+    }
+}
+
+fn call3(target: Address, calldata: Vec<u8>) -> IActionMulticall3::Call3 {
+    IActionMulticall3::Call3 {
+        target,
+        allowFailure: true,
+        callData: calldata.into(),
+    }
+}
+
+/// Merge runs of consecutive single-leg Balancer hops into one `batchSwap`
+/// leg (intermediate tokens never leave the Vault)
+fn merge_balancer_hops(plan: &mut Plan) -> Result<()> {
+    let is_balancer_hop = |hop: &PlanHop| hop.legs.len() == 1 && hop.legs[0].dexType == DexType::BalancerV2 as u8;
+
+    let mut merged: Vec<PlanHop> = Vec::with_capacity(plan.hops.len());
+    for hop in plan.hops.drain(..) {
+        match merged.last_mut() {
+            Some(prev) if is_balancer_hop(prev) && is_balancer_hop(&hop) => {
+                let (mut pool_ids, mut assets) = decode_balancer_data(&prev.legs[0].data)?;
+                let (next_ids, next_assets) = decode_balancer_data(&hop.legs[0].data)?;
+                pool_ids.extend(next_ids);
+                assets.extend(next_assets.into_iter().skip(1));
+
+                prev.tokenOut = hop.tokenOut;
+                prev.legs[0].data = encode_balancer_data(&pool_ids, &assets);
+                prev.legs[0].minAmountOut = hop.legs[0].minAmountOut;
+            }
+            _ => merged.push(hop),
+        }
+    }
+    plan.hops = merged;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::PlanLeg;
+    use alloy_primitives::aliases::U24;
+
+    fn balancer_hop(from: u8, to: u8, pool_id: u8) -> PlanHop {
+        let (token_in, token_out) = (Address::repeat_byte(from), Address::repeat_byte(to));
+        PlanHop {
+            tokenIn: token_in,
+            tokenOut: token_out,
+            legs: vec![PlanLeg {
+                dexType: DexType::BalancerV2 as u8,
+                pool: Address::repeat_byte(0xb0 + pool_id),
+                fee: U24::from(1_000),
+                shareBps: 10_000,
+                minAmountOut: U256::from(to),
+                data: encode_balancer_data(&[B256::repeat_byte(pool_id)], &[token_in, token_out]),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_merge_balancer_hops_into_batch_swap() {
+        let mut v3_hop = balancer_hop(3, 1, 9);
+        v3_hop.legs[0].dexType = DexType::UniswapV3 as u8;
+        v3_hop.legs[0].data = Bytes::new();
+
+        let mut plan = Plan {
+            lender: 1,
+            lenderPool: Address::ZERO,
+            token: Address::repeat_byte(1),
+            amount: U256::from(100),
+            minOut: U256::from(101),
+            hops: vec![balancer_hop(1, 2, 1), balancer_hop(2, 3, 2), v3_hop],
+            bribe: Default::default(),
+        };
+        assert!(uses_balancer_vault(&plan));
+        merge_balancer_hops(&mut plan).unwrap();
+        plan.validate().unwrap();
+
+        assert_eq!(plan.hops.len(), 2);
+        assert_eq!(plan.hops[0].tokenOut, Address::repeat_byte(3));
+        assert_eq!(plan.hops[0].legs[0].minAmountOut, U256::from(3));
+        let (pool_ids, assets) = decode_balancer_data(&plan.hops[0].legs[0].data).unwrap();
+        assert_eq!(pool_ids, vec![B256::repeat_byte(1), B256::repeat_byte(2)]);
+        assert_eq!(assets, vec![Address::repeat_byte(1), Address::repeat_byte(2), Address::repeat_byte(3)]);
+        assert!(plan.hops[1].legs[0].data.is_empty());
+    }
+
+    #[test]
+    fn test_curve_data_layout() {
+        let data = encode_curve_data(CURVE_STABLE_RECEIVED, 1, 0);
+        let (kind, i, j) = <(U256, U256, U256)>::abi_decode_params(&data).unwrap();
+        assert_eq!((kind, i, j), (U256::from(CURVE_STABLE_RECEIVED), U256::from(1), U256::ZERO));
+        assert_eq!(data.len(), 96);
+
+        // Pinned to the executor's constants
+        let source = crate::executor::get_executor_contract_source();
+        for (name, kind) in [("CURVE_STABLE", CURVE_STABLE), ("CURVE_CRYPTO", CURVE_CRYPTO), ("CURVE_STABLE_RECEIVED", CURVE_STABLE_RECEIVED)] {
+            assert!(source.contains(&format!("uint8 constant {} = {};", name, kind)), "{} mismatch", name);
+        }
+    }
+}
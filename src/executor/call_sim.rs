@@ -0,0 +1,240 @@
+//! Call Simulation - executor transactions through eth_simulateV1 / eth_call
+//!
+//! A lighter alternative to the local EVM fork: the node runs the exact
+//! `FlashLoanTransaction`s with state overrides that place the executor's
+//! runtime bytecode at its address (owned by the sender) when nothing is
+//! deployed there, and fund the sender and executor with ETH. Results carry
+//! the decoded `ArbitrageExecuted` events.
+//!
+//! eth_simulateV1 runs a whole bundle in sequence and returns logs; nodes
+//! without it fall back to eth_call (return data only), which can only run a
+//! single transaction: each later one would miss the earlier ones' state.
+
+use alloy_primitives::{address, Address, Bytes, Log, B256, U256};
+use alloy_provider::{Provider, ProviderBuilder};
+use alloy_sol_types::{decode_revert_reason, sol, SolEvent};
+use eyre::{eyre, Result};
+use serde_json::{json, Value};
+use tracing::debug;
+
+use super::FlashLoanTransaction;
+
+/// Where the executor is simulated when none is deployed
+pub const SCRATCH_EXECUTOR: Address = address!("00000000000000000000000000000000e7ec0de0");
+
+/// Storage slot of the executor's owner (OpenZeppelin `Ownable._owner`)
+pub const EXECUTOR_OWNER_SLOT: U256 = U256::ZERO;
+
+/// ETH given to the sender and executor in the overrides
+const OVERRIDE_BALANCE_WEI: u128 = 100_000_000_000_000_000_000; // 100 ETH
+
+sol! {
+    event ArbitrageExecuted(address indexed token, uint256 inputAmount, uint256 profit);
+}
+
+/// Load executor runtime bytecode (hex) from a file
+pub fn load_executor_code(path: &str) -> Result<Bytes> {
+    let hex_code = std::fs::read_to_string(path)?;
+    let code = hex::decode(hex_code.trim().trim_start_matches("0x"))
+        .map_err(|e| eyre!("Invalid executor bytecode in {}: {}", path, e))?;
+    Ok(code.into())
+}
+
+/// Decoded `ArbitrageExecuted` event
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArbitrageExecution {
+    pub token: Address,
+    pub input_amount: U256,
+    pub profit: U256,
+}
+
+/// Outcome of one simulated transaction
+#[derive(Debug, Clone)]
+pub struct CallSimResult {
+    pub success: bool,
+    /// Gas used (None from the eth_call fallback)
+    pub gas_used: Option<u64>,
+    pub error: Option<String>,
+    pub executions: Vec<ArbitrageExecution>,
+}
+
+impl CallSimResult {
+    /// Total profit reported by the executor's events
+    pub fn total_profit(&self) -> U256 {
+        self.executions.iter().map(|e| e.profit).sum()
+    }
+}
+
+/// Simulates executor transactions on the node with state overrides
+#[derive(Debug, Clone)]
+pub struct CallSimulator {
+    rpc_url: String,
+    executor_code: Option<Bytes>,
+}
+
+impl CallSimulator {
+    pub fn new(rpc_url: String, executor_code: Option<Bytes>) -> Self {
+        Self { rpc_url, executor_code }
+    }
+
+    /// Whether executor bytecode is available for injection
+    pub fn has_executor_code(&self) -> bool {
+        self.executor_code.is_some()
+    }
+
+    /// Run `txs` in order from `from` on `block` (0 = latest)
+    pub async fn simulate(
+        &self,
+        txs: &[&FlashLoanTransaction],
+        from: Address,
+        executor: Address,
+        block: u64,
+    ) -> Result<Vec<CallSimResult>> {
+        let provider = ProviderBuilder::new()
+            .on_http(self.rpc_url.parse()?);
+        let block_tag = if block == 0 { json!("latest") } else { json!(format!("0x{:x}", block)) };
+
+        // Inject the executor only where nothing is deployed at that block
+        let code = match block {
+            0 => provider.get_code_at(executor).await?,
+            _ => provider.get_code_at(executor).number(block).await?,
+        };
+        let deployed = !code.is_empty();
+        let overrides = self.state_overrides(from, executor, deployed);
+
+        let calls: Vec<Value> = txs.iter()
+            .map(|tx| json!({
+                "from": from,
+                "to": tx.to,
+                "input": tx.calldata,
+                "value": format!("0x{:x}", tx.value),
+                "gas": format!("0x{:x}", tx.gas_limit),
+            }))
+            .collect();
+
+        let payload = json!({
+            "blockStateCalls": [{ "stateOverrides": overrides, "calls": calls }],
+            "validation": false,
+        });
+        let simulated: Result<Value, _> = provider.raw_request(
+            "eth_simulateV1".into(),
+            (payload, block_tag.clone()),
+        ).await;
+
+        match simulated {
+            Ok(blocks) => parse_simulate_response(&blocks),
+            Err(e) if calls.len() != 1 => Err(eyre!(
+                "eth_simulateV1 unavailable ({}); eth_call runs a single transaction, not {}",
+                e, calls.len()
+            )),
+            Err(e) => {
+                debug!("eth_simulateV1 unavailable ({}), falling back to eth_call", e);
+                let output: Result<Bytes, _> = provider.raw_request(
+                    "eth_call".into(),
+                    (&calls[0], block_tag, overrides),
+                ).await;
+                Ok(vec![match output {
+                    Ok(_) => CallSimResult {
+                        success: true,
+                        gas_used: None,
+                        error: None,
+                        executions: Vec::new(),
+                    },
+                    Err(e) => CallSimResult {
+                        success: false,
+                        gas_used: None,
+                        error: Some(e.to_string()),
+                        executions: Vec::new(),
+                    },
+                }])
+            }
+        }
+    }
+
+    /// Funded sender and executor; executor code and owner when not deployed
+    fn state_overrides(&self, from: Address, executor: Address, deployed: bool) -> Value {
+        let balance = format!("0x{:x}", OVERRIDE_BALANCE_WEI);
+        let mut executor_override = json!({ "balance": balance });
+        if let (false, Some(code)) = (deployed, &self.executor_code) {
+            executor_override["code"] = json!(code);
+            let mut state_diff = serde_json::Map::new();
+            state_diff.insert(
+                B256::from(EXECUTOR_OWNER_SLOT).to_string(),
+                json!(B256::left_padding_from(from.as_slice())),
+            );
+            executor_override["stateDiff"] = Value::Object(state_diff);
+        }
+
+        let mut overrides = json!({});
+        overrides[executor.to_string()] = executor_override;
+        if from != executor {
+            overrides[from.to_string()] = json!({ "balance": balance });
+        }
+        overrides
+    }
+}
+
+/// Results of the first simulated block of an eth_simulateV1 response
+fn parse_simulate_response(blocks: &Value) -> Result<Vec<CallSimResult>> {
+    let calls = blocks.get(0)
+        .and_then(|b| b.get("calls"))
+        .and_then(Value::as_array)
+        .ok_or_else(|| eyre!("Malformed eth_simulateV1 response"))?;
+
+    calls.iter()
+        .map(|call| {
+            let return_data: Bytes = serde_json::from_value(call["returnData"].clone()).unwrap_or_default();
+            let success = call["status"].as_str() == Some("0x1");
+            let gas_used = call["gasUsed"].as_str()
+                .and_then(|g| u64::from_str_radix(g.trim_start_matches("0x"), 16).ok());
+            let logs: Vec<Log> = serde_json::from_value(call["logs"].clone()).unwrap_or_default();
+            let error = (!success).then(|| {
+                call["error"]["message"].as_str()
+                    .map(str::to_string)
+                    .or_else(|| decode_revert_reason(&return_data))
+                    .unwrap_or_else(|| "reverted".to_string())
+            });
+
+            Ok(CallSimResult {
+                success,
+                gas_used,
+                error,
+                executions: logs.iter()
+                    .filter_map(|log| ArbitrageExecuted::decode_log_data(&log.data).ok())
+                    .map(|e| ArbitrageExecution { token: e.token, input_amount: e.inputAmount, profit: e.profit })
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_simulate_response() {
+        let token = Address::repeat_byte(0x11);
+        let event = ArbitrageExecuted { token, inputAmount: U256::from(1000), profit: U256::from(25) };
+        let log = Log { address: SCRATCH_EXECUTOR, data: event.encode_log_data() };
+
+        let response = json!([{
+            "calls": [
+                { "status": "0x1", "returnData": "0x19", "gasUsed": "0x2710", "logs": [log] },
+                { "status": "0x0", "returnData": "0x", "gasUsed": "0x5208", "logs": [],
+                  "error": { "code": 3, "message": "execution reverted: Insufficient output" } },
+            ]
+        }]);
+
+        let results = parse_simulate_response(&response).unwrap();
+        assert!(results[0].success);
+        assert_eq!(results[0].gas_used, Some(10_000));
+        assert_eq!(results[0].executions, vec![ArbitrageExecution {
+            token,
+            input_amount: U256::from(1000),
+            profit: U256::from(25),
+        }]);
+        assert!(!results[1].success);
+        assert_eq!(results[1].error.as_deref(), Some("execution reverted: Insufficient output"));
+    }
+}
@@ -3,12 +3,12 @@
 //! This module handles the Flash Loan integration for executing arbitrage.
 //! Currently supports:
 //! - Balancer V2 (0% fee - recommended!)
-//! - Aave V3 (0.05% fee)
+//! - Aave V3 (`flashLoanSimple`, live FLASHLOAN_PREMIUM_TOTAL, nominally 0.05%)
+//! - Uniswap V3 flash swaps (the lending pool's fee tier)
 //!
 //! The executor contract must be deployed on-chain before production use.
 
-use alloy_primitives::{Address, Bytes, U256, address};
-use alloy_sol_types::{sol, SolCall};
+use alloy_primitives::{Address, Bytes, U256};
 use alloy_provider::{Provider, ProviderBuilder};
 use alloy_rpc_types::TransactionRequest;
 use eyre::{eyre, Result};
@@ -16,102 +16,17 @@ use tracing::{debug, info, warn};
 
 use crate::brain::ArbitrageCycle;
 use crate::config::{Config, FlashLoanProvider};
+use super::liquidity::LenderQuote;
+use super::actions::ActionResolver;
+use super::plan::{lender_id, Plan};
+use crate::gas_oracle::FeeRecommendation;
+use crate::simulator::SplitRoute;
+use crate::swap_gas::SwapGasModel;
 use crate::cartographer::Dex;
 
-// ============================================
-// CONTRACT ADDRESSES (Ethereum Mainnet)
-// ============================================
-
-/// Balancer V2 Vault (0% flash loan fee!)
-const BALANCER_VAULT: Address = address!("BA12222222228d8Ba445958a75a0704d566BF2C8");
-
-/// Aave V3 Pool (0.05% fee)
-const AAVE_V3_POOL: Address = address!("87870Bca3F3fD6335C3F4ce8392D69350B4fA4E2");
-
-/// Uniswap V3 Factory (for flash swaps)
-const UNISWAP_V3_FACTORY: Address = address!("1F98431c8aD98523631AE4a59f267346ea31F984");
-
-// ============================================
-// SOLIDITY INTERFACES
-// ============================================
-
-sol! {
-    /// Balancer V2 Vault interface for flash loans
-    interface IBalancerVault {
-        function flashLoan(
-            address recipient,
-            address[] memory tokens,
-            uint256[] memory amounts,
-            bytes memory userData
-        ) external;
-    }
-    
-    /// Aave V3 Pool interface for flash loans
-    interface IAavePool {
-        function flashLoan(
-            address receiverAddress,
-            address[] calldata assets,
-            uint256[] calldata amounts,
-            uint256[] calldata interestRateModes,
-            address onBehalfOf,
-            bytes calldata params,
-            uint16 referralCode
-        ) external;
-    }
-    
-    /// Our executor contract interface (matches ArbitrageExecutor.sol)
-    interface IArbitrageExecutor {
-        /// Execute an arbitrage cycle with a flash loan
-        /// @param path Token addresses [A, B, C, A] - must start and end same
-        /// @param swapInfo Packed array: each element = (fee << 8) | dexType
-        /// @param amount Flash loan amount
-        /// @param minOut Minimum output (should be > amount for profit)
-        function execute(
-            address[] calldata path,
-            uint32[] calldata swapInfo,
-            uint256 amount,
-            uint256 minOut
-        ) external returns (uint256 profit);
-        
-        /// Withdraw accumulated profits
-        function withdraw(address token) external;
-        
-        /// Withdraw ETH
-        function withdrawETH() external;
-    }
-    
-    /// Uniswap V3 Router for swaps
-    interface ISwapRouter {
-        struct ExactInputSingleParams {
-            address tokenIn;
-            address tokenOut;
-            uint24 fee;
-            address recipient;
-            uint256 deadline;
-            uint256 amountIn;
-            uint256 amountOutMinimum;
-            uint160 sqrtPriceLimitX96;
-        }
-        
-        function exactInputSingle(ExactInputSingleParams calldata params)
-            external payable returns (uint256 amountOut);
-    }
-    
-    /// Uniswap V2 Router for swaps
-    interface IUniswapV2Router {
-        function swapExactTokensForTokens(
-            uint256 amountIn,
-            uint256 amountOutMin,
-            address[] calldata path,
-            address to,
-            uint256 deadline
-        ) external returns (uint256[] memory amounts);
-    }
-}
-
 /// DEX type enum for the executor contract
 #[repr(u8)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DexType {
     UniswapV3 = 0,
     UniswapV2 = 1,
@@ -119,12 +34,15 @@ pub enum DexType {
     PancakeSwapV3 = 3,
     BalancerV2 = 4,
     Curve = 5,
+    SushiswapV3 = 6,
+    Erc4626 = 7,
 }
 
 impl From<Dex> for DexType {
     fn from(dex: Dex) -> Self {
         match dex {
-            Dex::UniswapV3 | Dex::SushiswapV3 => DexType::UniswapV3,
+            Dex::UniswapV3 => DexType::UniswapV3,
+            Dex::SushiswapV3 => DexType::SushiswapV3,
             Dex::UniswapV2 => DexType::UniswapV2,
             Dex::SushiswapV2 => DexType::SushiswapV2,
             Dex::PancakeSwapV3 => DexType::PancakeSwapV3,
@@ -134,27 +52,65 @@ impl From<Dex> for DexType {
     }
 }
 
+impl DexType {
+    /// DEX type of a graph edge; ERC-4626 virtual pools reuse `Dex::BalancerV2`
+    /// with the vault as the pool, on one side of the swap
+    pub fn for_edge(dex: Dex, pool: Address, token_in: Address, token_out: Address) -> Self {
+        match dex {
+            Dex::BalancerV2 if pool == token_in || pool == token_out => DexType::Erc4626,
+            _ => DexType::from(dex),
+        }
+    }
+}
+
 // ============================================
 // FLASH LOAN BUILDER
 // ============================================
 
 /// Builds and encodes flash loan transactions
+#[derive(Clone)]
 pub struct FlashLoanBuilder {
     provider: FlashLoanProvider,
+    /// Flash loan fee, parts per million
+    fee_ppm: u32,
+    /// Uniswap V3 pool lending via `flash` (UniswapV3 provider only)
+    lender_pool: Option<Address>,
     executor_address: Option<Address>,
+    gas_model: SwapGasModel,
+    /// Per-hop output tolerance for plan leg minimums
+    max_slippage: f64,
+    /// Curve / Balancer leg parameters (shared cache)
+    actions: ActionResolver,
 }
 
 impl FlashLoanBuilder {
     pub fn new(config: &Config) -> Self {
         Self {
             provider: config.flash_loan_provider,
+            fee_ppm: default_fee_ppm(config.flash_loan_provider),
+            lender_pool: None,
             executor_address: config
                 .executor_contract_address
                 .as_ref()
                 .and_then(|s| s.parse().ok()),
+            gas_model: SwapGasModel::new(config.flash_loan_provider),
+            max_slippage: config.max_slippage,
+            actions: ActionResolver::new(config.rpc_url.clone()),
         }
     }
     
+    /// Size gas limits with a (shared, calibrated) gas model
+    pub fn with_gas_model(mut self, gas_model: SwapGasModel) -> Self {
+        self.gas_model = gas_model;
+        self
+    }
+    
+    /// Encode for an executor at `executor` (e.g. a simulation scratch address)
+    pub fn with_executor_address(mut self, executor: Address) -> Self {
+        self.executor_address = Some(executor);
+        self
+    }
+    
     /// Check if we have an executor configured
     pub fn has_executor(&self) -> bool {
         self.executor_address.is_some()
@@ -165,150 +121,138 @@ impl FlashLoanBuilder {
         self.executor_address
     }
     
-    /// Build a flash loan transaction for the given arbitrage cycle
-    pub fn build_flash_loan_tx(
+    /// Builder borrowing from `lender` instead of the configured provider,
+    /// with its live fee (a Uniswap V3 lender's fee is its pool's fee tier)
+    pub fn for_lender(&self, lender: &LenderQuote) -> Self {
+        Self {
+            provider: lender.provider,
+            fee_ppm: lender.fee_ppm,
+            lender_pool: (lender.provider == FlashLoanProvider::UniswapV3).then_some(lender.source),
+            ..self.clone()
+        }
+    }
+    
+    /// Build the executor plan (legs only) for the given arbitrage cycle
+    ///
+    /// With a split `route`, hops are executed across all of their pool legs.
+    /// Curve / Balancer leg parameters are resolved on-chain (cached). The
+    /// lender and final minimum are set by `build_flash_loan_tx`.
+    pub async fn build_plan(
         &self,
         cycle: &ArbitrageCycle,
+        route: Option<&SplitRoute>,
         input_amount: U256,
-        min_profit: U256,
+    ) -> Result<Plan> {
+        let mut plan = self.unresolved_plan(cycle, route, input_amount, U256::ZERO)?;
+        self.actions.resolve(&mut plan).await?;
+        Ok(plan)
+    }
+    
+    /// Build a flash loan transaction executing `plan`, borrowing from this
+    /// builder's lender and requiring `min_output` back
+    ///
+    /// The executor takes the flash loan itself (so only it can start its own
+    /// callback), so the transaction always goes to the executor.
+    pub fn build_flash_loan_tx(
+        &self,
+        plan: &Plan,
+        min_output: U256,
+        cycle: &ArbitrageCycle,
+        route: Option<&SplitRoute>,
     ) -> Result<FlashLoanTransaction> {
         let executor = self.executor_address
             .ok_or_else(|| eyre!("Executor contract address not configured"))?;
         
-        // Get the starting token
-        let start_token = cycle.path[0];
-        
-        // Build the arbitrage execution calldata
-        let arb_calldata = self.build_arbitrage_calldata(cycle, input_amount, min_profit)?;
-        
-        // Build the flash loan request based on provider
-        let (to, calldata) = match self.provider {
-            FlashLoanProvider::BalancerV2 => {
-                self.build_balancer_flash_loan(executor, start_token, input_amount, arb_calldata)?
-            }
-            FlashLoanProvider::AaveV3 => {
-                self.build_aave_flash_loan(executor, start_token, input_amount, arb_calldata)?
-            }
-            FlashLoanProvider::UniswapV3 => {
-                // For Uniswap, we don't use a separate flash loan
-                // The executor handles flash swaps internally
-                (executor, arb_calldata)
-            }
+        let lender_pool = match self.provider {
+            FlashLoanProvider::UniswapV3 => self.lender_pool
+                .ok_or_else(|| eyre!("Uniswap V3 flash swap needs a lender pool"))?,
+            _ => Address::ZERO,
+        };
+        let plan = Plan {
+            lender: lender_id(self.provider),
+            lenderPool: lender_pool,
+            minOut: min_output,
+            ..plan.clone()
         };
         
-        // Estimate gas (rough estimate, will be refined by simulation)
-        let gas_estimate = self.estimate_gas(cycle);
+        // Gas limit from the calibrated model
+        let gas_estimate = match route {
+            Some(route) => self.gas_model.route_gas_limit(route),
+            None => self.gas_model.gas_limit(cycle, &[]),
+        };
         
         Ok(FlashLoanTransaction {
-            to,
-            calldata,
+            to: executor,
+            calldata: plan.execute_calldata(),
             value: U256::ZERO,
             gas_limit: gas_estimate,
             provider: self.provider,
         })
     }
     
-    /// Estimate gas for a cycle
-    fn estimate_gas(&self, cycle: &ArbitrageCycle) -> u64 {
-        let base_gas = 100_000u64; // Base overhead
-        let per_swap_v3 = 150_000u64;
-        let per_swap_v2 = 100_000u64;
-        
-        let swap_gas: u64 = cycle.dexes.iter().map(|dex| {
-            match dex {
-                Dex::UniswapV3 | Dex::SushiswapV3 | Dex::PancakeSwapV3 => per_swap_v3,
-                _ => per_swap_v2,
-            }
-        }).sum();
-        
-        base_gas + swap_gas
-    }
-    
-    /// Build Balancer V2 flash loan calldata
-    fn build_balancer_flash_loan(
-        &self,
-        recipient: Address,
-        token: Address,
-        amount: U256,
-        user_data: Bytes,
-    ) -> Result<(Address, Bytes)> {
-        let call = IBalancerVault::flashLoanCall {
-            recipient,
-            tokens: vec![token],
-            amounts: vec![amount],
-            userData: user_data,
-        };
-        
-        Ok((BALANCER_VAULT, Bytes::from(call.abi_encode())))
-    }
-    
-    /// Build Aave V3 flash loan calldata
-    fn build_aave_flash_loan(
+    /// Build the calldata the executor transaction would carry, for L1 data fee
+    /// estimation on rollups
+    ///
+    /// Works without a deployed executor (a placeholder address is encoded), so
+    /// it can be used during scanning in simulation mode. Curve / Balancer leg
+    /// data is left unresolved (a few words short of the real calldata).
+    /// `min_profit` is in start token units, priced by the caller.
+    pub fn estimate_calldata(
         &self,
-        recipient: Address,
-        token: Address,
-        amount: U256,
-        params: Bytes,
+        cycle: &ArbitrageCycle,
+        route: Option<&SplitRoute>,
+        input_amount: U256,
+        min_profit: U256,
     ) -> Result<(Address, Bytes)> {
-        let call = IAavePool::flashLoanCall {
-            receiverAddress: recipient,
-            assets: vec![token],
-            amounts: vec![amount],
-            interestRateModes: vec![U256::ZERO], // No debt
-            onBehalfOf: recipient,
-            params,
-            referralCode: 0,
-        };
-        
-        Ok((AAVE_V3_POOL, Bytes::from(call.abi_encode())))
+        let executor = self.executor_address.unwrap_or(Address::repeat_byte(0xee));
+        let min_output = self.calculate_min_output(input_amount, min_profit);
+        let plan = self.unresolved_plan(cycle, route, input_amount, min_output)?;
+        Ok((executor, plan.execute_calldata()))
     }
     
-    /// Build the arbitrage execution calldata
-    fn build_arbitrage_calldata(
+    /// Plan with pools and amounts, before Curve / Balancer leg data
+    fn unresolved_plan(
         &self,
         cycle: &ArbitrageCycle,
+        route: Option<&SplitRoute>,
         input_amount: U256,
         min_output: U256,
-    ) -> Result<Bytes> {
-        // Convert cycle data to contract-compatible format
-        let path: Vec<Address> = cycle.path.clone();
-        
-        // Pack dexType and fee into single u32: (fee << 8) | dexType
-        let swap_info: Vec<u32> = cycle.dexes.iter()
-            .zip(cycle.fees.iter())
-            .map(|(dex, &fee)| {
-                let dex_type = DexType::from(*dex) as u32;
-                let fee_u32 = fee.min(10000);
-                (fee_u32 << 8) | dex_type
-            })
-            .collect();
-        
-        let call = IArbitrageExecutor::executeCall {
-            path,
-            swapInfo: swap_info,
-            amount: input_amount,
-            minOut: min_output,
-        };
-        
-        Ok(Bytes::from(call.abi_encode()))
+    ) -> Result<Plan> {
+        match route {
+            Some(route) => Plan::from_route(self.provider, route, input_amount, min_output, self.max_slippage),
+            None => Plan::from_cycle(self.provider, cycle, input_amount, min_output),
+        }
+    }
+    
+    /// Builder with a flash loan fee read on-chain (e.g. Aave's live premium)
+    pub fn with_fee_ppm(mut self, fee_ppm: u32) -> Self {
+        self.fee_ppm = fee_ppm;
+        self
     }
     
-    /// Calculate the minimum output amount (input + min_profit - flash loan fee)
+    /// Calculate the minimum output amount (input + min_profit + flash loan fee)
+    ///
+    /// The fee is rounded up: lenders round the premium in their favour.
     pub fn calculate_min_output(
         &self,
         input_amount: U256,
         min_profit: U256,
     ) -> U256 {
-        let fee = match self.provider {
-            FlashLoanProvider::BalancerV2 => U256::ZERO, // 0% fee!
-            FlashLoanProvider::AaveV3 => input_amount * U256::from(5) / U256::from(10000), // 0.05%
-            FlashLoanProvider::UniswapV3 => input_amount * U256::from(3) / U256::from(1000), // ~0.3%
-        };
+        let fee = (input_amount * U256::from(self.fee_ppm)).div_ceil(U256::from(1_000_000u32));
         
         input_amount + min_profit + fee
     }
 }
 
+/// Nominal flash loan fee (ppm) until live lender data says otherwise
+fn default_fee_ppm(provider: FlashLoanProvider) -> u32 {
+    match provider {
+        FlashLoanProvider::BalancerV2 => 0,      // 0% fee!
+        FlashLoanProvider::AaveV3 => 500,        // 0.05%, until FLASHLOAN_PREMIUM_TOTAL is read
+        FlashLoanProvider::UniswapV3 => 3_000,   // ~0.3%, until a pool is picked
+    }
+}
+
 /// Represents a flash loan transaction ready for submission
 #[derive(Debug, Clone)]
 pub struct FlashLoanTransaction {
@@ -339,7 +283,7 @@ impl FlashLoanTransaction {
     }
     
     /// Convert to a TransactionRequest for signing
-    pub fn to_transaction_request(&self, from: Address, nonce: u64, gas_price: u128) -> TransactionRequest {
+    pub fn to_transaction_request(&self, from: Address, nonce: u64, fees: &FeeRecommendation) -> TransactionRequest {
         TransactionRequest::default()
             .from(from)
             .to(self.to)
@@ -347,8 +291,8 @@ impl FlashLoanTransaction {
             .value(self.value)
             .nonce(nonce)
             .gas_limit(self.gas_limit)
-            .max_fee_per_gas(gas_price)
-            .max_priority_fee_per_gas(gas_price / 10) // 10% priority fee
+            .max_fee_per_gas(fees.max_fee_per_gas)
+            .max_priority_fee_per_gas(fees.max_priority_fee_per_gas)
     }
 }
 
@@ -379,54 +323,131 @@ interface IFlashLoanRecipient {
     ) external;
 }
 
+interface IAavePool {
+    function flashLoanSimple(
+        address receiverAddress,
+        address asset,
+        uint256 amount,
+        bytes calldata params,
+        uint16 referralCode
+    ) external;
+}
+
 interface IBalancerVault {
+    enum SwapKind { GIVEN_IN, GIVEN_OUT }
+    
+    struct SingleSwap {
+        bytes32 poolId;
+        SwapKind kind;
+        address assetIn;
+        address assetOut;
+        uint256 amount;
+        bytes userData;
+    }
+    
+    struct BatchSwapStep {
+        bytes32 poolId;
+        uint256 assetInIndex;
+        uint256 assetOutIndex;
+        uint256 amount;
+        bytes userData;
+    }
+    
+    struct FundManagement {
+        address sender;
+        bool fromInternalBalance;
+        address payable recipient;
+        bool toInternalBalance;
+    }
+    
     function flashLoan(
         IFlashLoanRecipient recipient,
         IERC20[] memory tokens,
         uint256[] memory amounts,
         bytes memory userData
     ) external;
+    
+    function swap(
+        SingleSwap memory singleSwap,
+        FundManagement memory funds,
+        uint256 limit,
+        uint256 deadline
+    ) external payable returns (uint256 amountCalculated);
+    
+    function batchSwap(
+        SwapKind kind,
+        BatchSwapStep[] memory swaps,
+        address[] memory assets,
+        FundManagement memory funds,
+        int256[] memory limits,
+        uint256 deadline
+    ) external payable returns (int256[] memory assetDeltas);
 }
 
-// DEX Router interfaces
-interface IUniswapV3Router {
-    struct ExactInputSingleParams {
-        address tokenIn;
-        address tokenOut;
-        uint24 fee;
-        address recipient;
-        uint256 deadline;
-        uint256 amountIn;
-        uint256 amountOutMinimum;
-        uint160 sqrtPriceLimitX96;
-    }
-    function exactInputSingle(ExactInputSingleParams calldata params) external payable returns (uint256);
+// Pool interfaces (swaps go directly to the pools)
+interface IUniswapV2Pair {
+    function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast);
+    function token0() external view returns (address);
+    function swap(uint256 amount0Out, uint256 amount1Out, address to, bytes calldata data) external;
 }
 
-interface IUniswapV2Router {
-    function swapExactTokensForTokens(
-        uint256 amountIn,
-        uint256 amountOutMin,
-        address[] calldata path,
-        address to,
-        uint256 deadline
-    ) external returns (uint256[] memory amounts);
+interface IUniswapV3Pool {
+    function token0() external view returns (address);
+    function flash(address recipient, uint256 amount0, uint256 amount1, bytes calldata data) external;
+    function swap(
+        address recipient,
+        bool zeroForOne,
+        int256 amountSpecified,
+        uint160 sqrtPriceLimitX96,
+        bytes calldata data
+    ) external returns (int256 amount0, int256 amount1);
+}
+
+// Curve pools: stable pools take int128 coin indices, crypto pools uint256
+interface ICurveStablePool {
+    function exchange(int128 i, int128 j, uint256 dx, uint256 minDy) external;
+    function exchange_received(int128 i, int128 j, uint256 dx, uint256 minDy) external returns (uint256);
+}
+
+interface ICurveCryptoPool {
+    function exchange(uint256 i, uint256 j, uint256 dx, uint256 minDy) external;
+}
+
+interface IERC4626 {
+    function deposit(uint256 assets, address receiver) external returns (uint256 shares);
+    function redeem(uint256 shares, address receiver, address owner) external returns (uint256 assets);
+}
+
+interface IWETH {
+    function withdraw(uint256 amount) external;
 }
 
 /**
  * @title ArbitrageExecutor
- * @notice Executes arbitrage opportunities via flash loans
- * @dev Uses Balancer V2 flash loans (0% fee) by default
+ * @notice Executes arbitrage plans funded by flash loans
+ * @dev The executor starts the flash loan itself and only accepts the
+ *      callback for the plan it is running. Swaps go directly to pools:
+ *      V2 pairs via `swap`, V3 pools (Uniswap, SushiSwap, PancakeSwap) via
+ *      their swap callbacks, Curve via `exchange` / `exchange_received`,
+ *      Balancer via the Vault's `swap` / `batchSwap`, and ERC-4626 vaults
+ *      via `deposit` / `redeem`. Once the loan is repaid, the plan's bribe is
+ *      paid to `block.coinbase` out of the profit.
  */
 contract ArbitrageExecutor is IFlashLoanRecipient, Ownable, ReentrancyGuard, Pausable {
     using SafeERC20 for IERC20;
     
+    // Plan layout version (first byte of `execute` calldata)
+    uint8 public constant PLAN_VERSION = 4;
+    
     // Constants
+    address public constant WETH = 0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2;
     IBalancerVault public constant BALANCER_VAULT = IBalancerVault(0xBA12222222228d8Ba445958a75a0704d566BF2C8);
-    IUniswapV3Router public constant UNISWAP_V3_ROUTER = IUniswapV3Router(0xE592427A0AEce92De3Edee1F18E0157C05861564);
-    IUniswapV2Router public constant UNISWAP_V2_ROUTER = IUniswapV2Router(0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D);
-    IUniswapV2Router public constant SUSHISWAP_ROUTER = IUniswapV2Router(0xd9e1cE17f2641f24aE83637ab66a2cca9C378B9F);
-    IUniswapV3Router public constant PANCAKE_V3_ROUTER = IUniswapV3Router(0x1b81D678ffb9C0263b24A97847620C99d213eB14);
+    IAavePool public constant AAVE_POOL = IAavePool(0x87870Bca3F3fD6335C3F4ce8392D69350B4fA4E2);
+    
+    // Lenders
+    uint8 constant LENDER_BALANCER = 0;
+    uint8 constant LENDER_AAVE = 1;
+    uint8 constant LENDER_UNISWAP_V3 = 2;
     
     // DEX types
     uint8 constant DEX_UNISWAP_V3 = 0;
@@ -434,61 +455,160 @@ contract ArbitrageExecutor is IFlashLoanRecipient, Ownable, ReentrancyGuard, Pau
     uint8 constant DEX_SUSHISWAP_V2 = 2;
     uint8 constant DEX_PANCAKE_V3 = 3;
     uint8 constant DEX_BALANCER_V2 = 4;
+    uint8 constant DEX_CURVE = 5;
+    uint8 constant DEX_SUSHISWAP_V3 = 6;
+    uint8 constant DEX_ERC4626 = 7;
+    
+    // Curve exchange flavours (first word of a Curve leg's data)
+    uint8 constant CURVE_STABLE = 0;
+    uint8 constant CURVE_CRYPTO = 1;
+    uint8 constant CURVE_STABLE_RECEIVED = 2;
+    
+    // V3 price limits (TickMath.MIN_SQRT_RATIO + 1 / MAX_SQRT_RATIO - 1)
+    uint160 constant MIN_SQRT_RATIO_LIMIT = 4295128740;
+    uint160 constant MAX_SQRT_RATIO_LIMIT = 1461446703485210103287273052203988822378723970341;
+    
+    // One pool a hop is routed through
+    struct Leg {
+        uint8 dexType;
+        address pool;
+        uint24 fee;             // parts per million (V2 output math)
+        uint16 shareBps;        // share of the hop input; last leg takes the remainder
+        uint256 minAmountOut;   // minimum output of this leg
+        bytes data;             // Curve: (kind, i, j); Balancer: (poolIds, assets); else empty
+    }
+    
+    struct Hop {
+        address tokenIn;
+        address tokenOut;
+        Leg[] legs;
+    }
+    
+    // Paid to the block builder out of the profit, after the loan is repaid
+    struct Bribe {
+        uint256 value;          // wei to block.coinbase (0 = none)
+        uint256 amountIn;       // profit tokens spent on it
+        Hop[] hops;             // profit token -> WETH (empty when the token is WETH)
+    }
+    
+    struct Plan {
+        uint8 lender;
+        address lenderPool;     // Uniswap V3 pool lending via `flash` (else zero)
+        address token;
+        uint256 amount;
+        uint256 minOut;         // input + fee + min profit + bribe.amountIn
+        Hop[] hops;
+        Bribe bribe;
+    }
     
     // Events
     event ArbitrageExecuted(address indexed token, uint256 inputAmount, uint256 profit);
+    event CoinbasePaid(address indexed coinbase, uint256 amount);
     event ProfitWithdrawn(address indexed token, uint256 amount);
     
     // Profit tracking
     mapping(address => uint256) public accumulatedProfits;
     
+    // Hash of the plan being run; flash loan callbacks must carry it
+    bytes32 private _activePlan;
+    
+    // V3 pool currently swapping; the only caller allowed into the swap callback
+    address private _activeV3Pool;
+    
+    // V3 pool currently lending; the only caller allowed into the flash callback
+    address private _activeFlashPool;
+    
     constructor() Ownable(msg.sender) {}
     
     /**
-     * @notice Execute an arbitrage opportunity
-     * @param path Token path for the arbitrage
-     * @param pools Pool addresses for each swap
-     * @param dexTypes DEX type for each swap
-     * @param inputAmount Amount to borrow via flash loan
-     * @param minOutput Minimum output required (input + fee + min profit)
+     * @notice Execute an arbitrage plan
+     * @param plan PLAN_VERSION byte followed by abi.encode(Plan)
      */
-    function executeArbitrage(
-        address[] calldata path,
-        address[] calldata pools,
-        uint8[] calldata dexTypes,
-        uint256 inputAmount,
-        uint256 minOutput
-    ) external onlyOwner nonReentrant whenNotPaused returns (uint256 profit) {
-        require(path.length >= 2, "Invalid path");
-        require(path.length - 1 == pools.length, "Path/pools mismatch");
-        require(pools.length == dexTypes.length, "Pools/dexTypes mismatch");
-        
-        // Encode arbitrage data for callback
-        bytes memory userData = abi.encode(path, pools, dexTypes, minOutput);
-        
-        // Request flash loan
-        IERC20[] memory tokens = new IERC20[](1);
-        tokens[0] = IERC20(path[0]);
-        
-        uint256[] memory amounts = new uint256[](1);
-        amounts[0] = inputAmount;
+    function execute(bytes calldata plan) external onlyOwner nonReentrant whenNotPaused returns (uint256 profit) {
+        require(plan.length > 1 && uint8(plan[0]) == PLAN_VERSION, "Unsupported plan version");
+        Plan memory p = abi.decode(plan[1:], (Plan));
+        require(p.hops.length >= 1, "Empty plan");
+        require(
+            p.hops[0].tokenIn == p.token && p.hops[p.hops.length - 1].tokenOut == p.token,
+            "Plan must be a cycle"
+        );
+        if (p.bribe.hops.length > 0) {
+            require(
+                p.bribe.hops[0].tokenIn == p.token && p.bribe.hops[p.bribe.hops.length - 1].tokenOut == WETH,
+                "Bribe must swap to WETH"
+            );
+        } else {
+            require(p.bribe.value == 0 || p.token == WETH, "Bribe needs a swap to WETH");
+        }
         
-        // Get initial balance
-        uint256 balanceBefore = tokens[0].balanceOf(address(this));
+        bytes memory userData = abi.encode(p);
+        uint256 balanceBefore = IERC20(p.token).balanceOf(address(this));
         
-        // Execute flash loan (callback will do the swaps)
-        BALANCER_VAULT.flashLoan(this, tokens, amounts, userData);
+        _activePlan = keccak256(userData);
+        if (p.lender == LENDER_BALANCER) {
+            IERC20[] memory tokens = new IERC20[](1);
+            tokens[0] = IERC20(p.token);
+            uint256[] memory amounts = new uint256[](1);
+            amounts[0] = p.amount;
+            
+            // Callback runs the plan and repays
+            BALANCER_VAULT.flashLoan(this, tokens, amounts, userData);
+        } else if (p.lender == LENDER_AAVE) {
+            // Plans borrow a single asset, so flashLoanSimple (premium = FLASHLOAN_PREMIUM_TOTAL)
+            AAVE_POOL.flashLoanSimple(address(this), p.token, p.amount, userData, 0);
+        } else if (p.lender == LENDER_UNISWAP_V3) {
+            // Flash swap: the pool lends one side and charges its fee tier
+            bool isToken0 = IUniswapV3Pool(p.lenderPool).token0() == p.token;
+            _activeFlashPool = p.lenderPool;
+            IUniswapV3Pool(p.lenderPool).flash(
+                address(this),
+                isToken0 ? p.amount : 0,
+                isToken0 ? 0 : p.amount,
+                userData
+            );
+            _activeFlashPool = address(0);
+        } else {
+            revert("Unsupported lender");
+        }
+        _activePlan = bytes32(0);
         
         // Calculate profit
-        uint256 balanceAfter = tokens[0].balanceOf(address(this));
+        uint256 balanceAfter = IERC20(p.token).balanceOf(address(this));
         require(balanceAfter >= balanceBefore, "Arbitrage failed");
         
         profit = balanceAfter - balanceBefore;
-        accumulatedProfits[path[0]] += profit;
         
-        emit ArbitrageExecuted(path[0], inputAmount, profit);
+        // Only ever paid out of profit: minOut already covers amountIn
+        if (p.bribe.value > 0) {
+            require(profit >= p.bribe.amountIn, "Bribe exceeds profit");
+            profit -= p.bribe.amountIn;
+            _payCoinbase(p.bribe);
+        }
+        accumulatedProfits[p.token] += profit;
         
-        return profit;
+        emit ArbitrageExecuted(p.token, p.amount, profit);
+    }
+    
+    /**
+     * @notice Swap the bribe's profit tokens to WETH, unwrap and pay the coinbase
+     */
+    function _payCoinbase(Bribe memory bribe) internal {
+        uint256 wethAmount = bribe.amountIn;
+        for (uint256 i = 0; i < bribe.hops.length; i++) {
+            wethAmount = _executeHop(bribe.hops[i], wethAmount);
+        }
+        require(wethAmount >= bribe.value, "Bribe swap output too low");
+        
+        // Surplus from the swap stays as WETH profit
+        if (wethAmount > bribe.value) {
+            accumulatedProfits[WETH] += wethAmount - bribe.value;
+        }
+        
+        IWETH(WETH).withdraw(bribe.value);
+        (bool sent, ) = block.coinbase.call{value: bribe.value}("");
+        require(sent, "Coinbase payment failed");
+        
+        emit CoinbasePaid(block.coinbase, bribe.value);
     }
     
     /**
@@ -501,102 +621,298 @@ contract ArbitrageExecutor is IFlashLoanRecipient, Ownable, ReentrancyGuard, Pau
         bytes memory userData
     ) external override {
         require(msg.sender == address(BALANCER_VAULT), "Only Balancer Vault");
+        require(keccak256(userData) == _activePlan, "Unexpected flash loan");
         
-        // Decode arbitrage parameters
-        (
-            address[] memory path,
-            address[] memory pools,
-            uint8[] memory dexTypes,
-            uint256 minOutput
-        ) = abi.decode(userData, (address[], address[], uint8[], uint256));
-        
-        // Execute the swap chain
-        uint256 currentAmount = amounts[0];
-        
-        for (uint256 i = 0; i < pools.length; i++) {
-            currentAmount = _executeSwap(
-                path[i],
-                path[i + 1],
-                pools[i],
-                dexTypes[i],
-                currentAmount
-            );
+        Plan memory p = abi.decode(userData, (Plan));
+        _runPlan(p, amounts[0]);
+        
+        // Repay flash loan (Balancer has 0% fee!)
+        tokens[0].safeTransfer(address(BALANCER_VAULT), amounts[0] + feeAmounts[0]);
+    }
+    
+    /**
+     * @notice Callback from Aave V3 `flashLoanSimple`; Aave pulls amount + premium
+     */
+    function executeOperation(
+        address asset,
+        uint256 amount,
+        uint256 premium,
+        address initiator,
+        bytes calldata params
+    ) external returns (bool) {
+        require(msg.sender == address(AAVE_POOL), "Only Aave Pool");
+        require(initiator == address(this), "Unexpected initiator");
+        require(keccak256(params) == _activePlan, "Unexpected flash loan");
+        
+        Plan memory p = abi.decode(params, (Plan));
+        _runPlan(p, amount);
+        
+        IERC20(asset).forceApprove(address(AAVE_POOL), amount + premium);
+        return true;
+    }
+    
+    /**
+     * @notice Callback from a Uniswap V3 / SushiSwap V3 pool's `flash`
+     */
+    function uniswapV3FlashCallback(uint256 fee0, uint256 fee1, bytes calldata data) external {
+        _repayFlashPool(fee0 + fee1, data);
+    }
+    
+    /**
+     * @notice Callback from a PancakeSwap V3 pool's `flash`
+     */
+    function pancakeV3FlashCallback(uint256 fee0, uint256 fee1, bytes calldata data) external {
+        _repayFlashPool(fee0 + fee1, data);
+    }
+    
+    function _repayFlashPool(uint256 fee, bytes calldata data) internal {
+        require(msg.sender == _activeFlashPool, "Unexpected flash callback");
+        require(keccak256(data) == _activePlan, "Unexpected flash loan");
+        
+        Plan memory p = abi.decode(data, (Plan));
+        _runPlan(p, p.amount);
+        
+        // Only one side was borrowed, so only one fee is non-zero
+        IERC20(p.token).safeTransfer(msg.sender, p.amount + fee);
+    }
+    
+    /**
+     * @notice Run every hop and check the final output
+     */
+    function _runPlan(Plan memory p, uint256 amountIn) internal {
+        uint256 currentAmount = amountIn;
+        for (uint256 i = 0; i < p.hops.length; i++) {
+            currentAmount = _executeHop(p.hops[i], currentAmount);
         }
         
         // Verify we have enough to repay + profit
-        require(currentAmount >= minOutput, "Insufficient output");
+        require(currentAmount >= p.minOut, "Insufficient output");
+    }
+    
+    /**
+     * @notice Execute one hop across its pool legs
+     */
+    function _executeHop(Hop memory hop, uint256 amountIn) internal returns (uint256 amountOut) {
+        uint256 remaining = amountIn;
         
-        // Repay flash loan (Balancer has 0% fee!)
-        uint256 amountOwed = amounts[0] + feeAmounts[0];
-        tokens[0].safeTransfer(address(BALANCER_VAULT), amountOwed);
+        for (uint256 j = 0; j < hop.legs.length; j++) {
+            Leg memory leg = hop.legs[j];
+            uint256 legIn = j == hop.legs.length - 1
+                ? remaining
+                : amountIn * leg.shareBps / 10000;
+            remaining -= legIn;
+            
+            uint256 legOut = _swap(hop.tokenIn, hop.tokenOut, leg, legIn);
+            require(legOut >= leg.minAmountOut, "Leg output below minimum");
+            amountOut += legOut;
+        }
     }
     
     /**
-     * @notice Execute a single swap on the specified DEX
+     * @notice Swap `amountIn` of `tokenIn` directly against the leg's pool
      */
-    function _executeSwap(
+    function _swap(
         address tokenIn,
         address tokenOut,
+        Leg memory leg,
+        uint256 amountIn
+    ) internal returns (uint256 amountOut) {
+        if (leg.dexType == DEX_UNISWAP_V2 || leg.dexType == DEX_SUSHISWAP_V2) {
+            return _swapV2(tokenIn, leg.pool, leg.fee, amountIn);
+        }
+        if (leg.dexType == DEX_UNISWAP_V3 || leg.dexType == DEX_PANCAKE_V3 || leg.dexType == DEX_SUSHISWAP_V3) {
+            return _swapV3(tokenIn, tokenOut, leg.pool, amountIn);
+        }
+        if (leg.dexType == DEX_CURVE) {
+            return _swapCurve(tokenIn, tokenOut, leg.pool, leg.data, amountIn);
+        }
+        if (leg.dexType == DEX_BALANCER_V2) {
+            return _swapBalancer(leg.data, amountIn);
+        }
+        if (leg.dexType == DEX_ERC4626) {
+            return _swapERC4626(tokenIn, tokenOut, leg.pool, amountIn);
+        }
+        revert("Unsupported DEX");
+    }
+    
+    /**
+     * @notice V2 pair swap: pay the pair, then take the constant-product output
+     */
+    function _swapV2(
+        address tokenIn,
         address pool,
-        uint8 dexType,
+        uint24 fee,
         uint256 amountIn
     ) internal returns (uint256 amountOut) {
-        // Approve the router
-        IERC20(tokenIn).safeApprove(_getRouter(dexType), amountIn);
-        
-        if (dexType == DEX_UNISWAP_V3 || dexType == DEX_PANCAKE_V3) {
-            // V3 swap
-            IUniswapV3Router router = dexType == DEX_UNISWAP_V3 
-                ? UNISWAP_V3_ROUTER 
-                : PANCAKE_V3_ROUTER;
-            
-            // Get fee tier from pool (simplified - in production query the pool)
-            uint24 fee = 3000; // Default to 0.3%
-            
-            IUniswapV3Router.ExactInputSingleParams memory params = IUniswapV3Router.ExactInputSingleParams({
-                tokenIn: tokenIn,
-                tokenOut: tokenOut,
-                fee: fee,
-                recipient: address(this),
-                deadline: block.timestamp,
-                amountIn: amountIn,
-                amountOutMinimum: 0, // We check final output
-                sqrtPriceLimitX96: 0
-            });
-            
-            amountOut = router.exactInputSingle(params);
+        IUniswapV2Pair pair = IUniswapV2Pair(pool);
+        (uint112 reserve0, uint112 reserve1, ) = pair.getReserves();
+        bool zeroForOne = tokenIn == pair.token0();
+        (uint256 reserveIn, uint256 reserveOut) = zeroForOne
+            ? (uint256(reserve0), uint256(reserve1))
+            : (uint256(reserve1), uint256(reserve0));
+        
+        IERC20(tokenIn).safeTransfer(pool, amountIn);
+        
+        // What the pair actually received (fee-on-transfer safe)
+        uint256 received = IERC20(tokenIn).balanceOf(pool) - reserveIn;
+        uint256 inWithFee = received * (1_000_000 - fee);
+        amountOut = inWithFee * reserveOut / (reserveIn * 1_000_000 + inWithFee);
+        
+        (uint256 amount0Out, uint256 amount1Out) = zeroForOne
+            ? (uint256(0), amountOut)
+            : (amountOut, uint256(0));
+        pair.swap(amount0Out, amount1Out, address(this), "");
+    }
+    
+    /**
+     * @notice V3 pool swap: the pool pays out, then pulls `tokenIn` in the callback
+     */
+    function _swapV3(
+        address tokenIn,
+        address tokenOut,
+        address pool,
+        uint256 amountIn
+    ) internal returns (uint256 amountOut) {
+        bool zeroForOne = tokenIn < tokenOut;
+        
+        _activeV3Pool = pool;
+        (int256 amount0, int256 amount1) = IUniswapV3Pool(pool).swap(
+            address(this),
+            zeroForOne,
+            int256(amountIn),
+            zeroForOne ? MIN_SQRT_RATIO_LIMIT : MAX_SQRT_RATIO_LIMIT,
+            abi.encode(tokenIn)
+        );
+        _activeV3Pool = address(0);
+        
+        amountOut = uint256(-(zeroForOne ? amount1 : amount0));
+    }
+    
+    /**
+     * @notice Curve exchange; output measured by balance (older pools return nothing)
+     */
+    function _swapCurve(
+        address tokenIn,
+        address tokenOut,
+        address pool,
+        bytes memory data,
+        uint256 amountIn
+    ) internal returns (uint256 amountOut) {
+        (uint256 kind, uint256 i, uint256 j) = abi.decode(data, (uint256, uint256, uint256));
+        uint256 balanceBefore = IERC20(tokenOut).balanceOf(address(this));
+        
+        if (kind == CURVE_STABLE_RECEIVED) {
+            // Pay first, no approval needed
+            IERC20(tokenIn).safeTransfer(pool, amountIn);
+            ICurveStablePool(pool).exchange_received(int128(int256(i)), int128(int256(j)), amountIn, 0);
         } else {
-            // V2 swap
-            IUniswapV2Router router = dexType == DEX_SUSHISWAP_V2 
-                ? SUSHISWAP_ROUTER 
-                : UNISWAP_V2_ROUTER;
-            
-            address[] memory swapPath = new address[](2);
-            swapPath[0] = tokenIn;
-            swapPath[1] = tokenOut;
-            
-            uint256[] memory amounts = router.swapExactTokensForTokens(
-                amountIn,
-                0, // We check final output
-                swapPath,
-                address(this),
+            IERC20(tokenIn).forceApprove(pool, amountIn);
+            if (kind == CURVE_CRYPTO) {
+                ICurveCryptoPool(pool).exchange(i, j, amountIn, 0);
+            } else {
+                ICurveStablePool(pool).exchange(int128(int256(i)), int128(int256(j)), amountIn, 0);
+            }
+            IERC20(tokenIn).forceApprove(pool, 0);
+        }
+        
+        amountOut = IERC20(tokenOut).balanceOf(address(this)) - balanceBefore;
+    }
+    
+    /**
+     * @notice Balancer swap through one pool, or batchSwap through several
+     */
+    function _swapBalancer(bytes memory data, uint256 amountIn) internal returns (uint256 amountOut) {
+        (bytes32[] memory poolIds, address[] memory assets) = abi.decode(data, (bytes32[], address[]));
+        require(poolIds.length >= 1 && assets.length == poolIds.length + 1, "Invalid Balancer leg");
+        
+        IBalancerVault.FundManagement memory funds = IBalancerVault.FundManagement({
+            sender: address(this),
+            fromInternalBalance: false,
+            recipient: payable(address(this)),
+            toInternalBalance: false
+        });
+        IERC20(assets[0]).forceApprove(address(BALANCER_VAULT), amountIn);
+        
+        if (poolIds.length == 1) {
+            amountOut = BALANCER_VAULT.swap(
+                IBalancerVault.SingleSwap({
+                    poolId: poolIds[0],
+                    kind: IBalancerVault.SwapKind.GIVEN_IN,
+                    assetIn: assets[0],
+                    assetOut: assets[1],
+                    amount: amountIn,
+                    userData: ""
+                }),
+                funds,
+                0,
                 block.timestamp
             );
+        } else {
+            IBalancerVault.BatchSwapStep[] memory steps = new IBalancerVault.BatchSwapStep[](poolIds.length);
+            int256[] memory limits = new int256[](assets.length);
+            for (uint256 k = 0; k < poolIds.length; k++) {
+                steps[k] = IBalancerVault.BatchSwapStep({
+                    poolId: poolIds[k],
+                    assetInIndex: k,
+                    assetOutIndex: k + 1,
+                    amount: k == 0 ? amountIn : 0,   // 0 = previous step's output
+                    userData: ""
+                });
+            }
+            limits[0] = int256(amountIn);
             
-            amountOut = amounts[1];
+            int256[] memory deltas = BALANCER_VAULT.batchSwap(
+                IBalancerVault.SwapKind.GIVEN_IN,
+                steps,
+                assets,
+                funds,
+                limits,
+                block.timestamp
+            );
+            amountOut = uint256(-deltas[assets.length - 1]);
         }
         
-        // Reset approval
-        IERC20(tokenIn).safeApprove(_getRouter(dexType), 0);
-        
-        return amountOut;
+        IERC20(assets[0]).forceApprove(address(BALANCER_VAULT), 0);
+    }
+    
+    /**
+     * @notice ERC-4626 deposit (underlying -> shares) or redeem (shares -> underlying)
+     */
+    function _swapERC4626(
+        address tokenIn,
+        address tokenOut,
+        address vault,
+        uint256 amountIn
+    ) internal returns (uint256 amountOut) {
+        if (tokenOut == vault) {
+            IERC20(tokenIn).forceApprove(vault, amountIn);
+            amountOut = IERC4626(vault).deposit(amountIn, address(this));
+        } else {
+            require(tokenIn == vault, "Vault is neither side");
+            amountOut = IERC4626(vault).redeem(amountIn, address(this), address(this));
+        }
     }
     
-    function _getRouter(uint8 dexType) internal pure returns (address) {
-        if (dexType == DEX_UNISWAP_V3) return address(UNISWAP_V3_ROUTER);
-        if (dexType == DEX_PANCAKE_V3) return address(PANCAKE_V3_ROUTER);
-        if (dexType == DEX_SUSHISWAP_V2) return address(SUSHISWAP_ROUTER);
-        return address(UNISWAP_V2_ROUTER);
+    /**
+     * @notice Uniswap V3 / SushiSwap V3 swap callback
+     */
+    function uniswapV3SwapCallback(int256 amount0Delta, int256 amount1Delta, bytes calldata data) external {
+        _payV3Pool(amount0Delta, amount1Delta, data);
+    }
+    
+    /**
+     * @notice PancakeSwap V3 swap callback
+     */
+    function pancakeV3SwapCallback(int256 amount0Delta, int256 amount1Delta, bytes calldata data) external {
+        _payV3Pool(amount0Delta, amount1Delta, data);
+    }
+    
+    function _payV3Pool(int256 amount0Delta, int256 amount1Delta, bytes calldata data) internal {
+        require(msg.sender == _activeV3Pool, "Unexpected swap callback");
+        
+        address tokenIn = abi.decode(data, (address));
+        uint256 owed = uint256(amount0Delta > 0 ? amount0Delta : amount1Delta);
+        IERC20(tokenIn).safeTransfer(msg.sender, owed);
     }
     
     /**
@@ -656,6 +972,12 @@ mod tests {
         assert_eq!(DexType::from(Dex::UniswapV3) as u8, 0);
         assert_eq!(DexType::from(Dex::UniswapV2) as u8, 1);
         assert_eq!(DexType::from(Dex::SushiswapV2) as u8, 2);
+        assert_eq!(DexType::from(Dex::PancakeSwapV3) as u8, 3);
+        assert_eq!(DexType::from(Dex::SushiswapV3) as u8, 6);
+        
+        let (vault, usds) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        assert_eq!(DexType::for_edge(Dex::BalancerV2, vault, usds, vault) as u8, 7);
+        assert_eq!(DexType::for_edge(Dex::BalancerV2, Address::repeat_byte(3), usds, vault) as u8, 4);
     }
     
     #[test]
@@ -669,5 +991,49 @@ mod tests {
         // Balancer has 0% fee
         let min_output = builder.calculate_min_output(input, min_profit);
         assert_eq!(min_output, U256::from(1010u64));
+        
+        // Aave's 5 bps premium on 1000 is 0.5, owed as 1
+        let aave = builder.with_fee_ppm(500);
+        assert_eq!(aave.calculate_min_output(input, min_profit), U256::from(1011u64));
+        assert!(get_executor_contract_source().contains("function executeOperation("));
+    }
+    
+    #[test]
+    fn test_uniswap_flash_swap_lender() {
+        let config = Config::default();
+        let (a, b) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let cycle = ArbitrageCycle {
+            path: vec![a, b, a],
+            pools: vec![Address::repeat_byte(0xa1), Address::repeat_byte(0xa2)],
+            dexes: vec![Dex::UniswapV3, Dex::UniswapV2],
+            total_weight: 0.0,
+            expected_return: 1.01,
+            prices: vec![],
+            fees: vec![500, 3000],
+        };
+        let plan = Plan::from_cycle(FlashLoanProvider::BalancerV2, &cycle, U256::from(1_000_000u64), U256::ZERO).unwrap();
+        let builder = FlashLoanBuilder::new(&config).with_executor_address(Address::repeat_byte(0xee));
+        
+        // The lending pool's own fee tier (0.05%), not a flat 0.3%
+        let lender_pool = Address::repeat_byte(0xf1);
+        let flash = builder.for_lender(&LenderQuote {
+            provider: FlashLoanProvider::UniswapV3,
+            source: lender_pool,
+            available: U256::from(10_000_000u64),
+            fee_ppm: 500,
+        });
+        let min_output = flash.calculate_min_output(U256::from(1_000_000u64), U256::ZERO);
+        assert_eq!(min_output, U256::from(1_000_500u64));
+        
+        let tx = flash.build_flash_loan_tx(&plan, min_output, &cycle, None).unwrap();
+        let sent = Plan::from_execute_calldata(&tx.calldata).unwrap();
+        assert_eq!(sent.lender, lender_id(FlashLoanProvider::UniswapV3));
+        assert_eq!(sent.lenderPool, lender_pool);
+        assert_eq!(sent.minOut, min_output);
+        
+        // A flash swap can't be encoded without its pool
+        let unpooled = FlashLoanBuilder::new(&Config { flash_loan_provider: FlashLoanProvider::UniswapV3, ..config })
+            .with_executor_address(Address::repeat_byte(0xee));
+        assert!(unpooled.build_flash_loan_tx(&plan, min_output, &cycle, None).is_err());
     }
 }
@@ -13,8 +13,10 @@ use eyre::{eyre, Result};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
 use std::time::{SystemTime, UNIX_EPOCH};
-use tracing::{debug, info, warn};
+use tracing::{debug, info};
 
 use crate::config::Config;
 use super::flash_loan::FlashLoanTransaction;
@@ -67,6 +69,36 @@ pub struct FlashbotsBundle {
     pub reverting_tx_hashes: Vec<B256>,
 }
 
+impl FlashbotsBundle {
+    /// `eth_sendBundle` params for this bundle
+    pub fn send_params(&self) -> Value {
+        let tx_strings: Vec<String> = self.transactions
+            .iter()
+            .map(|tx| format!("0x{}", hex::encode(tx)))
+            .collect();
+        
+        let mut params = json!({
+            "txs": tx_strings,
+            "blockNumber": format!("0x{:x}", self.block_number),
+        });
+        
+        if let Some(min_ts) = self.min_timestamp {
+            params["minTimestamp"] = json!(min_ts);
+        }
+        if let Some(max_ts) = self.max_timestamp {
+            params["maxTimestamp"] = json!(max_ts);
+        }
+        if !self.reverting_tx_hashes.is_empty() {
+            let hashes: Vec<String> = self.reverting_tx_hashes
+                .iter()
+                .map(|h| format!("0x{}", hex::encode(h)))
+                .collect();
+            params["revertingTxHashes"] = json!(hashes);
+        }
+        params
+    }
+}
+
 /// Response from bundle submission
 #[derive(Debug, Clone, Deserialize)]
 pub struct BundleResponse {
@@ -88,6 +120,9 @@ pub struct SimulationResult {
     pub state_block: Option<u64>,
     pub gas_used: Option<u64>,
     pub coinbase_diff: Option<String>,
+    /// Direct ETH transfers to the coinbase (bribes), summed over the bundle
+    #[serde(default)]
+    pub eth_sent_to_coinbase: U256,
     pub error: Option<String>,
 }
 
@@ -146,30 +181,7 @@ impl FlashbotsClient {
             return Err(eyre!("Flashbots signer key not configured"));
         }
         
-        // Build the bundle params
-        let tx_strings: Vec<String> = bundle.transactions
-            .iter()
-            .map(|tx| format!("0x{}", hex::encode(tx)))
-            .collect();
-        
-        let mut params = json!({
-            "txs": tx_strings,
-            "blockNumber": format!("0x{:x}", bundle.block_number),
-        });
-        
-        if let Some(min_ts) = bundle.min_timestamp {
-            params["minTimestamp"] = json!(min_ts);
-        }
-        if let Some(max_ts) = bundle.max_timestamp {
-            params["maxTimestamp"] = json!(max_ts);
-        }
-        if !bundle.reverting_tx_hashes.is_empty() {
-            let hashes: Vec<String> = bundle.reverting_tx_hashes
-                .iter()
-                .map(|h| format!("0x{}", hex::encode(h)))
-                .collect();
-            params["revertingTxHashes"] = json!(hashes);
-        }
+        let params = bundle.send_params();
         
         let request = json!({
             "jsonrpc": "2.0",
@@ -270,6 +282,7 @@ impl FlashbotsClient {
                 state_block: None,
                 gas_used: None,
                 coinbase_diff: None,
+                eth_sent_to_coinbase: U256::ZERO,
                 error: Some(error.get("message").and_then(|m| m.as_str()).unwrap_or("Unknown").to_string()),
             });
         }
@@ -285,12 +298,24 @@ impl FlashbotsClient {
                         state_block: None,
                         gas_used: None,
                         coinbase_diff: None,
+                        eth_sent_to_coinbase: U256::ZERO,
                         error: Some(error.as_str().unwrap_or("Transaction error").to_string()),
                     });
                 }
             }
         }
         
+        let eth_sent_to_coinbase = result
+            .and_then(|r| r.get("results"))
+            .and_then(|r| r.as_array())
+            .map(|results| {
+                results.iter()
+                    .filter_map(|r| r.get("ethSentToCoinbase").and_then(|v| v.as_str()))
+                    .filter_map(|v| v.parse::<U256>().ok())
+                    .fold(U256::ZERO, |sum, v| sum + v)
+            })
+            .unwrap_or_default();
+        
         Ok(SimulationResult {
             success: true,
             state_block: result.and_then(|r| r.get("stateBlockNumber")).and_then(|s| {
@@ -298,6 +323,7 @@ impl FlashbotsClient {
             }),
             gas_used: result.and_then(|r| r.get("totalGasUsed")).and_then(|g| g.as_u64()),
             coinbase_diff: result.and_then(|r| r.get("coinbaseDiff")).and_then(|c| c.as_str()).map(String::from),
+            eth_sent_to_coinbase,
             error: None,
         })
     }
@@ -355,40 +381,56 @@ impl FlashbotsClient {
 
 /// Builds Flashbots bundles from arbitrage opportunities
 pub struct BundleBuilder {
-    miner_bribe_pct: f64,
+    bid_strategy: BidStrategy,
     chain_id: u64,
 }
 
 impl BundleBuilder {
     pub fn new(config: &Config) -> Self {
         Self {
-            miner_bribe_pct: config.miner_bribe_pct,
+            bid_strategy: BidStrategy::new(config),
             chain_id: config.chain_id,
         }
     }
     
+    /// How much of each trade's profit goes to the builder
+    pub fn bid_strategy(&self) -> &BidStrategy {
+        &self.bid_strategy
+    }
+    
     /// Build a bundle from a flash loan transaction
     pub fn build_bundle(
         &self,
-        flash_loan_tx: &FlashLoanTransaction,
+        _flash_loan_tx: &FlashLoanTransaction,
         signed_tx: Bytes,
         target_block: u64,
-        expected_profit_wei: U256,
+        bribe_wei: U256,
+    ) -> Result<FlashbotsBundle> {
+        self.build_multi_bundle(vec![signed_tx], target_block, bribe_wei)
+    }
+    
+    /// Build one bundle from several arbitrage transactions (consecutive
+    /// nonces, pool-disjoint cycles); `bribe_wei` is what their executor
+    /// plans pay to the coinbase together
+    pub fn build_multi_bundle(
+        &self,
+        signed_txs: Vec<Bytes>,
+        target_block: u64,
+        bribe_wei: U256,
     ) -> Result<FlashbotsBundle> {
-        // Calculate miner bribe
-        let bribe_wei = (expected_profit_wei * U256::from((self.miner_bribe_pct * 100.0) as u64)) 
-            / U256::from(10000);
+        if signed_txs.is_empty() {
+            return Err(eyre!("Cannot build an empty bundle"));
+        }
         
         info!(
-            "Building bundle for block {} with ${:.2} expected profit, ${:.2} miner bribe ({:.0}%)",
+            "Building {}-tx bundle for block {} paying {:.6} ETH to the coinbase",
+            signed_txs.len(),
             target_block,
-            expected_profit_wei.to::<u128>() as f64 / 1e18 * 3500.0,
-            bribe_wei.to::<u128>() as f64 / 1e18 * 3500.0,
-            self.miner_bribe_pct
+            bribe_wei.to::<u128>() as f64 / 1e18
         );
         
         Ok(FlashbotsBundle {
-            transactions: vec![signed_tx],
+            transactions: signed_txs,
             block_number: target_block,
             min_timestamp: None,
             max_timestamp: None,
@@ -396,9 +438,9 @@ impl BundleBuilder {
         })
     }
     
-    /// Calculate the bribe amount in wei
+    /// Calculate the bribe amount in wei (no known competition)
     pub fn calculate_bribe(&self, profit_wei: U256) -> U256 {
-        (profit_wei * U256::from((self.miner_bribe_pct * 100.0) as u64)) / U256::from(10000)
+        self.bid_strategy.bid(profit_wei, 0)
     }
     
     /// Calculate our take after bribe
@@ -408,6 +450,89 @@ impl BundleBuilder {
     }
 }
 
+// ============================================
+// BIDDING
+// ============================================
+
+/// Blocks between two bids on the same pools that still count as a rebid
+const REBID_WINDOW_BLOCKS: u64 = 2;
+
+/// Sizes the coinbase payment from a trade's simulated profit
+///
+/// The bid starts at `MINER_BRIBE_PCT` of the profit. An opportunity that is
+/// still there a block later was won by nobody or lost to a rival, so each
+/// consecutive rebid on the same pools halves the distance to
+/// `MAX_BRIBE_PCT`. The bid never drops below `MIN_BRIBE_ETH` and never
+/// leaves less than `MIN_PROFIT_USD` of profit, valued at the live ETH price
+/// (no bid until one is set).
+pub struct BidStrategy {
+    base_pct: f64,
+    max_pct: f64,
+    floor_wei: U256,
+    min_retained_usd: f64,
+    eth_price_usd: Mutex<f64>,
+    /// Sorted pool set -> (last block bid for, consecutive rebids)
+    rebids: Mutex<HashMap<Vec<Address>, (u64, u32)>>,
+}
+
+impl BidStrategy {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            base_pct: config.miner_bribe_pct,
+            max_pct: config.max_bribe_pct.max(config.miner_bribe_pct),
+            floor_wei: U256::from((config.min_bribe_eth * 1e18) as u128),
+            min_retained_usd: config.min_profit_usd,
+            eth_price_usd: Mutex::new(0.0),
+            rebids: Mutex::new(HashMap::new()),
+        }
+    }
+    
+    /// ETH price the retained minimum profit is valued at
+    pub fn set_eth_price(&self, eth_price_usd: f64) {
+        *self.eth_price_usd.lock().unwrap() = eth_price_usd;
+    }
+    
+    /// Last ETH price set (0 until one is)
+    pub fn eth_price(&self) -> f64 {
+        *self.eth_price_usd.lock().unwrap()
+    }
+    
+    /// Share of profit bid after `competition` lost blocks
+    pub fn bribe_pct(&self, competition: u32) -> f64 {
+        self.max_pct - (self.max_pct - self.base_pct) * 0.5f64.powi(competition as i32)
+    }
+    
+    /// Coinbase payment (wei) for a trade netting `profit_wei`
+    pub fn bid(&self, profit_wei: U256, competition: u32) -> U256 {
+        let pct_bps = (self.bribe_pct(competition) * 100.0) as u64;
+        let eth_price_usd = *self.eth_price_usd.lock().unwrap();
+        if eth_price_usd <= 0.0 {
+            return U256::ZERO;
+        }
+        let min_retained_wei = U256::from((self.min_retained_usd / eth_price_usd * 1e18) as u128);
+        
+        let bribe = (profit_wei * U256::from(pct_bps) / U256::from(10_000)).max(self.floor_wei);
+        bribe.min(profit_wei.saturating_sub(min_retained_wei))
+    }
+    
+    /// Record a bid on `pools` for `target_block` and return how many
+    /// consecutive blocks the same pools have been bid on before
+    pub fn competition(&self, pools: &[Address], target_block: u64) -> u32 {
+        let mut key = pools.to_vec();
+        key.sort();
+        
+        let mut rebids = self.rebids.lock().unwrap();
+        rebids.retain(|_, (block, _)| block.saturating_add(REBID_WINDOW_BLOCKS) >= target_block);
+        let competition = match rebids.get(&key) {
+            Some(&(block, count)) if block == target_block => count,
+            Some(&(block, count)) if block < target_block => count + 1,
+            _ => 0,
+        };
+        rebids.insert(key, (target_block, competition));
+        competition
+    }
+}
+
 // ============================================
 // SUBMISSION STRATEGY
 // ============================================
@@ -438,70 +563,14 @@ impl Default for SubmissionStrategy {
     }
 }
 
-/// Full bundle submission workflow
-pub async fn submit_arbitrage_bundle(
-    client: &FlashbotsClient,
-    flash_loan_tx: FlashLoanTransaction,
-    signed_tx: Bytes,
-    current_block: u64,
-    expected_profit_wei: U256,
-    config: &Config,
-    wallet: &WalletManager,
-) -> Result<Option<String>> {
-    let builder = BundleBuilder::new(config);
-    let strategy = SubmissionStrategy::default();
-    
-    // Submit to multiple consecutive blocks for better inclusion
-    let mut bundle_hashes = Vec::new();
-    
-    for i in 0..strategy.target_blocks {
-        let target_block = current_block + 1 + i as u64;
-        
-        let bundle = builder.build_bundle(
-            &flash_loan_tx,
-            signed_tx.clone(),
-            target_block,
-            expected_profit_wei,
-        )?;
-        
-        // First simulate
-        info!("Simulating bundle for block {}...", target_block);
-        let sim_result = client.simulate_bundle(&bundle, wallet).await?;
-        
-        if !sim_result.success {
-            warn!(
-                "Bundle simulation failed for block {}: {:?}",
-                target_block, sim_result.error
-            );
-            continue;
-        }
-        
-        info!(
-            "Simulation passed! Gas used: {:?}, Coinbase diff: {:?}",
-            sim_result.gas_used, sim_result.coinbase_diff
-        );
-        
-        // Submit the bundle
-        match client.send_bundle(&bundle, wallet).await {
-            Ok(response) => {
-                if let Some(hash) = response.bundle_hash {
-                    info!("Bundle submitted for block {}: {}", target_block, hash);
-                    bundle_hashes.push(hash);
-                } else if let Some(error) = response.error {
-                    warn!(
-                        "Bundle submission failed for block {}: {} (code {})",
-                        target_block, error.message, error.code
-                    );
-                }
-            }
-            Err(e) => {
-                warn!("Failed to submit bundle for block {}: {}", target_block, e);
-            }
+impl SubmissionStrategy {
+    /// Default strategy with the configured number of target blocks
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            target_blocks: config.bundle_target_blocks.max(1),
+            ..Default::default()
         }
     }
-    
-    // Return the first successful bundle hash
-    Ok(bundle_hashes.into_iter().next())
 }
 
 // ============================================
@@ -519,6 +588,7 @@ mod tests {
             ..Default::default()
         };
         let builder = BundleBuilder::new(&config);
+        builder.bid_strategy().set_eth_price(3500.0);
         
         // 100 ETH profit -> 90 ETH bribe
         let profit = U256::from(100u64) * U256::from(10u64).pow(U256::from(18u64));
@@ -528,4 +598,69 @@ mod tests {
         assert_eq!(bribe, U256::from(90u64) * U256::from(10u64).pow(U256::from(18u64)));
         assert_eq!(our_profit, U256::from(10u64) * U256::from(10u64).pow(U256::from(18u64)));
     }
+    
+    #[test]
+    fn test_multi_bundle_keeps_transaction_order() {
+        let builder = BundleBuilder::new(&Config::default());
+        let txs = vec![Bytes::from(vec![1u8]), Bytes::from(vec![2u8]), Bytes::from(vec![3u8])];
+        
+        let bundle = builder.build_multi_bundle(txs.clone(), 100, U256::from(1u64)).unwrap();
+        assert_eq!(bundle.transactions, txs);
+        assert_eq!(bundle.block_number, 100);
+        assert!(bundle.reverting_tx_hashes.is_empty());
+        
+        assert!(builder.build_multi_bundle(vec![], 100, U256::ZERO).is_err());
+    }
+    
+    #[test]
+    fn test_bid_curve_floor_and_cap() {
+        let config = Config {
+            miner_bribe_pct: 90.0,
+            max_bribe_pct: 98.0,
+            min_bribe_eth: 0.01,
+            min_profit_usd: 35.0, // 0.01 ETH at $3500
+            ..Default::default()
+        };
+        let strategy = BidStrategy::new(&config);
+        assert_eq!(strategy.bid(U256::from(10u64).pow(U256::from(18u64)), 0), U256::ZERO); // no price yet
+        strategy.set_eth_price(3500.0);
+        let eth = U256::from(10u64).pow(U256::from(18u64));
+        
+        assert_eq!(strategy.bribe_pct(0), 90.0);
+        assert_eq!(strategy.bribe_pct(1), 94.0);
+        assert_eq!(strategy.bribe_pct(2), 96.0);
+        assert_eq!(strategy.bid(eth, 1), eth * U256::from(94u64) / U256::from(100u64));
+        
+        // Floor lifts small bids, but the minimum profit is always kept
+        let small = eth / U256::from(50u64);
+        assert_eq!(strategy.bid(small, 0), eth / U256::from(100u64));
+        assert_eq!(strategy.bid(eth / U256::from(100u64), 0), U256::ZERO);
+        
+        // Same pools in consecutive blocks raise the competition level
+        let pools = [Address::repeat_byte(2), Address::repeat_byte(1)];
+        assert_eq!(strategy.competition(&pools, 100), 0);
+        assert_eq!(strategy.competition(&pools, 100), 0);
+        assert_eq!(strategy.competition(&[pools[1], pools[0]], 101), 1);
+        assert_eq!(strategy.competition(&pools, 102), 2);
+        assert_eq!(strategy.competition(&pools, 110), 0);
+    }
+    
+    #[test]
+    fn test_rebidding_the_same_pools_raises_the_bid() {
+        let config = Config { miner_bribe_pct: 50.0, max_bribe_pct: 90.0, ..Default::default() };
+        let strategy = BidStrategy::new(&config);
+        strategy.set_eth_price(3500.0);
+        let profit = U256::from(10u64).pow(U256::from(18u64));
+        let pools = [Address::repeat_byte(1), Address::repeat_byte(2)];
+        
+        // One scan per block, each bidding for the next
+        let bids: Vec<U256> = (200..204)
+            .map(|block| strategy.bid(profit, strategy.competition(&pools, block + 1)))
+            .collect();
+        assert!(bids.windows(2).all(|w| w[1] > w[0]), "{:?}", bids);
+        
+        // Rescanning within a block doesn't
+        let again = strategy.bid(profit, strategy.competition(&pools, 204));
+        assert_eq!(again, bids[3]);
+    }
 }
@@ -0,0 +1,671 @@
+//! Bundle Inclusion Tracking & PnL Reconciliation
+//!
+//! Every submitted transaction is followed through its target blocks. Once
+//! it lands, its receipt is decoded (the executor's `ArbitrageExecuted` /
+//! `CoinbasePaid` events and the profit token's transfers) into realized
+//! profit; once its last target block passes without it, the target blocks
+//! are checked for another transaction trading the same cycle, in the same
+//! direction, to tell being outbid from plain non-inclusion. Landed transactions also calibrate the
+//! swap gas model. Each outcome is appended, next to what
+//! the simulation expected, to `EXECUTION_LOG_PATH` (JSON lines).
+
+use alloy_primitives::{keccak256, Address, Log, B256, I256, U256};
+use alloy_provider::{Provider, ProviderBuilder};
+use alloy_rpc_types::Filter;
+use alloy_sol_types::{sol, SolEvent};
+use chrono::{DateTime, Utc};
+use eyre::Result;
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::brain::ArbitrageCycle;
+use crate::config::Config;
+use crate::swap_gas::SwapGasModel;
+use super::nonce::{NonceOutcome, SettledNonce};
+use super::ExecutionResult;
+
+sol! {
+    interface IExecutorEvents {
+        event ArbitrageExecuted(address indexed token, uint256 inputAmount, uint256 profit);
+        event CoinbasePaid(address indexed coinbase, uint256 amount);
+    }
+
+    interface IErc20Events {
+        event Transfer(address indexed from, address indexed to, uint256 value);
+    }
+}
+
+// ============================================
+// TRACKED TRANSACTIONS
+// ============================================
+
+/// A submitted executor transaction and what its simulation expected
+#[derive(Debug, Clone)]
+pub struct TrackedTx {
+    pub tx_hash: B256,
+    pub bundle_hash: String,
+    pub target_blocks: Vec<u64>,
+    /// Borrowed (and profit) token
+    pub token: Address,
+    pub path: Vec<Address>,
+    /// Pools each hop trades, split-route legs included
+    pub pools: Vec<Vec<Address>>,
+    /// Profit tokens left at the executor after repaying and the bribe
+    pub expected_profit: U256,
+    pub expected_profit_usd: f64,
+    /// Wei the plan pays to the coinbase
+    pub expected_bribe: U256,
+    /// Cycle and ticks crossed per hop to calibrate the gas model with once
+    /// it lands; None for split routes, whose gas can't be attributed to hops
+    pub gas_sample: Option<(ArbitrageCycle, Vec<u32>)>,
+}
+
+impl TrackedTx {
+    /// Hash of a signed EIP-2718 transaction
+    pub fn hash_of(raw: &[u8]) -> B256 {
+        keccak256(raw)
+    }
+
+    fn last_target_block(&self) -> u64 {
+        self.target_blocks.iter().copied().max().unwrap_or_default()
+    }
+}
+
+/// Status of a submitted bundle
+#[derive(Debug, Clone, PartialEq)]
+pub enum BundleStatus {
+    /// Bundle was included in a block
+    Included {
+        block_number: u64,
+        tx_hash: B256,
+    },
+    /// Landed but reverted (e.g. sent outside a bundle)
+    Reverted {
+        block_number: u64,
+        tx_hash: B256,
+    },
+    /// Another transaction traded the same cycle in a target block
+    Outbid {
+        block_number: u64,
+        competitor: B256,
+    },
+    /// Bundle was not included within the timeout
+    NotIncluded {
+        checked_until_block: u64,
+    },
+    /// Another transaction from the wallet took the nonce
+    Replaced {
+        nonce: u64,
+    },
+    /// A later submission of ours landed with the same nonce
+    Superseded {
+        nonce: u64,
+        by: B256,
+    },
+}
+
+impl BundleStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            BundleStatus::Included { .. } => "included",
+            BundleStatus::Reverted { .. } => "reverted",
+            BundleStatus::Outbid { .. } => "outbid",
+            BundleStatus::NotIncluded { .. } => "not_included",
+            BundleStatus::Replaced { .. } => "replaced",
+            BundleStatus::Superseded { .. } => "superseded",
+        }
+    }
+}
+
+/// What a landed transaction actually did
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RealizedPnl {
+    /// Profit booked by the executor (`ArbitrageExecuted`), else its net
+    /// profit token transfers
+    pub profit: U256,
+    /// Net profit token transfers into the executor
+    pub transfer_delta: I256,
+    /// ETH paid to the coinbase (`CoinbasePaid`)
+    pub bribe_paid: U256,
+    pub gas_used: u64,
+    pub gas_cost_wei: U256,
+}
+
+impl RealizedPnl {
+    /// Decode the executor's events and `token` transfers from a receipt's logs
+    pub fn from_logs<'a>(logs: impl IntoIterator<Item = &'a Log>, executor: Address, token: Address) -> Self {
+        let mut booked = None;
+        let mut pnl = RealizedPnl::default();
+
+        for log in logs {
+            if log.address == executor {
+                if let Ok(event) = IExecutorEvents::ArbitrageExecuted::decode_log(log) {
+                    booked = Some(booked.unwrap_or(U256::ZERO) + event.profit);
+                } else if let Ok(event) = IExecutorEvents::CoinbasePaid::decode_log(log) {
+                    pnl.bribe_paid += event.amount;
+                }
+            } else if log.address == token {
+                if let Ok(transfer) = IErc20Events::Transfer::decode_log(log) {
+                    let value = I256::from_raw(transfer.value);
+                    if transfer.to == executor {
+                        pnl.transfer_delta += value;
+                    }
+                    if transfer.from == executor {
+                        pnl.transfer_delta -= value;
+                    }
+                }
+            }
+        }
+
+        pnl.profit = booked.unwrap_or_else(|| {
+            if pnl.transfer_delta.is_negative() { U256::ZERO } else { pnl.transfer_delta.into_raw() }
+        });
+        pnl
+    }
+}
+
+/// Outcome of one tracked transaction
+#[derive(Debug, Clone)]
+pub struct InclusionReport {
+    pub tx: TrackedTx,
+    pub status: BundleStatus,
+    pub realized: Option<RealizedPnl>,
+}
+
+impl InclusionReport {
+    /// Realized profit in USD, at the token price the simulation used
+    pub fn realized_profit_usd(&self) -> Option<f64> {
+        let realized = self.realized.as_ref()?;
+        if self.tx.expected_profit.is_zero() {
+            return None;
+        }
+        let ratio = realized.profit.to::<u128>() as f64 / self.tx.expected_profit.to::<u128>() as f64;
+        Some(self.tx.expected_profit_usd * ratio)
+    }
+
+    /// The execution result this outcome amounts to
+    pub fn result(&self) -> ExecutionResult {
+        match self.status {
+            BundleStatus::Included { block_number, .. } => ExecutionResult::Included {
+                bundle_hash: self.tx.bundle_hash.clone(),
+                block_number,
+                actual_profit_wei: self.realized.as_ref().map(|r| r.profit).unwrap_or_default(),
+            },
+            BundleStatus::Reverted { block_number, tx_hash } => ExecutionResult::Failed {
+                reason: format!("{:?} reverted in block {}", tx_hash, block_number),
+            },
+            BundleStatus::Outbid { block_number, competitor } => ExecutionResult::Failed {
+                reason: format!("Outbid in block {} by {:?}", block_number, competitor),
+            },
+            BundleStatus::NotIncluded { checked_until_block } => ExecutionResult::Failed {
+                reason: format!("Not included by block {}", checked_until_block),
+            },
+            BundleStatus::Replaced { nonce } => ExecutionResult::Failed {
+                reason: format!("Nonce {} taken by another wallet transaction", nonce),
+            },
+            BundleStatus::Superseded { nonce, by } => ExecutionResult::Skipped {
+                reason: format!("Superseded by {:?} with nonce {}", by, nonce),
+            },
+        }
+    }
+
+    fn record(&self) -> ExecutionRecord {
+        let block_number = match self.status {
+            BundleStatus::Included { block_number, .. }
+            | BundleStatus::Reverted { block_number, .. }
+            | BundleStatus::Outbid { block_number, .. } => Some(block_number),
+            BundleStatus::NotIncluded { .. } | BundleStatus::Replaced { .. } | BundleStatus::Superseded { .. } => None,
+        };
+        ExecutionRecord {
+            timestamp: Utc::now(),
+            bundle_hash: self.tx.bundle_hash.clone(),
+            tx_hash: format!("{:?}", self.tx.tx_hash),
+            outcome: self.status.label().to_string(),
+            block_number,
+            competitor_tx: match self.status {
+                BundleStatus::Outbid { competitor, .. } => Some(format!("{:?}", competitor)),
+                _ => None,
+            },
+            path: self.tx.path.iter().map(|a| format!("{:?}", a)).collect(),
+            target_blocks: self.tx.target_blocks.clone(),
+            expected_profit: self.tx.expected_profit.to_string(),
+            realized_profit: self.realized.as_ref().map(|r| r.profit.to_string()),
+            expected_profit_usd: self.tx.expected_profit_usd,
+            realized_profit_usd: self.realized_profit_usd(),
+            expected_bribe_wei: self.tx.expected_bribe.to_string(),
+            bribe_paid_wei: self.realized.as_ref().map(|r| r.bribe_paid.to_string()),
+            gas_used: self.realized.as_ref().map(|r| r.gas_used),
+            gas_cost_wei: self.realized.as_ref().map(|r| r.gas_cost_wei.to_string()),
+        }
+    }
+}
+
+/// One line of the execution log
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecutionRecord {
+    pub timestamp: DateTime<Utc>,
+    pub bundle_hash: String,
+    pub tx_hash: String,
+    pub outcome: String,
+    pub block_number: Option<u64>,
+    pub competitor_tx: Option<String>,
+    pub path: Vec<String>,
+    pub target_blocks: Vec<u64>,
+    pub expected_profit: String,
+    pub realized_profit: Option<String>,
+    pub expected_profit_usd: f64,
+    pub realized_profit_usd: Option<f64>,
+    pub expected_bribe_wei: String,
+    pub bribe_paid_wei: Option<String>,
+    pub gas_used: Option<u64>,
+    pub gas_cost_wei: Option<String>,
+}
+
+impl ExecutionRecord {
+    /// Append this record to a file
+    pub fn append_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        if let Some(parent) = path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        writeln!(file, "{}", serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Running totals over resolved transactions
+#[derive(Debug, Clone, Default)]
+pub struct InclusionStats {
+    pub included: u64,
+    pub reverted: u64,
+    pub outbid: u64,
+    pub not_included: u64,
+    pub replaced: u64,
+    pub superseded: u64,
+    /// Expected vs realized profit of included transactions (USD)
+    pub expected_usd: f64,
+    pub realized_usd: f64,
+}
+
+// ============================================
+// TRACKER
+// ============================================
+
+/// Watches submitted transactions until they land or their blocks pass
+pub struct InclusionTracker {
+    rpc_url: String,
+    executor: Option<Address>,
+    log_path: String,
+    gas_model: SwapGasModel,
+    pending: Mutex<Vec<TrackedTx>>,
+    /// Every transaction we submitted, which never outbids another of ours
+    ours: Mutex<HashSet<B256>>,
+    stats: Mutex<InclusionStats>,
+}
+
+impl InclusionTracker {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            rpc_url: config.rpc_url.clone(),
+            executor: config.executor_contract_address.as_ref().and_then(|s| s.parse().ok()),
+            log_path: config.execution_log_path.clone(),
+            gas_model: SwapGasModel::new(config.flash_loan_provider),
+            pending: Mutex::new(Vec::new()),
+            ours: Mutex::new(HashSet::new()),
+            stats: Mutex::new(InclusionStats::default()),
+        }
+    }
+
+    /// Calibrate a (shared) gas model from landed transactions
+    pub fn with_gas_model(mut self, gas_model: SwapGasModel) -> Self {
+        self.gas_model = gas_model;
+        self
+    }
+
+    /// Start watching a submitted transaction
+    pub fn track(&self, tx: TrackedTx) {
+        debug!("Tracking {:?} for blocks {:?}", tx.tx_hash, tx.target_blocks);
+        self.ours.lock().unwrap().insert(tx.tx_hash);
+        self.pending.lock().unwrap().push(tx);
+    }
+
+    pub fn stats(&self) -> InclusionStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Resolve every tracked transaction that landed or whose target blocks
+    /// have all passed; the rest stay pending
+    pub async fn poll(&self) -> Result<Vec<InclusionReport>> {
+        let pending = self.pending.lock().unwrap().clone();
+        if pending.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let provider = ProviderBuilder::new()
+            .on_http(self.rpc_url.parse()?);
+        let current_block = provider.get_block_number().await?;
+
+        let mut reports = Vec::new();
+        for tx in pending {
+            let report = match provider.get_transaction_receipt(tx.tx_hash).await? {
+                Some(receipt) => {
+                    let block_number = receipt.block_number.unwrap_or(current_block);
+                    if let (true, Some((cycle, ticks_crossed))) = (receipt.status(), &tx.gas_sample) {
+                        if let Err(e) = self.gas_model.calibrate_from_receipt(
+                            &self.rpc_url, tx.tx_hash, receipt.gas_used, cycle, ticks_crossed,
+                        ).await {
+                            warn!("Gas calibration from {:?} failed: {}", tx.tx_hash, e);
+                        }
+                    }
+                    let status = if receipt.status() {
+                        BundleStatus::Included { block_number, tx_hash: tx.tx_hash }
+                    } else {
+                        BundleStatus::Reverted { block_number, tx_hash: tx.tx_hash }
+                    };
+                    let executor = self.executor.unwrap_or(receipt.to.unwrap_or_default());
+                    let logs: Vec<&Log> = receipt.inner.logs().iter().map(|l| &l.inner).collect();
+                    let realized = RealizedPnl {
+                        gas_used: receipt.gas_used,
+                        gas_cost_wei: U256::from(receipt.gas_used) * U256::from(receipt.effective_gas_price),
+                        ..RealizedPnl::from_logs(logs, executor, tx.token)
+                    };
+                    InclusionReport { tx, status, realized: Some(realized) }
+                }
+                None if current_block >= tx.last_target_block() => {
+                    let status = self.missed_status(&provider, &tx, current_block).await;
+                    InclusionReport { tx, status, realized: None }
+                }
+                None => continue,
+            };
+            reports.push(report);
+        }
+
+        if !reports.is_empty() {
+            let resolved: Vec<B256> = reports.iter().map(|r| r.tx.tx_hash).collect();
+            self.pending.lock().unwrap().retain(|tx| !resolved.contains(&tx.tx_hash));
+        }
+        for report in &reports {
+            self.record(report);
+        }
+        Ok(reports)
+    }
+
+    /// Resolve the tracked transactions a used nonce rules out: all of ours
+    /// when another wallet transaction took it, the superseded ones when one
+    /// of ours landed (that one is resolved from its receipt)
+    pub fn resolve_nonce(&self, settled: &SettledNonce) -> Vec<InclusionReport> {
+        let status = match (settled.outcome, settled.tx_hash) {
+            (NonceOutcome::Included, Some(by)) => BundleStatus::Superseded { nonce: settled.nonce, by },
+            _ => BundleStatus::Replaced { nonce: settled.nonce },
+        };
+        let dead: Vec<B256> = settled.submitted.iter()
+            .copied()
+            .filter(|hash| Some(*hash) != settled.tx_hash)
+            .collect();
+
+        let mut resolved = Vec::new();
+        self.pending.lock().unwrap().retain(|tx| {
+            let hit = dead.contains(&tx.tx_hash);
+            if hit {
+                resolved.push(tx.clone());
+            }
+            !hit
+        });
+
+        let reports: Vec<InclusionReport> = resolved.into_iter()
+            .map(|tx| InclusionReport { tx, status: status.clone(), realized: None })
+            .collect();
+        for report in &reports {
+            self.record(report);
+        }
+        reports
+    }
+
+    /// Outbid when another transaction traded our cycle in a target block
+    async fn missed_status<P: Provider>(&self, provider: &P, tx: &TrackedTx, current_block: u64) -> BundleStatus {
+        let first_block = tx.target_blocks.iter().copied().min().unwrap_or_default();
+        let filter = Filter::new()
+            .from_block(first_block)
+            .to_block(tx.last_target_block())
+            .address(tx.pools.concat());
+
+        match provider.get_logs(&filter).await {
+            Ok(logs) => {
+                let touches: Vec<(u64, B256, Address)> = logs.iter()
+                    .filter_map(|log| Some((log.block_number?, log.transaction_hash?, log.address())))
+                    .collect();
+                let ours = self.ours.lock().unwrap().clone();
+                match competitor(&tx.pools, &ours, &touches) {
+                    Some((block_number, competitor)) => BundleStatus::Outbid { block_number, competitor },
+                    None => BundleStatus::NotIncluded { checked_until_block: current_block },
+                }
+            }
+            Err(e) => {
+                debug!("Pool logs for {:?} unavailable: {}", tx.tx_hash, e);
+                BundleStatus::NotIncluded { checked_until_block: current_block }
+            }
+        }
+    }
+
+    fn record(&self, report: &InclusionReport) {
+        {
+            let mut stats = self.stats.lock().unwrap();
+            match report.status {
+                BundleStatus::Included { .. } => {
+                    stats.included += 1;
+                    stats.expected_usd += report.tx.expected_profit_usd;
+                    stats.realized_usd += report.realized_profit_usd().unwrap_or_default();
+                }
+                BundleStatus::Reverted { .. } => stats.reverted += 1,
+                BundleStatus::Outbid { .. } => stats.outbid += 1,
+                BundleStatus::NotIncluded { .. } => stats.not_included += 1,
+                BundleStatus::Replaced { .. } => stats.replaced += 1,
+                BundleStatus::Superseded { .. } => stats.superseded += 1,
+            }
+        }
+
+        match &report.status {
+            BundleStatus::Included { block_number, .. } => info!(
+                "✅ {:?} included in block {}: profit {} (expected {}), bribe paid {} wei",
+                report.tx.tx_hash,
+                block_number,
+                report.realized.as_ref().map(|r| r.profit).unwrap_or_default(),
+                report.tx.expected_profit,
+                report.realized.as_ref().map(|r| r.bribe_paid).unwrap_or_default()
+            ),
+            status => warn!("{:?} {}: {:?}", report.tx.tx_hash, status.label(), status),
+        }
+
+        if let Err(e) = report.record().append_to_file(&self.log_path) {
+            warn!("Failed to write execution log: {}", e);
+        }
+    }
+}
+
+/// The first transaction not of `ours` that traded every hop in the cycle's order
+/// (starting from any hop), from pool logs `(block, tx, pool)` in chain order
+///
+/// Trading the hops in reverse is the opposite arbitrage, which doesn't take
+/// our profit. A two-hop cycle can't be told apart from its reverse this way.
+fn competitor(hop_pools: &[Vec<Address>], ours: &HashSet<B256>, touches: &[(u64, B256, Address)]) -> Option<(u64, B256)> {
+    let mut traded: Vec<(u64, B256, Vec<usize>)> = Vec::new();
+    for &(block_number, hash, pool) in touches {
+        if ours.contains(&hash) {
+            continue;
+        }
+        let Some(hop) = hop_pools.iter().position(|pools| pools.contains(&pool)) else {
+            continue;
+        };
+        let hops = match traded.iter().position(|(_, h, _)| *h == hash) {
+            Some(i) => &mut traded[i].2,
+            None => {
+                traded.push((block_number, hash, Vec::new()));
+                &mut traded.last_mut().unwrap().2
+            }
+        };
+        if !hops.contains(&hop) {
+            hops.push(hop);
+        }
+    }
+
+    let n = hop_pools.len();
+    traded.into_iter()
+        .find(|(_, _, hops)| hops.len() == n && hops.iter().enumerate().all(|(k, &hop)| hop == (hops[0] + k) % n))
+        .map(|(block_number, hash, _)| (block_number, hash))
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{LogData, B256};
+
+    fn log(address: Address, topics: Vec<B256>, data: Vec<u8>) -> Log {
+        Log { address, data: LogData::new_unchecked(topics, data.into()) }
+    }
+
+    #[test]
+    fn test_realized_pnl_from_receipt_logs() {
+        let executor = Address::repeat_byte(0xee);
+        let token = Address::repeat_byte(0x11);
+        let pool = Address::repeat_byte(0x22);
+        let word = |v: u64| U256::from(v).to_be_bytes::<32>().to_vec();
+
+        let transfer = |from: Address, to: Address, value: u64| log(
+            token,
+            vec![IErc20Events::Transfer::SIGNATURE_HASH, from.into_word(), to.into_word()],
+            word(value),
+        );
+        let mut executed = word(1_000);
+        executed.extend(word(12));
+        let logs = vec![
+            transfer(pool, executor, 1_000),    // flash loan
+            transfer(executor, pool, 1_000),    // first swap
+            transfer(pool, executor, 1_020),    // last swap
+            transfer(executor, pool, 1_000),    // repay
+            transfer(executor, pool, 8),        // bribe swap
+            log(executor, vec![IExecutorEvents::CoinbasePaid::SIGNATURE_HASH, pool.into_word()], word(5)),
+            log(executor, vec![IExecutorEvents::ArbitrageExecuted::SIGNATURE_HASH, token.into_word()], executed),
+        ];
+
+        let pnl = RealizedPnl::from_logs(&logs, executor, token);
+        assert_eq!(pnl.profit, U256::from(12));
+        assert_eq!(pnl.transfer_delta, I256::try_from(12).unwrap());
+        assert_eq!(pnl.bribe_paid, U256::from(5));
+
+        // Without the executor event, transfers decide
+        let pnl = RealizedPnl::from_logs(&logs[..5], executor, token);
+        assert_eq!(pnl.profit, U256::from(12));
+    }
+
+    #[test]
+    fn test_report_reconciles_expected_profit() {
+        let tx = TrackedTx {
+            tx_hash: TrackedTx::hash_of(&[0x02, 0x01]),
+            bundle_hash: "0xab".to_string(),
+            target_blocks: vec![100, 101, 102],
+            token: Address::repeat_byte(1),
+            path: vec![Address::repeat_byte(1), Address::repeat_byte(2), Address::repeat_byte(1)],
+            pools: vec![vec![Address::repeat_byte(3)], vec![Address::repeat_byte(4)]],
+            expected_profit: U256::from(200),
+            expected_profit_usd: 50.0,
+            expected_bribe: U256::from(7),
+            gas_sample: None,
+        };
+        assert_eq!(tx.last_target_block(), 102);
+
+        let report = InclusionReport {
+            tx: tx.clone(),
+            status: BundleStatus::Included { block_number: 101, tx_hash: tx.tx_hash },
+            realized: Some(RealizedPnl { profit: U256::from(150), ..Default::default() }),
+        };
+        assert_eq!(report.realized_profit_usd(), Some(37.5));
+        let record = report.record();
+        assert_eq!(record.outcome, "included");
+        assert_eq!(record.block_number, Some(101));
+        assert_eq!(record.realized_profit.as_deref(), Some("150"));
+        assert!(matches!(report.result(), ExecutionResult::Included { block_number: 101, .. }));
+
+        let missed = InclusionReport { tx: tx.clone(), status: BundleStatus::NotIncluded { checked_until_block: 103 }, realized: None };
+        assert_eq!(missed.record().outcome, "not_included");
+        assert_eq!(missed.realized_profit_usd(), None);
+
+        // A nonce taken by another wallet transaction resolves only the
+        // transactions submitted with it
+        let tracker = InclusionTracker {
+            rpc_url: String::new(),
+            executor: None,
+            log_path: std::env::temp_dir().join("sniper_replaced_test.jsonl").display().to_string(),
+            gas_model: SwapGasModel::default(),
+            pending: Mutex::new(vec![tx.clone(), TrackedTx { tx_hash: B256::repeat_byte(9), ..tx.clone() }]),
+            ours: Mutex::new(HashSet::new()),
+            stats: Mutex::new(InclusionStats::default()),
+        };
+        let replaced = SettledNonce {
+            nonce: 5,
+            tx_hash: None,
+            submitted: vec![B256::repeat_byte(8), tx.tx_hash],
+            outcome: NonceOutcome::Replaced,
+        };
+        let reports = tracker.resolve_nonce(&replaced);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].record().outcome, "replaced");
+        assert_eq!(tracker.stats().replaced, 1);
+        assert_eq!(tracker.pending.lock().unwrap().len(), 1);
+
+        // When a later submission of ours lands, the earlier one is
+        // superseded and the landed one is left to its receipt
+        let landed = TrackedTx { tx_hash: B256::repeat_byte(10), ..tx.clone() };
+        tracker.track(landed.clone());
+        let included = SettledNonce {
+            nonce: 6,
+            tx_hash: Some(landed.tx_hash),
+            submitted: vec![B256::repeat_byte(9), landed.tx_hash],
+            outcome: NonceOutcome::Included,
+        };
+        let reports = tracker.resolve_nonce(&included);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].record().outcome, "superseded");
+        assert_eq!(tracker.stats().superseded, 1);
+        assert_eq!(tracker.pending.lock().unwrap()[0].tx_hash, landed.tx_hash);
+    }
+
+    #[test]
+    fn test_outbid_only_by_the_same_cycle_direction() {
+        let (a, b, c, leg) = (Address::repeat_byte(0xa), Address::repeat_byte(0xb), Address::repeat_byte(0xc), Address::repeat_byte(0xd));
+        let hop_pools = vec![vec![a], vec![b, leg], vec![c]];
+        let (own, replacement) = (B256::repeat_byte(1), B256::repeat_byte(5));
+        let ours = HashSet::from([own, replacement]);
+        let (other, reverse, partial) = (B256::repeat_byte(2), B256::repeat_byte(3), B256::repeat_byte(4));
+
+        // A swap through one of our pools, and the opposite arbitrage
+        let touches = vec![
+            (100, partial, b),
+            (100, reverse, c),
+            (100, reverse, b),
+            (100, reverse, a),
+            (101, own, a),
+        ];
+        assert_eq!(competitor(&hop_pools, &ours, &touches), None);
+
+        // Our own replacement bundle landing the same cycle
+        let mut touches = touches;
+        touches.extend([(101, replacement, a), (101, replacement, b), (101, replacement, c)]);
+        assert_eq!(competitor(&hop_pools, &ours, &touches), None);
+
+        // The same cycle, from its second hop and through the split leg
+        touches.extend([(101, other, leg), (101, other, c), (101, other, a)]);
+        assert_eq!(competitor(&hop_pools, &ours, &touches), Some((101, other)));
+    }
+}
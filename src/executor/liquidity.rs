@@ -0,0 +1,431 @@
+//! Flash Liquidity Registry - who can lend what, right now
+//!
+//! Reads, in one or two Multicall3 batches:
+//! - Balancer V2: the Vault's balance of each token (0% fee)
+//! - Aave V3: reserve configuration (active / paused / flash loans enabled),
+//!   the aToken's underlying balance and the live FLASHLOAN_PREMIUM_TOTAL
+//! - Uniswap V3: token balances of the deepest Uniswap V3 pools holding the
+//!   token (fee = the pool's fee tier)
+//!
+//! The scanner starts cycles from every token with enough borrowable
+//! liquidity, sizes trades to what can actually be borrowed, and the
+//! executor picks the cheapest lender for each plan.
+
+use alloy_primitives::{address, Address, U256};
+use alloy_provider::{Provider, ProviderBuilder};
+use alloy_rpc_types::TransactionRequest;
+use alloy_sol_types::{sol, SolCall};
+use eyre::{eyre, Result};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+use crate::cartographer::{self, Dex, PoolState};
+use crate::config::FlashLoanProvider;
+
+// ============================================
+// CONSTANTS
+// ============================================
+
+/// Balancer V2 Vault
+const BALANCER_VAULT: Address = address!("BA12222222228d8Ba445958a75a0704d566BF2C8");
+
+/// Aave V3 Pool
+const AAVE_V3_POOL: Address = address!("87870Bca3F3fD6335C3F4ce8392D69350B4fA4E2");
+
+/// Multicall3 address (same on all EVM chains)
+const MULTICALL3: Address = address!("cA11bde05977b3631167028862bE2a173976CA11");
+
+/// Lender balances are re-read at most this often
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Uniswap V3 pools considered per token
+const MAX_UNIV3_LENDERS_PER_TOKEN: usize = 3;
+
+/// Calls per Multicall3 batch
+const MAX_CALLS_PER_BATCH: usize = 200;
+
+/// Aave reserve configuration bits
+const AAVE_ACTIVE_BIT: usize = 56;
+const AAVE_PAUSED_BIT: usize = 60;
+const AAVE_FLASHLOAN_ENABLED_BIT: usize = 63;
+
+// ============================================
+// SOLIDITY INTERFACES
+// ============================================
+
+sol! {
+    interface ILiquidityMulticall3 {
+        struct Call3 {
+            address target;
+            bool allowFailure;
+            bytes callData;
+        }
+
+        struct Result {
+            bool success;
+            bytes returnData;
+        }
+
+        function aggregate3(Call3[] calldata calls)
+            external payable returns (Result[] memory returnData);
+    }
+
+    interface IAavePoolReserves {
+        struct ReserveConfigurationMap {
+            uint256 data;
+        }
+
+        struct ReserveData {
+            ReserveConfigurationMap configuration;
+            uint128 liquidityIndex;
+            uint128 currentLiquidityRate;
+            uint128 variableBorrowIndex;
+            uint128 currentVariableBorrowRate;
+            uint128 currentStableBorrowRate;
+            uint40 lastUpdateTimestamp;
+            uint16 id;
+            address aTokenAddress;
+            address stableDebtTokenAddress;
+            address variableDebtTokenAddress;
+            address interestRateStrategyAddress;
+            uint128 accruedToTreasury;
+            uint128 unbacked;
+            uint128 isolationModeTotalDebt;
+        }
+
+        function getReserveData(address asset) external view returns (ReserveData memory);
+        function FLASHLOAN_PREMIUM_TOTAL() external view returns (uint128);
+    }
+
+    interface ILenderBalance {
+        function balanceOf(address account) external view returns (uint256);
+    }
+}
+
+// ============================================
+// TYPES
+// ============================================
+
+/// One lender's offer for a token
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LenderQuote {
+    pub provider: FlashLoanProvider,
+
+    /// Contract holding the liquidity (Vault, aToken or Uniswap pool)
+    pub source: Address,
+
+    /// Amount that can be borrowed (raw units)
+    pub available: U256,
+
+    /// Fee in parts per million of the borrowed amount
+    pub fee_ppm: u32,
+}
+
+impl LenderQuote {
+    /// Fee as a fraction of the borrowed amount
+    pub fn fee_fraction(&self) -> f64 {
+        self.fee_ppm as f64 / 1_000_000.0
+    }
+}
+
+/// Whether an Aave reserve configuration allows flash loans
+fn aave_flash_enabled(configuration: U256) -> bool {
+    configuration.bit(AAVE_ACTIVE_BIT)
+        && !configuration.bit(AAVE_PAUSED_BIT)
+        && configuration.bit(AAVE_FLASHLOAN_ENABLED_BIT)
+}
+
+#[derive(Debug, Default)]
+struct RegistryState {
+    lenders: HashMap<Address, Vec<LenderQuote>>,
+    refreshed_at: Option<Instant>,
+}
+
+/// Shared view of flash loan liquidity. Clones share the same state.
+#[derive(Debug, Clone)]
+pub struct FlashLiquidityRegistry {
+    rpc_url: String,
+    preferred: FlashLoanProvider,
+    state: Arc<RwLock<RegistryState>>,
+}
+
+impl FlashLiquidityRegistry {
+    pub fn new(rpc_url: String, preferred: FlashLoanProvider) -> Self {
+        Self {
+            rpc_url,
+            preferred,
+            state: Arc::new(RwLock::new(RegistryState::default())),
+        }
+    }
+
+    /// Whether any lender data has been loaded
+    pub fn is_loaded(&self) -> bool {
+        self.state.read().map(|s| s.refreshed_at.is_some()).unwrap_or(false)
+    }
+
+    /// Re-read lender liquidity for `tokens` (rate-limited to REFRESH_INTERVAL)
+    ///
+    /// Returns the number of tokens with at least one lender.
+    pub async fn refresh(&self, tokens: &[Address], pools: &[PoolState]) -> Result<usize> {
+        {
+            let state = self.state.read().map_err(|_| eyre!("Flash liquidity lock poisoned"))?;
+            if state.refreshed_at.is_some_and(|t| t.elapsed() < REFRESH_INTERVAL) {
+                return Ok(state.lenders.len());
+            }
+        }
+
+        // Round 1: Aave premium, Balancer balances and Aave reserve data
+        let mut calls = vec![call3(AAVE_V3_POOL, IAavePoolReserves::FLASHLOAN_PREMIUM_TOTALCall {}.abi_encode())];
+        for token in tokens {
+            calls.push(call3(*token, ILenderBalance::balanceOfCall { account: BALANCER_VAULT }.abi_encode()));
+            calls.push(call3(AAVE_V3_POOL, IAavePoolReserves::getReserveDataCall { asset: *token }.abi_encode()));
+        }
+        let results = self.multicall(calls).await?;
+
+        let premium_ppm = results.first()
+            .filter(|r| r.success)
+            .and_then(|r| IAavePoolReserves::FLASHLOAN_PREMIUM_TOTALCall::abi_decode_returns(&r.returnData).ok())
+            .map(bps_to_ppm);
+
+        let mut lenders: HashMap<Address, Vec<LenderQuote>> = HashMap::new();
+        let mut a_tokens = Vec::new();
+        for (token, pair) in tokens.iter().zip(results[1..].chunks(2)) {
+            if let Some(balance) = pair.first().and_then(decode_balance) {
+                if !balance.is_zero() {
+                    lenders.entry(*token).or_default().push(LenderQuote {
+                        provider: FlashLoanProvider::BalancerV2,
+                        source: BALANCER_VAULT,
+                        available: balance,
+                        fee_ppm: 0,
+                    });
+                }
+            }
+
+            let reserve = pair.get(1)
+                .filter(|r| r.success)
+                .and_then(|r| IAavePoolReserves::getReserveDataCall::abi_decode_returns(&r.returnData).ok());
+            if let (Some(reserve), Some(_)) = (reserve, premium_ppm) {
+                if reserve.aTokenAddress != Address::ZERO && aave_flash_enabled(reserve.configuration.data) {
+                    a_tokens.push((*token, reserve.aTokenAddress));
+                }
+            }
+        }
+
+        // Round 2: aToken balances and the deepest Uniswap V3 pools per token
+        let univ3 = deepest_univ3_pools(tokens, pools);
+        let mut calls = Vec::with_capacity(a_tokens.len() + univ3.len());
+        for (token, a_token) in &a_tokens {
+            calls.push(call3(*token, ILenderBalance::balanceOfCall { account: *a_token }.abi_encode()));
+        }
+        for (token, pool) in &univ3 {
+            calls.push(call3(*token, ILenderBalance::balanceOfCall { account: pool.address }.abi_encode()));
+        }
+        let results = self.multicall(calls).await?;
+
+        for ((token, a_token), result) in a_tokens.iter().zip(&results) {
+            if let Some(balance) = decode_balance(result).filter(|b| !b.is_zero()) {
+                lenders.entry(*token).or_default().push(LenderQuote {
+                    provider: FlashLoanProvider::AaveV3,
+                    source: *a_token,
+                    available: balance,
+                    fee_ppm: premium_ppm.unwrap_or(500),
+                });
+            }
+        }
+        for ((token, pool), result) in univ3.iter().zip(&results[a_tokens.len()..]) {
+            if let Some(balance) = decode_balance(result).filter(|b| !b.is_zero()) {
+                lenders.entry(*token).or_default().push(LenderQuote {
+                    provider: FlashLoanProvider::UniswapV3,
+                    source: pool.address,
+                    available: balance,
+                    fee_ppm: pool.fee,
+                });
+            }
+        }
+
+        let count = lenders.len();
+        info!(
+            "Flash liquidity: {} / {} tokens borrowable (Aave premium {} bps)",
+            count,
+            tokens.len(),
+            premium_ppm.map_or("n/a".to_string(), |p| (p / 100).to_string())
+        );
+
+        let mut state = self.state.write().map_err(|_| eyre!("Flash liquidity lock poisoned"))?;
+        state.lenders = lenders;
+        state.refreshed_at = Some(Instant::now());
+        Ok(count)
+    }
+
+    /// All known lenders for `token`
+    pub fn lenders(&self, token: &Address) -> Vec<LenderQuote> {
+        self.state.read()
+            .ok()
+            .and_then(|s| s.lenders.get(token).cloned())
+            .unwrap_or_default()
+    }
+
+    /// Aave's live flash loan premium (FLASHLOAN_PREMIUM_TOTAL), in ppm
+    pub async fn aave_premium_ppm(&self) -> Result<u32> {
+        let results = self.multicall(vec![
+            call3(AAVE_V3_POOL, IAavePoolReserves::FLASHLOAN_PREMIUM_TOTALCall {}.abi_encode()),
+        ]).await?;
+        results.first()
+            .filter(|r| r.success)
+            .and_then(|r| IAavePoolReserves::FLASHLOAN_PREMIUM_TOTALCall::abi_decode_returns(&r.returnData).ok())
+            .map(bps_to_ppm)
+            .ok_or_else(|| eyre!("Failed to read Aave FLASHLOAN_PREMIUM_TOTAL"))
+    }
+
+    /// Cheapest lender able to lend `amount` of `token`. Lenders whose source
+    /// is in `exclude_pools` (the cycle's own pools, or the Balancer Vault
+    /// when the plan swaps through it) are skipped: they're locked while
+    /// lending.
+    pub fn best_lender(&self, token: &Address, amount: U256, exclude_pools: &[Address]) -> Option<LenderQuote> {
+        select_lender(&self.lenders(token), amount, exclude_pools, self.preferred)
+    }
+
+    /// Largest amount of `token` any single usable lender can provide
+    pub fn max_borrowable(&self, token: &Address, exclude_pools: &[Address]) -> Option<U256> {
+        self.lenders(token).iter()
+            .filter(|q| !excluded(q, exclude_pools))
+            .map(|q| q.available)
+            .max()
+    }
+
+    /// Tokens whose deepest lender holds at least `min_usd`, in address order
+    pub fn start_tokens(&self, prices_usd: &HashMap<Address, f64>, min_usd: f64) -> Vec<Address> {
+        let Ok(state) = self.state.read() else {
+            return Vec::new();
+        };
+        let mut tokens: Vec<Address> = state.lenders.iter()
+            .filter(|(token, quotes)| {
+                let Some(price) = prices_usd.get(*token) else {
+                    return false;
+                };
+                let decimals = cartographer::get_token_decimals(token);
+                quotes.iter().any(|q| to_tokens(q.available, decimals) * price >= min_usd)
+            })
+            .map(|(token, _)| *token)
+            .collect();
+        tokens.sort();
+        debug!("Flash liquidity: {} start tokens above ${:.0}", tokens.len(), min_usd);
+        tokens
+    }
+
+    async fn multicall(&self, calls: Vec<ILiquidityMulticall3::Call3>) -> Result<Vec<ILiquidityMulticall3::Result>> {
+        let provider = ProviderBuilder::new()
+            .on_http(self.rpc_url.parse()?);
+
+        let mut results = Vec::with_capacity(calls.len());
+        for chunk in calls.chunks(MAX_CALLS_PER_BATCH) {
+            let calldata = ILiquidityMulticall3::aggregate3Call { calls: chunk.to_vec() }.abi_encode();
+            let tx = TransactionRequest::default()
+                .to(MULTICALL3)
+                .input(calldata.into());
+            let output = provider.call(tx).await
+                .map_err(|e| eyre!("Multicall3 failed: {}", e))?;
+            results.extend(ILiquidityMulticall3::aggregate3Call::abi_decode_returns(&output)
+                .map_err(|e| eyre!("Failed to decode multicall result: {}", e))?);
+        }
+        Ok(results)
+    }
+}
+
+fn call3(target: Address, calldata: Vec<u8>) -> ILiquidityMulticall3::Call3 {
+    ILiquidityMulticall3::Call3 {
+        target,
+        allowFailure: true,
+        callData: calldata.into(),
+    }
+}
+
+fn decode_balance(result: &ILiquidityMulticall3::Result) -> Option<U256> {
+    if !result.success {
+        return None;
+    }
+    ILenderBalance::balanceOfCall::abi_decode_returns(&result.returnData).ok()
+}
+
+/// Aave premiums are in basis points
+fn bps_to_ppm(bps: u128) -> u32 {
+    (bps as u32).saturating_mul(100)
+}
+
+fn to_tokens(amount: U256, decimals: u8) -> f64 {
+    amount.to_string().parse::<f64>().unwrap_or(0.0) / 10f64.powi(decimals as i32)
+}
+
+fn excluded(quote: &LenderQuote, exclude_pools: &[Address]) -> bool {
+    exclude_pools.contains(&quote.source)
+}
+
+/// Cheapest quote covering `amount`; ties go to the preferred provider, then
+/// to the deepest source
+fn select_lender(
+    quotes: &[LenderQuote],
+    amount: U256,
+    exclude_pools: &[Address],
+    preferred: FlashLoanProvider,
+) -> Option<LenderQuote> {
+    quotes.iter()
+        .filter(|q| q.available >= amount && !excluded(q, exclude_pools))
+        .min_by_key(|q| (q.fee_ppm, q.provider != preferred, std::cmp::Reverse(q.available)))
+        .copied()
+}
+
+/// Up to MAX_UNIV3_LENDERS_PER_TOKEN Uniswap V3 pools per token, by liquidity
+fn deepest_univ3_pools<'a>(tokens: &[Address], pools: &'a [PoolState]) -> Vec<(Address, &'a PoolState)> {
+    let mut selected = Vec::new();
+    for token in tokens {
+        let mut candidates: Vec<&PoolState> = pools.iter()
+            .filter(|p| p.dex == Dex::UniswapV3 && !p.is_v4 && (p.token0 == *token || p.token1 == *token))
+            .collect();
+        candidates.sort_by_key(|p| std::cmp::Reverse(p.liquidity));
+        selected.extend(candidates.into_iter().take(MAX_UNIV3_LENDERS_PER_TOKEN).map(|p| (*token, p)));
+    }
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aave_flash_enabled_bits() {
+        let bit = |i: usize| U256::from(1) << i;
+        let active_flash = bit(AAVE_ACTIVE_BIT) | bit(AAVE_FLASHLOAN_ENABLED_BIT);
+
+        assert!(aave_flash_enabled(active_flash));
+        assert!(!aave_flash_enabled(bit(AAVE_ACTIVE_BIT)));
+        assert!(!aave_flash_enabled(active_flash | bit(AAVE_PAUSED_BIT)));
+    }
+
+    #[test]
+    fn test_select_cheapest_sufficient_lender() {
+        let pool = Address::repeat_byte(0x11);
+        let quote = |provider, source, available: u64, fee_ppm| LenderQuote {
+            provider,
+            source,
+            available: U256::from(available),
+            fee_ppm,
+        };
+        let quotes = vec![
+            quote(FlashLoanProvider::BalancerV2, BALANCER_VAULT, 1_000, 0),
+            quote(FlashLoanProvider::AaveV3, Address::repeat_byte(0x22), 50_000, 500),
+            quote(FlashLoanProvider::UniswapV3, pool, 100_000, 100),
+        ];
+        let pick = |amount: u64, exclude: &[Address]| {
+            select_lender(&quotes, U256::from(amount), exclude, FlashLoanProvider::BalancerV2)
+                .map(|q| q.provider)
+        };
+
+        assert_eq!(pick(500, &[]), Some(FlashLoanProvider::BalancerV2));
+        assert_eq!(pick(10_000, &[]), Some(FlashLoanProvider::UniswapV3));
+        // The cycle trades through the Uniswap pool: it can't also lend
+        assert_eq!(pick(10_000, &[pool]), Some(FlashLoanProvider::AaveV3));
+        assert_eq!(pick(75_000, &[pool]), None);
+    }
+}
@@ -2,16 +2,24 @@
 //!
 //! This module handles the actual execution of arbitrage opportunities:
 //! - Flash Loan acquisition (Balancer V2 / Aave V3)
-//! - Transaction signing with alloy
-//! - Flashbots bundle submission (private, no failed tx costs)
-//! - Bundle monitoring and profit tracking
+//! - Transaction signing with alloy, nonces reserved per bundle
+//! - Bundle submission to Flashbots and other builders (private, no failed tx costs)
+//! - Inclusion tracking and expected vs realized profit
 //!
 //! ⚠️  WARNING: This module interacts with real funds in production mode!
 //! Always test on Goerli/Sepolia testnet first.
 
+mod actions;
+mod call_sim;
 mod flash_loan;
 mod flashbots;
+mod inclusion;
+mod liquidity;
+mod nonce;
+mod passphrase_fd;
+mod plan;
 mod signer;
+mod submission;
 
 pub use flash_loan::{
     FlashLoanBuilder,
@@ -24,35 +32,116 @@ pub use flashbots::{
     FlashbotsClient,
     FlashbotsBundle,
     BundleBuilder,
+    BidStrategy,
     BundleResponse,
     SimulationResult,
     SubmissionStrategy,
     FlashbotsEndpoints,
-    submit_arbitrage_bundle,
 };
 
+pub use submission::{SubmissionManager, SubmissionReport, BuilderResponse};
+
+pub use call_sim::{
+    CallSimulator,
+    CallSimResult,
+    ArbitrageExecution,
+    SCRATCH_EXECUTOR,
+    EXECUTOR_OWNER_SLOT,
+    load_executor_code,
+};
+
+pub use actions::{
+    ActionResolver,
+    BALANCER_VAULT,
+    uses_balancer_vault,
+    lender_exclusions,
+    cycle_lender_exclusions,
+};
+
+pub use inclusion::{
+    InclusionTracker,
+    InclusionReport,
+    InclusionStats,
+    BundleStatus,
+    TrackedTx,
+    RealizedPnl,
+};
+
+pub use liquidity::{FlashLiquidityRegistry, LenderQuote};
+
+pub use nonce::NonceManager;
+
+pub use plan::{Plan, PlanBribe, PlanHop, PlanLeg, PLAN_VERSION, WETH};
+
 pub use signer::{WalletManager, generate_new_wallet};
 
-use alloy_primitives::{Address, Bytes, U256};
+use alloy_primitives::{Address, U256};
 use alloy_provider::{Provider, ProviderBuilder};
 use eyre::{eyre, Result};
+use std::collections::HashSet;
 use tracing::{info, warn, error, debug};
 
 use crate::brain::ArbitrageCycle;
-use crate::config::{Config, ExecutionMode};
+use crate::config::{Config, ExecutionMode, FlashLoanProvider};
+use crate::gas_oracle::{FeeRecommendation, GasOracle};
+use crate::simulator::swap_simulator::ArbitrageSimulation;
+use crate::simulator::EvmSimulator;
+use crate::swap_gas::SwapGasModel;
+
+/// An arbitrage with its flash loan transaction, ready to bundle
+struct PlannedArb<'a> {
+    cycle: &'a ArbitrageCycle,
+    simulation: &'a ArbitrageSimulation,
+    tx: FlashLoanTransaction,
+    /// Wei the plan pays to the block's coinbase
+    bribe: U256,
+    /// Profit tokens the executor should keep (after loan, fee and bribe)
+    expected_profit: U256,
+}
+
+fn total_profit_usd(plans: &[PlannedArb<'_>]) -> f64 {
+    plans.iter().map(|p| p.simulation.profit_usd).sum()
+}
+
+fn total_bribe(plans: &[PlannedArb<'_>]) -> U256 {
+    plans.iter().map(|p| p.bribe).fold(U256::ZERO, |sum, b| sum + b)
+}
+
+/// Pools each hop of a plan trades, split-route legs included
+fn hop_pools(plan: &PlannedArb<'_>) -> Vec<Vec<Address>> {
+    plan.cycle.pools.iter().enumerate().map(|(i, &pool)| {
+        let legs = plan.simulation.route.as_ref().and_then(|r| r.hops.get(i));
+        let mut pools = vec![pool];
+        pools.extend(legs.into_iter().flat_map(|h| h.legs.iter().map(|l| l.swap.pool)).filter(|p| *p != pool));
+        pools
+    }).collect()
+}
 
 /// The main execution engine - now with full signing support
 pub struct ExecutionEngine {
     config: Config,
     flash_loan_builder: FlashLoanBuilder,
+    flash_liquidity: FlashLiquidityRegistry,
     flashbots_client: FlashbotsClient,
+    submission: SubmissionManager,
+    inclusion: InclusionTracker,
     bundle_builder: BundleBuilder,
     wallet_manager: WalletManager,
+    nonces: NonceManager,
+    gas_oracle: GasOracle,
+    gas_model: SwapGasModel,
+    evm_simulator: Option<EvmSimulator>,
+    call_simulator: CallSimulator,
 }
 
 impl ExecutionEngine {
     /// Create a new execution engine
-    pub fn new(config: Config) -> Self {
+    pub fn new(
+        config: Config,
+        gas_oracle: GasOracle,
+        gas_model: SwapGasModel,
+        flash_liquidity: FlashLiquidityRegistry,
+    ) -> Self {
         // Initialize wallet manager from environment
         let wallet_manager = WalletManager::from_env()
             .unwrap_or_else(|e| {
@@ -60,15 +149,55 @@ impl ExecutionEngine {
                 WalletManager::new(None, None, config.chain_id).unwrap()
             });
         
+        // Executor runtime bytecode, for simulating without a deployed contract
+        let executor_code = config.executor_bytecode_path.as_deref().and_then(|path| {
+            load_executor_code(path)
+                .map_err(|e| warn!("Executor bytecode not loaded: {}", e))
+                .ok()
+        });
+        
+        // Local fork simulation of the exact executor transactions
+        let evm_simulator = config.fork_simulation.then(|| {
+            let simulator = EvmSimulator::new(config.rpc_url.clone(), config.chain_id);
+            match &executor_code {
+                Some(code) => simulator.with_executor_code(code.clone()),
+                None => simulator,
+            }
+        });
+        let call_simulator = CallSimulator::new(config.rpc_url.clone(), executor_code);
+        
         Self {
-            flash_loan_builder: FlashLoanBuilder::new(&config),
+            flash_loan_builder: FlashLoanBuilder::new(&config).with_gas_model(gas_model.clone()),
+            flash_liquidity,
             flashbots_client: FlashbotsClient::new(&config),
+            submission: SubmissionManager::new(&config),
+            inclusion: InclusionTracker::new(&config).with_gas_model(gas_model.clone()),
             bundle_builder: BundleBuilder::new(&config),
+            nonces: NonceManager::new(&config, wallet_manager.profit_wallet_address()),
             wallet_manager,
+            gas_oracle,
+            gas_model,
+            evm_simulator,
+            call_simulator,
             config,
         }
     }
     
+    /// Live ETH price (bids keep `MIN_PROFIT_USD` of profit at this price)
+    pub fn set_eth_price(&self, eth_price_usd: f64) {
+        self.bundle_builder.bid_strategy().set_eth_price(eth_price_usd);
+    }
+    
+    /// Flash loan builder (used for calldata-based cost estimates during scanning)
+    pub fn flash_loan_builder(&self) -> &FlashLoanBuilder {
+        &self.flash_loan_builder
+    }
+    
+    /// Live flash loan liquidity (start tokens, sizing, lender choice)
+    pub fn flash_liquidity(&self) -> &FlashLiquidityRegistry {
+        &self.flash_liquidity
+    }
+    
     /// Check if the engine is ready for production
     pub fn is_production_ready(&self) -> bool {
         self.config.execution_mode == ExecutionMode::Production
@@ -105,6 +234,15 @@ impl ExecutionEngine {
                 self.config.executor_contract_address.clone()
                     .unwrap_or_else(|| "Not deployed".to_string()),
             ),
+            (
+                "Bundle Builders".to_string(),
+                !self.submission.builders().is_empty(),
+                format!(
+                    "{} builder(s), {} block(s) per bundle",
+                    self.submission.builders().len(),
+                    self.submission.strategy().target_blocks
+                ),
+            ),
         ]
     }
     
@@ -112,7 +250,20 @@ impl ExecutionEngine {
     pub async fn execute(
         &self,
         cycle: &ArbitrageCycle,
-        simulation: &crate::simulator::swap_simulator::ArbitrageSimulation,
+        simulation: &ArbitrageSimulation,
+        current_block: u64,
+    ) -> Result<ExecutionResult> {
+        self.execute_batch(&[(cycle, simulation)], current_block).await
+    }
+    
+    /// Execute several pool-disjoint arbitrages in one bundle
+    ///
+    /// Each cycle keeps its own flash loan transaction; they go out with
+    /// consecutive nonces, are simulated together, and the bribe is sized on
+    /// their combined profit. A `current_block` of 0 is looked up.
+    pub async fn execute_batch(
+        &self,
+        batch: &[(&ArbitrageCycle, &ArbitrageSimulation)],
         current_block: u64,
     ) -> Result<ExecutionResult> {
         // Safety checks
@@ -122,131 +273,251 @@ impl ExecutionEngine {
             });
         }
         
-        if !simulation.is_profitable {
+        let profitable: Vec<_> = batch.iter()
+            .filter(|(_, simulation)| simulation.is_profitable)
+            .collect();
+        
+        if profitable.is_empty() {
             return Ok(ExecutionResult::Skipped {
                 reason: "Simulation shows unprofitable".to_string(),
             });
         }
         
-        // Calculate input amount (flash loan amount)
-        let input_amount = simulation.input_amount;
+        // Resolve the block we're bidding after before sizing any bribe:
+        // competition counts rebids per target block
+        let current_block = match current_block {
+            0 => self.get_current_block().await?,
+            block => block,
+        };
         
-        // Calculate minimum output (must cover: loan + fee + min_profit)
-        let min_profit_wei = U256::from((self.config.min_profit_usd * 1e18 / 3500.0) as u128);
-        let min_output = self.flash_loan_builder.calculate_min_output(input_amount, min_profit_wei);
+        // Pools traded by the plans built so far
+        let mut traded = HashSet::new();
         
-        // Build the flash loan transaction
-        let flash_loan_tx = self.flash_loan_builder.build_flash_loan_tx(
-            cycle,
-            input_amount,
-            min_output,
-        )?;
+        let mut plans = Vec::with_capacity(profitable.len());
+        for &&(cycle, simulation) in &profitable {
+            let plan = match self.flash_loan_builder.build_plan(cycle, simulation.route.as_ref(), simulation.input_amount).await {
+                Ok(plan) => plan,
+                Err(e) => {
+                    warn!("Cannot encode {:?} for the executor ({}), dropping cycle", cycle.path, e);
+                    continue;
+                }
+            };
+            
+            // Pools the plan trades through can't lend (a flash-lending V3 pool
+            // is locked), nor can the Balancer Vault when the plan swaps there
+            let exclude = lender_exclusions(&plan);
+            
+            // Plans sharing a pool (split legs and merged Balancer hops
+            // included) would trade against each other's price impact
+            if !exclude.iter().filter(|p| **p != BALANCER_VAULT).all(|p| traded.insert(*p)) {
+                return Ok(ExecutionResult::Aborted {
+                    reason: "Batched cycles share a pool".to_string(),
+                });
+            }
+            
+            // Borrow from the cheapest lender that can cover the input; without
+            // lender data, fall back to the configured provider
+            let builder = if self.flash_liquidity.is_loaded() {
+                match self.flash_liquidity.best_lender(&cycle.path[0], simulation.input_amount, &exclude) {
+                    Some(lender) => {
+                        debug!("Flash loan from {} ({} ppm)", lender.provider, lender.fee_ppm);
+                        self.flash_loan_builder.for_lender(&lender)
+                    }
+                    None => {
+                        warn!("No lender can provide {} of {:?}, dropping cycle", simulation.input_amount, cycle.path[0]);
+                        continue;
+                    }
+                }
+            } else if self.config.flash_loan_provider == FlashLoanProvider::BalancerV2 && uses_balancer_vault(&plan) {
+                warn!("Cycle {:?} swaps through the Balancer Vault it would borrow from, dropping cycle", cycle.path);
+                continue;
+            } else if self.config.flash_loan_provider == FlashLoanProvider::AaveV3 {
+                // Price the loan at Aave's live premium, not the nominal 0.05%
+                match self.flash_liquidity.aave_premium_ppm().await {
+                    Ok(premium_ppm) => self.flash_loan_builder.clone().with_fee_ppm(premium_ppm),
+                    Err(e) => {
+                        warn!("{}, dropping cycle", e);
+                        continue;
+                    }
+                }
+            } else {
+                self.flash_loan_builder.clone()
+            };
+            let builder = match self.simulation_executor() {
+                Some(executor) if builder.executor_address().is_none() => builder.with_executor_address(executor),
+                _ => builder,
+            };
+            
+            // Pay the builder out of the profit
+            let (plan, bribe) = self.attach_bribe(plan, simulation, current_block);
+            
+            // Calculate minimum output (must cover: loan + fee + min_profit + bribe)
+            let min_profit = simulation.start_token_amount(self.config.min_profit_usd);
+            let min_output = builder.calculate_min_output(simulation.input_amount, min_profit + plan.bribe.amountIn);
+            
+            // Build the flash loan transaction
+            let tx = match builder.build_flash_loan_tx(&plan, min_output, cycle, simulation.route.as_ref()) {
+                Ok(tx) => tx,
+                Err(e) => {
+                    warn!("Cannot fund {:?} ({}), dropping cycle", cycle.path, e);
+                    continue;
+                }
+            };
+            
+            let expected_profit = simulation.output_amount.saturating_sub(min_output - min_profit);
+            plans.push(PlannedArb { cycle, simulation, tx, bribe, expected_profit });
+        }
+        
+        if plans.is_empty() {
+            return Ok(ExecutionResult::Skipped {
+                reason: "Insufficient flash loan liquidity".to_string(),
+            });
+        }
         
         match self.config.execution_mode {
             ExecutionMode::Simulation => {
-                self.execute_simulation(cycle, simulation, &flash_loan_tx).await
+                self.execute_simulation(&plans).await
             }
             
             ExecutionMode::DryRun => {
-                self.execute_dry_run(cycle, simulation, &flash_loan_tx, current_block).await
+                self.execute_dry_run(&plans, current_block).await
             }
             
             ExecutionMode::Production => {
-                self.execute_production(cycle, simulation, &flash_loan_tx, current_block).await
+                self.execute_production(&plans, current_block).await
+            }
+        }
+    }
+    
+    /// Add the coinbase payment the bid strategy sets for this trade
+    ///
+    /// The bid is sized on the simulated net profit and how many blocks in a
+    /// row these pools have been bid on. A non-WETH profit is swapped along
+    /// the cycle's own hops up to WETH, buying with the simulated rate plus
+    /// slippage room (any surplus stays as WETH profit). Without a way to WETH
+    /// the trade relies on its priority fee alone.
+    fn attach_bribe(&self, plan: Plan, simulation: &ArbitrageSimulation, current_block: u64) -> (Plan, U256) {
+        let strategy = self.bundle_builder.bid_strategy();
+        let profit_wei = U256::from(simulation.profit_wei.max(0) as u128);
+        let competition = strategy.competition(&simulation.cycle.pools, current_block + 1);
+        let value = strategy.bid(profit_wei, competition);
+        if value.is_zero() {
+            return (plan, U256::ZERO);
+        }
+        
+        // Index into the simulated hops, which Balancer merging doesn't touch
+        let simulated_weth_hop = simulation.cycle.path.iter().skip(1).position(|t| *t == WETH);
+        let amount_in = match simulated_weth_hop.filter(|_| plan.weth_hop().is_some()) {
+            _ if plan.token == WETH => value,
+            Some(hop) => {
+                // WETH out of the simulated trade at that hop
+                let weth_out = match simulation.route.as_ref() {
+                    Some(route) => route.hops.get(hop)
+                        .map(|h| h.legs.iter().fold(U256::ZERO, |sum, l| sum + l.swap.amount_out))
+                        .unwrap_or_default(),
+                    None => simulation.swaps.get(hop).map(|s| s.amount_out).unwrap_or_default(),
+                };
+                if weth_out.is_zero() {
+                    return (plan, U256::ZERO);
+                }
+                let room_bps = ((self.config.max_slippage * (hop + 2) as f64) * 10_000.0) as u64;
+                (value * simulation.input_amount).div_ceil(weth_out) * U256::from(10_000 + room_bps) / U256::from(10_000)
+            }
+            None => U256::ZERO,
+        };
+        
+        match plan.clone().with_bribe(value, amount_in) {
+            Ok(plan) => {
+                debug!(
+                    "Bribe {:.6} ETH ({:.1}% of profit, competition {})",
+                    value.to::<u128>() as f64 / 1e18,
+                    strategy.bribe_pct(competition),
+                    competition
+                );
+                (plan, value)
+            }
+            Err(e) => {
+                debug!("No coinbase payment ({}), relying on the priority fee", e);
+                (plan, U256::ZERO)
             }
         }
     }
     
     /// Simulation mode - log only, no execution
-    async fn execute_simulation(
-        &self,
-        cycle: &ArbitrageCycle,
-        simulation: &crate::simulator::swap_simulator::ArbitrageSimulation,
-        flash_loan_tx: &FlashLoanTransaction,
-    ) -> Result<ExecutionResult> {
-        info!("📋 SIMULATION MODE: Would execute arbitrage");
-        debug!("   Path: {:?}", cycle.path);
-        debug!("   Input: {} wei", simulation.input_amount);
-        debug!("   Expected profit: ${:.2}", simulation.profit_usd);
-        
-        // Log the opportunity if enabled
-        if self.config.simulation_log {
-            self.log_opportunity(cycle, simulation)?;
+    async fn execute_simulation(&self, plans: &[PlannedArb<'_>]) -> Result<ExecutionResult> {
+        info!("📋 SIMULATION MODE: Would execute {} arbitrage(s)", plans.len());
+        
+        for plan in plans {
+            debug!("   Path: {:?}", plan.cycle.path);
+            debug!("   Input: {} wei", plan.simulation.input_amount);
+            debug!("   Expected profit: ${:.2}", plan.simulation.profit_usd);
+            
+            // Log the opportunity if enabled
+            if self.config.simulation_log {
+                self.log_opportunity(plan.cycle, plan.simulation).await?;
+            }
         }
         
+        // Validate the exact transactions when there's an executor to run them
+        let would_execute = match self.call_simulate(plans, 0).await {
+            Ok(results) => results.iter().all(|r| r.success),
+            Err(e) => {
+                debug!("Call simulation skipped: {}", e);
+                true
+            }
+        };
+        
         Ok(ExecutionResult::Simulated {
-            expected_profit_usd: simulation.profit_usd,
-            would_execute: true,
+            expected_profit_usd: total_profit_usd(plans),
+            would_execute,
         })
     }
     
     /// Dry run mode - build and simulate bundles but don't submit
     async fn execute_dry_run(
         &self,
-        cycle: &ArbitrageCycle,
-        simulation: &crate::simulator::swap_simulator::ArbitrageSimulation,
-        flash_loan_tx: &FlashLoanTransaction,
+        plans: &[PlannedArb<'_>],
         current_block: u64,
     ) -> Result<ExecutionResult> {
-        info!("🔬 DRY RUN MODE: Building and simulating bundle...");
+        info!("🔬 DRY RUN MODE: Building and simulating {}-tx bundle...", plans.len());
         
-        // For dry run, we create a mock signed transaction
-        // In production, this would be properly signed
-        let mock_signed_tx = flash_loan_tx.calldata.clone();
+        // Calibrate the gas model against the real executor calldata
+        if let Some(from) = self.wallet_manager.profit_wallet_address() {
+            for plan in plans {
+                if let Err(e) = self.calibrate_gas(plan.cycle, plan.simulation, &plan.tx, from).await {
+                    debug!("Gas calibration skipped: {}", e);
+                }
+            }
+        }
         
-        // Build the bundle
-        let bundle = self.bundle_builder.build_bundle(
-            flash_loan_tx,
-            mock_signed_tx,
-            current_block + 1,
-            U256::from((simulation.profit_usd * 1e18 / 3500.0) as u128),
-        )?;
+        if let Some(reason) = self.fork_simulate(plans, current_block).await {
+            return Ok(ExecutionResult::Failed { reason });
+        }
         
-        // Simulate with Flashbots if we have a signer
-        if self.wallet_manager.has_flashbots_signer() {
-            match self.flashbots_client.simulate_bundle(&bundle, &self.wallet_manager).await {
-                Ok(sim_result) => {
-                    if sim_result.success {
-                        info!("✅ Bundle simulation passed!");
-                        info!("   Gas used: {:?}", sim_result.gas_used);
-                        info!("   Coinbase diff: {:?}", sim_result.coinbase_diff);
-                        
-                        Ok(ExecutionResult::DryRun {
-                            simulation_passed: true,
-                            gas_used: sim_result.gas_used,
-                            coinbase_diff: sim_result.coinbase_diff,
-                        })
-                    } else {
-                        warn!("❌ Bundle simulation failed: {:?}", sim_result.error);
-                        
-                        Ok(ExecutionResult::DryRun {
-                            simulation_passed: false,
-                            gas_used: None,
-                            coinbase_diff: None,
-                        })
-                    }
-                }
-                Err(e) => {
-                    error!("Failed to simulate bundle: {}", e);
-                    Ok(ExecutionResult::Failed {
-                        reason: format!("Simulation error: {}", e),
-                    })
-                }
+        // Run the exact transactions on the node with state overrides
+        match self.call_simulate(plans, current_block).await {
+            Ok(results) => {
+                let passed = results.iter().all(|r| r.success);
+                let gas_used = results.iter().map(|r| r.gas_used).sum::<Option<u64>>();
+                Ok(ExecutionResult::DryRun {
+                    simulation_passed: passed,
+                    gas_used,
+                    coinbase_diff: None,
+                })
+            }
+            Err(e) => {
+                error!("Failed to simulate transactions: {}", e);
+                Ok(ExecutionResult::Failed {
+                    reason: format!("Simulation error: {}", e),
+                })
             }
-        } else {
-            warn!("No Flashbots signer key - skipping bundle simulation");
-            Ok(ExecutionResult::Skipped {
-                reason: "No Flashbots signer key configured".to_string(),
-            })
         }
     }
     
     /// Production mode - full execution with real transactions
     async fn execute_production(
         &self,
-        cycle: &ArbitrageCycle,
-        simulation: &crate::simulator::swap_simulator::ArbitrageSimulation,
-        flash_loan_tx: &FlashLoanTransaction,
+        plans: &[PlannedArb<'_>],
         current_block: u64,
     ) -> Result<ExecutionResult> {
         // Check production readiness
@@ -262,57 +533,125 @@ impl ExecutionEngine {
             });
         }
         
-        info!("🚀 PRODUCTION MODE: Executing arbitrage!");
+        info!("🚀 PRODUCTION MODE: Executing {} arbitrage(s)!", plans.len());
         warn!("⚠️  This will use real funds!");
         
-        // Get current gas price
-        let gas_price = self.get_current_gas_price().await?;
-        let priority_fee = gas_price / 10; // 10% priority fee
+        let target_block = current_block + 1;
+        
+        // EIP-1559 fee caps for the target block
+        let gas_info = self.gas_oracle.get_gas_price().await;
+        let fees = gas_info.fee_recommendation(target_block);
         
-        // Check if gas is too high
-        if gas_price > (self.config.max_gas_gwei as u128) * 1_000_000_000 {
+        // Check if gas is too high (expected price at inclusion, not the cap)
+        let effective_gas_price = fees.effective_gas_price();
+        if effective_gas_price > (self.config.max_gas_gwei as u128) * 1_000_000_000 {
             return Ok(ExecutionResult::Aborted {
                 reason: format!(
                     "Gas price too high: {} gwei > {} max",
-                    gas_price / 1_000_000_000,
+                    effective_gas_price / 1_000_000_000,
                     self.config.max_gas_gwei
                 ),
             });
         }
         
-        // Clone wallet manager for mutable operations
-        let mut wallet = WalletManager::from_env()?;
+        debug!(
+            "Fees for block {}: base {:.3} gwei, maxFee {:.3} gwei, tip {:.3} gwei",
+            target_block,
+            fees.base_fee_wei as f64 / 1e9,
+            fees.max_fee_per_gas as f64 / 1e9,
+            fees.max_priority_fee_per_gas as f64 / 1e9
+        );
+        
+        // Run the exact transactions on a fork before signing anything
+        if let Some(reason) = self.fork_simulate(plans, current_block).await {
+            // Pending bundles trading these pools were built on opportunities
+            // that no longer hold; withdraw them rather than let builders retry
+            let pools: Vec<Address> = plans.iter().flat_map(hop_pools).flatten().collect();
+            if let Err(e) = self.submission.cancel_overlapping(&pools, &self.wallet_manager).await {
+                warn!("Failed to cancel overlapping bundles: {}", e);
+            }
+            return Ok(ExecutionResult::Failed { reason });
+        }
         
-        // Update nonce from network
-        wallet.update_nonce(&self.config.rpc_url).await?;
+        // Gas limit of each flash loan transaction
+        let mut gas_limits = Vec::with_capacity(plans.len());
+        for plan in plans {
+            // Measure the real calldata; size the gas limit from the refreshed model
+            let ticks_crossed: Vec<u32> = plan.simulation.swaps.iter().map(|s| s.ticks_crossed).collect();
+            let gas_limit = match self.wallet_manager.profit_wallet_address() {
+                Some(from) => match self.calibrate_gas(plan.cycle, plan.simulation, &plan.tx, from).await {
+                    Ok(_) => match plan.simulation.route.as_ref().filter(|r| r.is_split()) {
+                        Some(route) => self.gas_model.route_gas_limit(route),
+                        None => self.gas_model.gas_limit(plan.cycle, &ticks_crossed),
+                    },
+                    Err(e) => {
+                        return Ok(ExecutionResult::Failed {
+                            reason: format!("Gas estimation failed (tx would revert?): {}", e),
+                        });
+                    }
+                },
+                None => plan.tx.gas_limit,
+            };
+            
+            gas_limits.push(gas_limit);
+        }
         
-        // Sign the flash loan transaction
-        let signed_tx = wallet.sign_transaction(
-            flash_loan_tx.to,
-            flash_loan_tx.calldata.clone(),
-            flash_loan_tx.value,
-            flash_loan_tx.gas_limit,
-            gas_price,
-            priority_fee,
-        ).await?;
+        // Take nonces after any bundle still being built, reusing those of the
+        // submitted bundle this one replaces, until the last block it targets
+        let expires_after_block = self.submission.target_blocks(target_block)
+            .last()
+            .copied()
+            .unwrap_or(target_block);
+        let nonces = self.nonces.reserve(plans.len(), expires_after_block).await?;
+        
+        let result = self.sign_and_submit(plans, &nonces, &gas_limits, &fees, current_block).await;
+        if !matches!(result, Ok(ExecutionResult::Submitted { .. })) {
+            self.nonces.release(&nonces);
+        }
+        result
+    }
+    
+    /// Sign the plans with reserved nonces, simulate the bundle on the relay
+    /// and broadcast it
+    async fn sign_and_submit(
+        &self,
+        plans: &[PlannedArb<'_>],
+        nonces: &[u64],
+        gas_limits: &[u64],
+        fees: &FeeRecommendation,
+        current_block: u64,
+    ) -> Result<ExecutionResult> {
+        let target_block = current_block + 1;
+        
+        // Sign each flash loan transaction (consecutive nonces)
+        let mut signed_txs = Vec::with_capacity(plans.len());
+        for ((plan, &nonce), &gas_limit) in plans.iter().zip(nonces).zip(gas_limits) {
+            signed_txs.push(self.wallet_manager.sign_transaction_at(
+                nonce,
+                plan.tx.to,
+                plan.tx.calldata.clone(),
+                plan.tx.value,
+                gas_limit,
+                fees,
+            ).await?);
+        }
         
-        info!("✓ Transaction signed");
+        info!("✓ {} transaction(s) signed", signed_txs.len());
         
-        // Calculate expected profit in wei
-        let expected_profit_wei = U256::from((simulation.profit_usd * 1e18 / 3500.0) as u128);
+        let expected_profit_usd = total_profit_usd(plans);
+        let bribe_wei = total_bribe(plans);
         
         // Build the bundle
-        let bundle = self.bundle_builder.build_bundle(
-            flash_loan_tx,
-            signed_tx.clone(),
-            current_block + 1,
-            expected_profit_wei,
+        let bundle = self.bundle_builder.build_multi_bundle(
+            signed_txs,
+            target_block,
+            bribe_wei,
         )?;
         
-        info!("✓ Bundle built for block {}", current_block + 1);
+        info!("✓ Bundle built for block {}", target_block);
         
-        // Simulate first
-        let sim_result = self.flashbots_client.simulate_bundle(&bundle, &wallet).await?;
+        // Simulate the combined plan first
+        let sim_result = self.flashbots_client.simulate_bundle(&bundle, &self.wallet_manager).await?;
         
         if !sim_result.success {
             return Ok(ExecutionResult::Failed {
@@ -320,37 +659,162 @@ impl ExecutionEngine {
             });
         }
         
-        info!("✓ Simulation passed, gas used: {:?}", sim_result.gas_used);
+        if sim_result.eth_sent_to_coinbase < bribe_wei {
+            return Ok(ExecutionResult::Failed {
+                reason: format!(
+                    "Bundle pays the coinbase {} wei, plans bribe {} wei",
+                    sim_result.eth_sent_to_coinbase, bribe_wei
+                ),
+            });
+        }
         
-        // Submit to Flashbots
-        let response = self.flashbots_client.send_bundle(&bundle, &wallet).await?;
+        info!(
+            "✓ Simulation passed, gas used: {:?}, coinbase paid {} wei",
+            sim_result.gas_used, sim_result.eth_sent_to_coinbase
+        );
         
-        if let Some(ref error) = response.error {
+        // Broadcast to every builder for the next `target_blocks` blocks
+        let pools: Vec<Address> = plans.iter().flat_map(hop_pools).flatten().collect();
+        let report = self.submission.submit(&bundle, current_block, &pools, &self.wallet_manager).await?;
+        
+        if report.accepted() == 0 {
             return Ok(ExecutionResult::Failed {
-                reason: format!("Bundle submission failed: {} (code {})", error.message, error.code),
+                reason: format!("Bundle submission failed: {}", report.errors().join("; ")),
             });
         }
         
-        let bundle_hash = response.bundle_hash.unwrap_or_else(|| "unknown".to_string());
+        let bundle_hash = report.bundle_hash().unwrap_or("unknown").to_string();
+        
+        // Follow each transaction through the target blocks
+        for ((plan, raw), &nonce) in plans.iter().zip(&bundle.transactions).zip(nonces) {
+            let tx_hash = TrackedTx::hash_of(raw);
+            self.nonces.submitted(nonce, tx_hash);
+            self.inclusion.track(TrackedTx {
+                tx_hash,
+                bundle_hash: bundle_hash.clone(),
+                target_blocks: report.target_blocks.clone(),
+                token: plan.cycle.path[0],
+                path: plan.cycle.path.clone(),
+                pools: hop_pools(plan),
+                expected_profit: plan.expected_profit,
+                expected_profit_usd: plan.simulation.profit_usd,
+                expected_bribe: plan.bribe,
+                gas_sample: (!plan.simulation.route.as_ref().is_some_and(|r| r.is_split())).then(|| {
+                    let ticks_crossed = plan.simulation.swaps.iter().map(|s| s.ticks_crossed).collect();
+                    (plan.cycle.clone(), ticks_crossed)
+                }),
+            });
+        }
         
         info!("🎯 Bundle submitted! Hash: {}", bundle_hash);
-        info!("   Target block: {}", current_block + 1);
-        info!("   Expected profit: ${:.2}", simulation.profit_usd);
+        info!("   Target blocks: {:?} ({} accepted)", report.target_blocks, report.accepted());
+        info!("   Expected profit: ${:.2}", expected_profit_usd);
         
         Ok(ExecutionResult::Submitted {
             bundle_hash,
-            target_block: current_block + 1,
-            expected_profit_usd: simulation.profit_usd,
+            target_block,
+            expected_profit_usd,
         })
     }
     
-    /// Get current gas price from network
-    async fn get_current_gas_price(&self) -> Result<u128> {
-        let provider = ProviderBuilder::new()
-            .on_http(self.config.rpc_url.parse()?);
+    /// Executor address to simulate against: the deployed one, or a scratch
+    /// address when runtime bytecode is available to inject there
+    fn simulation_executor(&self) -> Option<Address> {
+        self.flash_loan_builder.executor_address().or_else(|| {
+            (self.config.execution_mode != ExecutionMode::Production && self.call_simulator.has_executor_code())
+                .then_some(SCRATCH_EXECUTOR)
+        })
+    }
+    
+    /// Run the plans' transactions in order through eth_simulateV1 / eth_call
+    /// with state overrides, logging what the executor reports
+    async fn call_simulate(&self, plans: &[PlannedArb<'_>], current_block: u64) -> Result<Vec<CallSimResult>> {
+        let executor = self.simulation_executor()
+            .ok_or_else(|| eyre!("No executor deployed and no executor bytecode to inject"))?;
+        let from = self.wallet_manager.profit_wallet_address().unwrap_or_default();
+        let txs: Vec<&FlashLoanTransaction> = plans.iter().map(|p| &p.tx).collect();
+        
+        let results = self.call_simulator.simulate(&txs, from, executor, current_block).await?;
+        for (plan, result) in plans.iter().zip(&results) {
+            if result.success {
+                info!(
+                    "✅ Call simulation passed: gas {:?}, executor profit {} (expected ${:.2})",
+                    result.gas_used, result.total_profit(), plan.simulation.profit_usd
+                );
+            } else {
+                warn!("❌ Call simulation reverted: {}", result.error.as_deref().unwrap_or("unknown"));
+            }
+        }
+        Ok(results)
+    }
+    
+    /// Run the plans' transactions in order on a local fork of `current_block`
+    ///
+    /// Returns the reason to abort if any transaction reverts. Fork errors
+    /// (RPC, missing runtime) only skip the check.
+    async fn fork_simulate(&self, plans: &[PlannedArb<'_>], current_block: u64) -> Option<String> {
+        let simulator = self.evm_simulator.as_ref()?;
+        let executor = self.simulation_executor()?;
+        let from = self.wallet_manager.profit_wallet_address().unwrap_or_default();
+        
+        let txs: Vec<&FlashLoanTransaction> = plans.iter().map(|p| &p.tx).collect();
+        let mut tokens: Vec<Address> = plans.iter().flat_map(|p| p.cycle.path.iter().copied()).collect();
+        tokens.sort();
+        tokens.dedup();
+        
+        let results = match simulator.simulate_bundle(&txs, from, executor, &tokens, current_block).await {
+            Ok(results) => results,
+            Err(e) => {
+                warn!("Fork simulation unavailable: {}", e);
+                return None;
+            }
+        };
         
-        let gas_price = provider.get_gas_price().await?;
-        Ok(gas_price)
+        for (plan, result) in plans.iter().zip(&results) {
+            if !result.success {
+                let reason = result.revert_reason.clone().unwrap_or_default();
+                warn!("❌ Fork simulation reverted after {} gas: {}", result.gas_used, reason);
+                return Some(format!("Fork simulation reverted: {}", reason));
+            }
+            if result.coinbase_payment() < plan.bribe {
+                warn!("❌ Coinbase received {} wei, plan bribes {}", result.coinbase_payment(), plan.bribe);
+                return Some(format!("Bribe not received by the coinbase ({} < {} wei)", result.coinbase_payment(), plan.bribe));
+            }
+            info!("✅ Fork simulation passed: {} gas, output 0x{}", result.gas_used, hex::encode(&result.output));
+            for delta in &result.balance_deltas {
+                info!("   {:?} @ {:?}: {}", delta.token, delta.account, delta.delta());
+            }
+            debug!(
+                "   Start token profit at executor: {}",
+                result.delta_of(plan.cycle.path[0], executor)
+            );
+        }
+        None
+    }
+    
+    /// Run eth_estimateGas / debug_traceCall on the executor tx and feed the gas model
+    async fn calibrate_gas(
+        &self,
+        cycle: &ArbitrageCycle,
+        simulation: &ArbitrageSimulation,
+        flash_loan_tx: &FlashLoanTransaction,
+        from: Address,
+    ) -> Result<u64> {
+        // Split routes have several swaps per hop: their gas can't be attributed
+        // to the cycle's hops, so only check that the transaction executes
+        if simulation.route.as_ref().is_some_and(|r| r.is_split()) {
+            return flash_loan_tx.estimate_gas(&self.config.rpc_url, from).await;
+        }
+        
+        let ticks_crossed: Vec<u32> = simulation.swaps.iter().map(|s| s.ticks_crossed).collect();
+        self.gas_model.calibrate_with_call(
+            &self.config.rpc_url,
+            from,
+            flash_loan_tx.to,
+            &flash_loan_tx.calldata,
+            cycle,
+            &ticks_crossed,
+        ).await
     }
     
     /// Get current block number
@@ -361,65 +825,76 @@ impl ExecutionEngine {
         Ok(provider.get_block_number().await?)
     }
     
-    /// Monitor a submitted bundle for inclusion
-    pub async fn monitor_bundle(
-        &self,
-        bundle_hash: &str,
-        target_block: u64,
-        timeout_blocks: u64,
-    ) -> Result<BundleStatus> {
-        let provider = ProviderBuilder::new()
-            .on_http(self.config.rpc_url.parse()?);
+    /// Resolve submitted transactions that landed or whose target blocks passed
+    ///
+    /// Once one lands, bundles still pending from earlier blocks reuse its
+    /// nonce and are cancelled, and the wallet's nonce reservations are
+    /// reconciled with the chain.
+    pub async fn poll_inclusions(&self) -> Vec<InclusionReport> {
+        let mut reports = Vec::new();
+        if self.wallet_manager.has_profit_wallet() {
+            if let Err(e) = self.nonces.refresh().await {
+                warn!("Nonce refresh failed: {}", e);
+            }
+        }
         
-        let deadline = target_block + timeout_blocks;
+        // Used nonces, settled here or while reserving: our transactions that
+        // didn't use them can never land
+        for settled in self.nonces.take_settled() {
+            reports.extend(self.inclusion.resolve_nonce(&settled));
+        }
         
-        loop {
-            let current_block = provider.get_block_number().await?;
-            
-            if current_block > deadline {
-                return Ok(BundleStatus::NotIncluded {
-                    checked_until_block: current_block,
-                });
+        match self.inclusion.poll().await {
+            Ok(polled) => reports.extend(polled),
+            Err(e) => {
+                warn!("Inclusion check failed: {}", e);
+                return reports;
             }
-            
-            // Check bundle stats from Flashbots
-            if let Ok(stats) = self.flashbots_client.get_bundle_stats(bundle_hash).await {
-                if let Some(result) = stats.get("result") {
-                    if let Some(is_simulated) = result.get("isSimulated") {
-                        if is_simulated.as_bool() == Some(true) {
-                            // Bundle was simulated by builder
-                            debug!("Bundle {} simulated by builder", bundle_hash);
-                        }
-                    }
-                }
+        }
+        
+        let landed = reports.iter()
+            .filter_map(|r| match r.status {
+                BundleStatus::Included { block_number, .. } | BundleStatus::Reverted { block_number, .. } => Some(block_number),
+                _ => None,
+            })
+            .max();
+        if let Some(block_number) = landed {
+            if let Err(e) = self.submission.cancel_stale(block_number, &self.wallet_manager).await {
+                warn!("Failed to cancel stale bundles: {}", e);
             }
-            
-            // Wait for next block
-            tokio::time::sleep(tokio::time::Duration::from_secs(12)).await;
         }
+        reports
     }
     
-    /// Log a profitable opportunity to file
-    fn log_opportunity(
+    /// Expected vs realized totals of the transactions resolved so far
+    pub fn inclusion_stats(&self) -> InclusionStats {
+        self.inclusion.stats()
+    }
+    
+    /// Log a profitable opportunity to file, costing gas at the live gas and
+    /// ETH prices
+    async fn log_opportunity(
         &self,
         cycle: &ArbitrageCycle,
-        simulation: &crate::simulator::swap_simulator::ArbitrageSimulation,
+        simulation: &ArbitrageSimulation,
     ) -> Result<()> {
         use crate::config::OpportunityLog;
         use chrono::Utc;
         
-        let gas_cost_usd = simulation.total_gas_used as f64 * 20.0 * 1e-9 * 3500.0;
+        let gas_price_gwei = self.gas_oracle.get_gas_price().await.gas_price_gwei;
+        let eth_price_usd = self.bundle_builder.bid_strategy().eth_price();
+        let gas_cost_usd = simulation.total_gas_used as f64 * gas_price_gwei * 1e-9 * eth_price_usd;
         
         let log = OpportunityLog {
             timestamp: Utc::now(),
             path: cycle.path.iter().map(|a| format!("{:?}", a)).collect(),
             dexes: cycle.dexes.iter().map(|d| d.to_string()).collect(),
-            input_usd: self.config.default_simulation_usd,
+            input_usd: simulation.input_usd,
             gross_profit_usd: simulation.profit_usd + gas_cost_usd,
             gas_cost_usd,
             net_profit_usd: simulation.profit_usd,
-            gas_price_gwei: 20.0,
-            eth_price_usd: 3500.0,
+            gas_price_gwei,
+            eth_price_usd,
             block_number: 0,
         };
         
@@ -487,21 +962,3 @@ impl ExecutionResult {
         )
     }
 }
-
-/// Status of a submitted bundle
-#[derive(Debug)]
-pub enum BundleStatus {
-    /// Bundle was included in a block
-    Included {
-        block_number: u64,
-        tx_hash: String,
-    },
-    /// Bundle was not included within the timeout
-    NotIncluded {
-        checked_until_block: u64,
-    },
-    /// Error checking bundle status
-    Error {
-        reason: String,
-    },
-}
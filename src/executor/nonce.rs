@@ -0,0 +1,291 @@
+//! Execution Wallet Nonces
+//!
+//! One long-lived view of the profit wallet's nonces, shared by every
+//! submission. A bundle being signed and simulated holds its nonces
+//! exclusively, so two executions in flight never sign the same nonce.
+//! Once submitted, a bundle is superseded by the next submission: builders
+//! replace it in every block it still targets, so the next bundle reuses
+//! its nonces, remembering the earlier transactions in case one lands.
+//!
+//! On refresh the account's confirmed (`latest`) and pending transaction
+//! counts are read. Reservations below the confirmed nonce have been used,
+//! by one of our transactions (its receipt exists) or by a replacement. A
+//! reservation whose last target block passed unused is released, together
+//! with every reservation above it, which can no longer land. Used nonces
+//! are kept until the inclusion tracker takes them, whichever refresh (a poll
+//! or a reservation) settled them.
+
+use alloy_primitives::{Address, B256};
+use alloy_provider::{Provider, ProviderBuilder};
+use eyre::{eyre, Result};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::config::Config;
+
+// ============================================
+// NONCE BOOK
+// ============================================
+
+/// A nonce held by a bundle being built or already submitted
+#[derive(Debug, Clone, PartialEq)]
+struct Reservation {
+    /// Transactions submitted with this nonce, superseded ones first
+    tx_hashes: Vec<B256>,
+    /// Whether the latest bundle holding it went out to the builders
+    submitted: bool,
+    /// Last block the transaction can land in
+    expires_after_block: u64,
+}
+
+/// How a reserved nonce was used on chain
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NonceOutcome {
+    /// One of our transactions landed with it
+    Included,
+    /// Another transaction from the wallet took it
+    Replaced,
+}
+
+/// A reservation resolved by a refresh
+#[derive(Debug, Clone, PartialEq)]
+pub struct SettledNonce {
+    pub nonce: u64,
+    /// The transaction that landed, when it was ours
+    pub tx_hash: Option<B256>,
+    /// Every transaction we submitted with this nonce
+    pub submitted: Vec<B256>,
+    pub outcome: NonceOutcome,
+}
+
+/// Nonce bookkeeping, without network access
+#[derive(Debug, Default)]
+struct NonceBook {
+    /// Transaction count at the latest block
+    confirmed: u64,
+    /// Transaction count including the public mempool
+    pending: u64,
+    reservations: BTreeMap<u64, Reservation>,
+}
+
+impl NonceBook {
+    /// Apply fresh account counts at `block`; returns the used nonces with
+    /// the transactions that may have used them
+    fn apply(&mut self, confirmed: u64, pending: u64, block: u64) -> Vec<(u64, Vec<B256>)> {
+        self.confirmed = confirmed;
+        self.pending = pending.max(confirmed);
+
+        let live = self.reservations.split_off(&confirmed);
+        let used = std::mem::replace(&mut self.reservations, live)
+            .into_iter()
+            .map(|(nonce, r)| (nonce, r.tx_hashes))
+            .collect();
+
+        // The first expired reservation strands every later one
+        if let Some(&expired) = self.reservations.iter()
+            .find(|(_, r)| r.expires_after_block <= block)
+            .map(|(nonce, _)| nonce)
+        {
+            let released = self.reservations.split_off(&expired);
+            debug!("Released {} expired nonce(s) from {}", released.len(), expired);
+        }
+        used
+    }
+
+    /// First nonce not taken on chain, in the mempool or by a bundle in progress
+    fn next_nonce(&self) -> u64 {
+        let after_in_progress = self.reservations.iter()
+            .filter(|(_, r)| !r.submitted)
+            .map(|(n, _)| n + 1)
+            .max()
+            .unwrap_or(0);
+        self.pending.max(after_in_progress)
+    }
+
+    fn reserve(&mut self, count: usize, expires_after_block: u64) -> Vec<u64> {
+        let first = self.next_nonce();
+        let nonces: Vec<u64> = (first..first + count as u64).collect();
+        for &nonce in &nonces {
+            // A submitted bundle holding the nonce is replaced by this one
+            let r = self.reservations.entry(nonce).or_insert_with(|| Reservation {
+                tx_hashes: vec![],
+                submitted: false,
+                expires_after_block,
+            });
+            r.submitted = false;
+            r.expires_after_block = r.expires_after_block.max(expires_after_block);
+        }
+        nonces
+    }
+
+    /// Drop the reservations of a bundle that was never submitted; bundles
+    /// it would have replaced stay live, and other executions in progress
+    /// keep theirs
+    fn release(&mut self, nonces: &[u64]) {
+        for nonce in nonces {
+            if let Some(mut r) = self.reservations.remove(nonce) {
+                if !r.tx_hashes.is_empty() {
+                    r.submitted = true;
+                    self.reservations.insert(*nonce, r);
+                }
+            }
+        }
+    }
+
+    fn submitted(&mut self, nonce: u64, tx_hash: B256) {
+        if let Some(r) = self.reservations.get_mut(&nonce) {
+            r.tx_hashes.push(tx_hash);
+            r.submitted = true;
+        }
+    }
+}
+
+// ============================================
+// NONCE MANAGER
+// ============================================
+
+/// Confirmed and pending nonces of the profit wallet, plus the nonces held
+/// by bundles still in flight
+pub struct NonceManager {
+    rpc_url: String,
+    address: Option<Address>,
+    book: Mutex<NonceBook>,
+    /// Used nonces not yet taken by `take_settled`
+    settled: Mutex<Vec<SettledNonce>>,
+}
+
+impl NonceManager {
+    pub fn new(config: &Config, address: Option<Address>) -> Self {
+        Self {
+            rpc_url: config.rpc_url.clone(),
+            address,
+            book: Mutex::new(NonceBook::default()),
+            settled: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Re-read the account's nonces and resolve used and expired reservations
+    pub async fn refresh(&self) -> Result<()> {
+        let address = self.address.ok_or_else(|| eyre!("No profit wallet configured"))?;
+        let provider = ProviderBuilder::new()
+            .on_http(self.rpc_url.parse()?);
+
+        // Block first: counts read afterwards are at least as recent
+        let block = provider.get_block_number().await?;
+        let confirmed = provider.get_transaction_count(address).await?;
+        let pending = provider.get_transaction_count(address).pending().await?;
+
+        let used = self.book.lock().unwrap().apply(confirmed, pending, block);
+
+        let mut settled = Vec::with_capacity(used.len());
+        for (nonce, tx_hashes) in used {
+            // Latest submission first
+            let mut tx_hash = None;
+            for &hash in tx_hashes.iter().rev() {
+                if provider.get_transaction_receipt(hash).await?.is_some() {
+                    tx_hash = Some(hash);
+                    break;
+                }
+            }
+            let outcome = match tx_hash {
+                Some(hash) => {
+                    debug!("Nonce {} used by {:?}", nonce, hash);
+                    NonceOutcome::Included
+                }
+                None => {
+                    warn!("Nonce {} was used by a transaction we did not submit", nonce);
+                    NonceOutcome::Replaced
+                }
+            };
+            settled.push(SettledNonce { nonce, tx_hash, submitted: tx_hashes, outcome });
+        }
+
+        debug!("Nonces at block {}: confirmed {}, pending {}", block, confirmed, pending);
+        self.settled.lock().unwrap().extend(settled);
+        Ok(())
+    }
+
+    /// Nonces used on chain since the last call
+    pub fn take_settled(&self) -> Vec<SettledNonce> {
+        std::mem::take(&mut *self.settled.lock().unwrap())
+    }
+
+    /// Refresh, then hold `count` consecutive nonces until `expires_after_block`
+    pub async fn reserve(&self, count: usize, expires_after_block: u64) -> Result<Vec<u64>> {
+        self.refresh().await?;
+        let mut book = self.book.lock().unwrap();
+        let nonces = book.reserve(count, expires_after_block);
+        info!(
+            "Reserved nonce(s) {:?} until block {} (confirmed {}, pending {})",
+            nonces, expires_after_block, book.confirmed, book.pending
+        );
+        Ok(nonces)
+    }
+
+    /// Record the transaction a builder accepted with a reserved nonce; the
+    /// next reservation supersedes it
+    pub fn submitted(&self, nonce: u64, tx_hash: B256) {
+        self.book.lock().unwrap().submitted(nonce, tx_hash);
+    }
+
+    /// Give back nonces whose bundle was never submitted
+    pub fn release(&self, nonces: &[u64]) {
+        debug!("Releasing nonce(s) {:?}", nonces);
+        self.book.lock().unwrap().release(nonces);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_in_progress_nonces_are_exclusive() {
+        let mut book = NonceBook::default();
+        book.apply(5, 5, 100);
+
+        // Two executions in flight at once
+        assert_eq!(book.reserve(2, 103), vec![5, 6]);
+        assert_eq!(book.reserve(1, 103), vec![7]);
+        book.release(&[7]);
+
+        // One failing leaves the other's nonces alone, and its own are not
+        // handed out below a bundle still in progress
+        assert_eq!(book.reserve(1, 103), vec![7]);
+        book.release(&[5, 6]);
+        assert!(book.reservations.contains_key(&7));
+        assert_eq!(book.reserve(1, 103), vec![8]);
+        book.release(&[7, 8]);
+        assert_eq!(book.reserve(2, 103), vec![5, 6]);
+
+        // The next bundle replaces the submitted one and reuses its nonces
+        book.submitted(5, B256::repeat_byte(1));
+        book.submitted(6, B256::repeat_byte(2));
+        assert_eq!(book.reserve(1, 104), vec![5]);
+        assert_eq!(book.reservations[&5].tx_hashes, vec![B256::repeat_byte(1)]);
+
+        // Failing before submission leaves the earlier bundle live
+        book.release(&[5]);
+        assert!(book.reservations.values().all(|r| r.submitted));
+
+        // A public transaction from the wallet is skipped
+        book.apply(5, 7, 101);
+        assert_eq!(book.reserve(1, 104), vec![7]);
+    }
+
+    #[test]
+    fn test_used_and_expired_nonces_are_freed() {
+        let mut book = NonceBook::default();
+        book.apply(5, 5, 100);
+        book.reserve(2, 102);
+        book.submitted(5, B256::repeat_byte(1));
+        book.reserve(1, 110);
+
+        // Nonce 5 landed; 6 expires at 102, stranding 7
+        let used = book.apply(6, 6, 102);
+        assert_eq!(used, vec![(5, vec![B256::repeat_byte(1)])]);
+        assert_eq!(book.next_nonce(), 6);
+        assert!(book.reservations.is_empty());
+    }
+}
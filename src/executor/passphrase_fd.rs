@@ -0,0 +1,54 @@
+//! Keystore Passphrase Descriptor
+//!
+//! `KEYSTORE_PASSWORD_FD` names a file descriptor the parent process keeps
+//! open with the passphrase(s) written to it. Shared by the bot's signer and
+//! the `generate-wallet` binary (included there by path), so it depends on
+//! `std` only.
+
+use std::io;
+
+/// Read everything written to file descriptor `fd`
+///
+/// The descriptor stays open: it is read through a duplicate, so naming
+/// stdin, stdout or stderr never closes them.
+#[cfg(unix)]
+pub fn read_fd(fd: i32) -> io::Result<String> {
+    use std::io::Read;
+    use std::os::fd::BorrowedFd;
+
+    if fd < 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid descriptor {}", fd)));
+    }
+    // SAFETY: only borrowed long enough to duplicate it; the duplicate is
+    // what gets read and closed
+    let owned = unsafe { BorrowedFd::borrow_raw(fd) }.try_clone_to_owned()?;
+    let mut content = String::new();
+    std::fs::File::from(owned).read_to_string(&mut content)?;
+    Ok(content)
+}
+
+#[cfg(not(unix))]
+pub fn read_fd(_fd: i32) -> io::Result<String> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "reading a passphrase from a file descriptor needs a Unix platform"))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::io::{Seek, Write};
+    use std::os::fd::AsRawFd;
+
+    #[test]
+    fn test_read_fd_leaves_descriptor_open() {
+        let path = std::env::temp_dir().join("sniper_passphrase_fd_test");
+        let mut file = std::fs::File::options().create(true).truncate(true).read(true).write(true).open(&path).unwrap();
+        file.write_all(b"first\nsecond\n").unwrap();
+        file.rewind().unwrap();
+
+        assert_eq!(read_fd(file.as_raw_fd()).unwrap(), "first\nsecond\n");
+        // Still ours to use
+        file.write_all(b"third\n").unwrap();
+        assert!(read_fd(-1).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
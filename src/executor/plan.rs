@@ -0,0 +1,505 @@
+//! Executor Plan - versioned calldata for `ArbitrageExecutor.execute`
+//!
+//! A plan carries everything the executor needs for one arbitrage: the
+//! lender, the borrowed token and amount, the minimum final balance, and
+//! every hop with explicit pool addresses and per-leg parameters. It is
+//! encoded as one version byte followed by the ABI-encoded `Plan` struct, so
+//! the contract rejects calldata built for a different layout.
+//!
+//! A plan can also carry a bribe: ETH paid to `block.coinbase` out of the
+//! profit once the loan is repaid, unwrapped from WETH or bought with the
+//! profit token along the plan's own hops up to WETH.
+
+use alloy_primitives::{address, aliases::U24, Address, Bytes, U256};
+use alloy_sol_types::{sol, SolCall, SolValue};
+use eyre::{eyre, Result};
+
+use super::actions::{decode_balancer_data, encode_balancer_data};
+use super::flash_loan::DexType;
+use crate::brain::ArbitrageCycle;
+use crate::config::FlashLoanProvider;
+use crate::simulator::SplitRoute;
+
+/// Layout version of the encoded plan (first calldata byte of `execute`)
+pub const PLAN_VERSION: u8 = 4;
+
+/// Wrapped Ether (bribes are unwrapped from it)
+pub const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+
+/// Basis points in 100%
+const BPS: u64 = 10_000;
+
+/// Largest fee a leg can carry (parts per million, below 100%)
+const MAX_LEG_FEE: u32 = 999_999;
+
+sol! {
+    /// Our executor contract interface (matches ArbitrageExecutor.sol)
+    #[derive(Debug, Default, PartialEq)]
+    interface IArbitrageExecutor {
+        /// One pool a hop is routed through
+        /// @param dexType DexType of the pool
+        /// @param fee Pool fee in parts per million (V2 output math)
+        /// @param shareBps Share of the hop input (last leg takes the remainder)
+        /// @param minAmountOut Minimum output of this leg
+        /// @param data DEX-specific parameters (Curve / Balancer, see actions.rs)
+        struct Leg {
+            uint8 dexType;
+            address pool;
+            uint24 fee;
+            uint16 shareBps;
+            uint256 minAmountOut;
+            bytes data;
+        }
+
+        /// One hop of the cycle, across one or more pools
+        struct Hop {
+            address tokenIn;
+            address tokenOut;
+            Leg[] legs;
+        }
+
+        /// Coinbase payment made from the profit after repaying the loan
+        /// @param value Wei paid to `block.coinbase` (zero = no bribe)
+        /// @param amountIn Profit tokens spent on it
+        /// @param hops Profit token -> WETH (empty when the token is WETH)
+        struct Bribe {
+            uint256 value;
+            uint256 amountIn;
+            Hop[] hops;
+        }
+
+        /// Everything needed for one flash-loan funded arbitrage
+        /// @param lenderPool Uniswap V3 pool lending via `flash` (else zero)
+        /// @param minOut Input + fee + min profit + the bribe's amountIn
+        struct Plan {
+            uint8 lender;
+            address lenderPool;
+            address token;
+            uint256 amount;
+            uint256 minOut;
+            Hop[] hops;
+            Bribe bribe;
+        }
+
+        /// Borrow, run the plan, repay, and book the profit
+        /// @param plan PLAN_VERSION byte followed by abi.encode(Plan)
+        function execute(bytes calldata plan) external returns (uint256 profit);
+
+        /// Withdraw accumulated profits
+        function withdrawProfits(address token) external;
+
+        /// Withdraw the full balance of a token
+        function emergencyWithdraw(address token) external;
+    }
+}
+
+pub use IArbitrageExecutor::{Bribe as PlanBribe, Hop as PlanHop, Leg as PlanLeg, Plan};
+
+/// Lender id the executor dispatches the flash loan on
+pub fn lender_id(provider: FlashLoanProvider) -> u8 {
+    match provider {
+        FlashLoanProvider::BalancerV2 => 0,
+        FlashLoanProvider::AaveV3 => 1,
+        FlashLoanProvider::UniswapV3 => 2,
+    }
+}
+
+impl Plan {
+    /// Plan for a cycle with one pool per hop (no per-hop minimums, only the
+    /// final `min_out` is known)
+    pub fn from_cycle(
+        provider: FlashLoanProvider,
+        cycle: &ArbitrageCycle,
+        amount: U256,
+        min_out: U256,
+    ) -> Result<Self> {
+        if cycle.path.len() != cycle.pools.len() + 1 || cycle.pools.len() != cycle.dexes.len() {
+            return Err(eyre!("Cycle path, pools and dexes do not line up"));
+        }
+
+        let hops = cycle.pools.iter()
+            .enumerate()
+            .map(|(i, pool)| PlanHop {
+                tokenIn: cycle.path[i],
+                tokenOut: cycle.path[i + 1],
+                legs: vec![leg(
+                    DexType::for_edge(cycle.dexes[i], *pool, cycle.path[i], cycle.path[i + 1]),
+                    *pool,
+                    cycle.fees.get(i).copied().unwrap_or_default(),
+                    BPS as u16,
+                    U256::ZERO,
+                )],
+            })
+            .collect();
+
+        Self::checked(provider, cycle.path[0], amount, min_out, hops)
+    }
+
+    /// Plan for a simulated route: every hop with its pool legs, each leg
+    /// guarded by its simulated output less `max_slippage` per hop so far
+    pub fn from_route(
+        provider: FlashLoanProvider,
+        route: &SplitRoute,
+        amount: U256,
+        min_out: U256,
+        max_slippage: f64,
+    ) -> Result<Self> {
+        let token = route.hops.first()
+            .map(|h| h.token_in)
+            .ok_or_else(|| eyre!("Empty route"))?;
+
+        let hops = route.hops.iter()
+            .enumerate()
+            .map(|(i, hop)| {
+                // Shortfalls compound along the route, so later hops get more room
+                let tolerance_bps = ((max_slippage * (i + 1) as f64) * BPS as f64).min(BPS as f64) as u64;
+                PlanHop {
+                    tokenIn: hop.token_in,
+                    tokenOut: hop.token_out,
+                    legs: hop.legs.iter()
+                        .map(|l| leg(
+                            DexType::for_edge(l.swap.dex, l.swap.pool, hop.token_in, hop.token_out),
+                            l.swap.pool,
+                            l.fee,
+                            l.share_bps,
+                            l.swap.amount_out * U256::from(BPS - tolerance_bps) / U256::from(BPS),
+                        ))
+                        .collect(),
+                }
+            })
+            .collect();
+
+        Self::checked(provider, token, amount, min_out, hops)
+    }
+
+    fn checked(
+        provider: FlashLoanProvider,
+        token: Address,
+        amount: U256,
+        min_out: U256,
+        hops: Vec<PlanHop>,
+    ) -> Result<Self> {
+        let plan = Self {
+            lender: lender_id(provider),
+            lenderPool: Address::ZERO,
+            token,
+            amount,
+            minOut: min_out,
+            hops,
+            bribe: PlanBribe::default(),
+        };
+        plan.validate()?;
+        Ok(plan)
+    }
+
+    /// Checks the executor would otherwise revert on
+    pub fn validate(&self) -> Result<()> {
+        let (first, last) = match (self.hops.first(), self.hops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Err(eyre!("Plan has no hops")),
+        };
+        if first.tokenIn != self.token || last.tokenOut != self.token {
+            return Err(eyre!("Plan must start and end in the borrowed token"));
+        }
+        if self.hops.windows(2).any(|w| w[0].tokenOut != w[1].tokenIn) {
+            return Err(eyre!("Plan hops are not chained"));
+        }
+        if self.hops.iter().any(|h| h.legs.is_empty()) {
+            return Err(eyre!("Plan has a hop without legs"));
+        }
+        
+        let bribe = &self.bribe;
+        match (bribe.hops.first(), bribe.hops.last()) {
+            (Some(first), Some(last)) if first.tokenIn != self.token || last.tokenOut != WETH => {
+                return Err(eyre!("Bribe must swap the borrowed token to WETH"));
+            }
+            (None, _) if !bribe.value.is_zero() && self.token != WETH => {
+                return Err(eyre!("Bribe needs a swap to WETH"));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Index of the first hop reaching WETH: ending in it, or passing
+    /// through it inside a merged Balancer batchSwap
+    pub fn weth_hop(&self) -> Option<usize> {
+        self.hops.iter().position(|h| h.tokenOut == WETH || batch_swap_weth_index(h).is_some())
+    }
+
+    /// Pay `value` wei to the coinbase out of the profit
+    ///
+    /// A WETH profit is unwrapped as is. Otherwise `amount_in` of the profit
+    /// token is swapped to WETH through the plan's hops up to `weth_hop`
+    /// (leg minimums dropped; the executor checks the WETH received instead).
+    pub fn with_bribe(mut self, value: U256, amount_in: U256) -> Result<Self> {
+        self.bribe = if value.is_zero() {
+            PlanBribe::default()
+        } else if self.token == WETH {
+            PlanBribe { value, amountIn: value, hops: vec![] }
+        } else {
+            let last = self.weth_hop()
+                .ok_or_else(|| eyre!("Plan never reaches WETH, cannot pay a bribe"))?;
+            let mut hops: Vec<PlanHop> = self.hops[..=last].iter()
+                .map(|hop| PlanHop {
+                    legs: hop.legs.iter()
+                        .map(|l| PlanLeg { minAmountOut: U256::ZERO, ..l.clone() })
+                        .collect(),
+                    ..hop.clone()
+                })
+                .collect();
+            
+            // Stop a batchSwap that passes through WETH there
+            if let Some(hop) = hops.last_mut().filter(|h| h.tokenOut != WETH) {
+                let (pool_ids, assets) = decode_balancer_data(&hop.legs[0].data)?;
+                let k = batch_swap_weth_index(hop).ok_or_else(|| eyre!("Bribe hop never reaches WETH"))?;
+                hop.legs[0].data = encode_balancer_data(&pool_ids[..k], &assets[..=k]);
+                hop.tokenOut = WETH;
+            }
+            PlanBribe { value, amountIn: amount_in, hops }
+        };
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// Version byte followed by the ABI-encoded plan
+    pub fn encode(&self) -> Bytes {
+        let mut encoded = vec![PLAN_VERSION];
+        encoded.extend_from_slice(&self.abi_encode());
+        encoded.into()
+    }
+
+    /// Decode an encoded plan, rejecting other layout versions
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        match data.split_first() {
+            Some((&PLAN_VERSION, body)) => Self::abi_decode(body)
+                .map_err(|e| eyre!("Failed to decode plan: {}", e)),
+            Some((version, _)) => Err(eyre!("Unsupported plan version {}", version)),
+            None => Err(eyre!("Empty plan")),
+        }
+    }
+
+    /// Calldata for `ArbitrageExecutor.execute(plan)`
+    pub fn execute_calldata(&self) -> Bytes {
+        IArbitrageExecutor::executeCall { plan: self.encode() }.abi_encode().into()
+    }
+
+    /// Decode `execute` calldata back into its plan
+    pub fn from_execute_calldata(calldata: &[u8]) -> Result<Self> {
+        let call = IArbitrageExecutor::executeCall::abi_decode(calldata)
+            .map_err(|e| eyre!("Not an execute call: {}", e))?;
+        Self::decode(&call.plan)
+    }
+}
+
+fn leg(dex_type: DexType, pool: Address, fee: u32, share_bps: u16, min_amount_out: U256) -> PlanLeg {
+    PlanLeg {
+        dexType: dex_type as u8,
+        pool,
+        fee: U24::from(fee.min(MAX_LEG_FEE)),
+        shareBps: share_bps,
+        minAmountOut: min_amount_out,
+        data: Bytes::new(),
+    }
+}
+
+/// Position of WETH among the assets of a merged Balancer hop, when it is
+/// an intermediate asset
+fn batch_swap_weth_index(hop: &PlanHop) -> Option<usize> {
+    let leg = match hop.legs.as_slice() {
+        [leg] if leg.dexType == DexType::BalancerV2 as u8 => leg,
+        _ => return None,
+    };
+    let (_, assets) = decode_balancer_data(&leg.data).ok()?;
+    assets.iter()
+        .position(|a| *a == WETH)
+        .filter(|&k| k > 0 && k + 1 < assets.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::B256;
+    use crate::cartographer::Dex;
+    use crate::executor::get_executor_contract_source;
+    use crate::simulator::{RouteHop, RouteLeg, SwapResult};
+
+    fn token(b: u8) -> Address {
+        Address::repeat_byte(b)
+    }
+
+    fn route_leg(pool: u8, dex: Dex, token_in: Address, token_out: Address, out: u64, share_bps: u16) -> RouteLeg {
+        RouteLeg {
+            swap: SwapResult {
+                pool: Address::repeat_byte(pool),
+                token_in,
+                token_out,
+                amount_in: U256::ZERO,
+                amount_out: U256::from(out),
+                gas_used: 0,
+                ticks_crossed: 0,
+                dex,
+            },
+            fee: 500,
+            share_bps,
+        }
+    }
+
+    #[test]
+    fn test_cycle_plan_round_trip() {
+        let cycle = ArbitrageCycle {
+            path: vec![token(1), token(2), token(3), token(1)],
+            pools: vec![token(0xa1), token(0xa2), token(0xa3)],
+            dexes: vec![Dex::UniswapV3, Dex::SushiswapV2, Dex::PancakeSwapV3],
+            total_weight: 0.0,
+            expected_return: 1.01,
+            prices: vec![],
+            fees: vec![500, 3000, 2500],
+        };
+        let plan = Plan::from_cycle(FlashLoanProvider::BalancerV2, &cycle, U256::from(1_000), U256::from(1_001)).unwrap();
+
+        let calldata = plan.execute_calldata();
+        assert_eq!(&calldata[..4], IArbitrageExecutor::executeCall::SELECTOR.as_slice());
+        let decoded = Plan::from_execute_calldata(&calldata).unwrap();
+        assert_eq!(decoded, plan);
+
+        assert_eq!(decoded.hops[1].legs[0].pool, token(0xa2));
+        assert_eq!(decoded.hops[1].legs[0].dexType, DexType::SushiswapV2 as u8);
+        assert_eq!(decoded.hops[2].legs[0].fee, U24::from(2500));
+        assert_eq!(decoded.lender, 0);
+    }
+
+    #[test]
+    fn test_route_plan_round_trip_and_version() {
+        let (a, b) = (token(1), token(2));
+        let route = SplitRoute {
+            hops: vec![
+                RouteHop {
+                    token_in: a,
+                    token_out: b,
+                    legs: vec![
+                        route_leg(0xb1, Dex::UniswapV3, a, b, 6_000, 6_000),
+                        route_leg(0xb2, Dex::UniswapV2, a, b, 4_000, 4_000),
+                    ],
+                },
+                RouteHop { token_in: b, token_out: a, legs: vec![route_leg(0xb3, Dex::SushiswapV3, b, a, 10_100, 10_000)] },
+            ],
+        };
+        let plan = Plan::from_route(FlashLoanProvider::AaveV3, &route, U256::from(10_000), U256::from(10_005), 0.01).unwrap();
+
+        // 1% room on the first hop, 2% on the second
+        assert_eq!(plan.hops[0].legs[0].minAmountOut, U256::from(5_940));
+        assert_eq!(plan.hops[1].legs[0].minAmountOut, U256::from(9_898));
+        assert_eq!(plan.hops[0].legs[1].shareBps, 4_000);
+
+        let encoded = plan.encode();
+        assert_eq!(encoded[0], PLAN_VERSION);
+        assert_eq!(Plan::decode(&encoded).unwrap(), plan);
+
+        let mut stale = encoded.to_vec();
+        stale[0] = PLAN_VERSION - 1;
+        assert!(Plan::decode(&stale).is_err());
+    }
+
+    #[test]
+    fn test_bribe_swaps_profit_to_weth() {
+        let cycle = ArbitrageCycle {
+            path: vec![token(1), WETH, token(3), token(1)],
+            pools: vec![token(0xa1), token(0xa2), token(0xa3)],
+            dexes: vec![Dex::UniswapV3, Dex::UniswapV2, Dex::UniswapV3],
+            total_weight: 0.0,
+            expected_return: 1.01,
+            prices: vec![],
+            fees: vec![500, 3000, 500],
+        };
+        let plan = Plan::from_cycle(FlashLoanProvider::BalancerV2, &cycle, U256::from(1_000), U256::from(1_001)).unwrap();
+        assert_eq!(plan.weth_hop(), Some(0));
+
+        let bribed = plan.clone().with_bribe(U256::from(7), U256::from(20)).unwrap();
+        assert_eq!(bribed.bribe.hops.len(), 1);
+        assert_eq!(bribed.bribe.hops[0].legs[0].pool, token(0xa1));
+        assert_eq!(bribed.bribe.amountIn, U256::from(20));
+        assert_eq!(Plan::decode(&bribed.encode()).unwrap(), bribed);
+
+        // No bribe, or no way to WETH
+        assert_eq!(plan.clone().with_bribe(U256::ZERO, U256::from(20)).unwrap().bribe, PlanBribe::default());
+        let no_weth = Plan { hops: plan.hops.iter().cloned().map(|h| PlanHop { tokenOut: token(9), ..h }).collect(), ..plan };
+        assert!(no_weth.with_bribe(U256::from(7), U256::from(20)).is_err());
+    }
+
+    #[test]
+    fn test_bribe_stops_merged_batch_swap_at_weth() {
+        // token1 -> WETH -> token3 merged into one batchSwap, then back to token1
+        let ids = [B256::repeat_byte(1), B256::repeat_byte(2)];
+        let leg = |dex: DexType, pool, data| PlanLeg {
+            dexType: dex as u8,
+            pool,
+            fee: U24::from(3000),
+            shareBps: 10_000,
+            minAmountOut: U256::from(1),
+            data,
+        };
+        let plan = Plan {
+            lender: 1,
+            lenderPool: Address::ZERO,
+            token: token(1),
+            amount: U256::from(1_000),
+            minOut: U256::from(1_001),
+            hops: vec![
+                PlanHop {
+                    tokenIn: token(1),
+                    tokenOut: token(3),
+                    legs: vec![leg(DexType::BalancerV2, token(0xb1), encode_balancer_data(&ids, &[token(1), WETH, token(3)]))],
+                },
+                PlanHop {
+                    tokenIn: token(3),
+                    tokenOut: token(1),
+                    legs: vec![leg(DexType::UniswapV3, token(0xa3), Bytes::new())],
+                },
+            ],
+            bribe: Default::default(),
+        };
+        assert_eq!(plan.weth_hop(), Some(0));
+
+        let bribed = plan.with_bribe(U256::from(7), U256::from(20)).unwrap();
+        let hop = &bribed.bribe.hops[0];
+        assert_eq!(hop.tokenOut, WETH);
+        assert_eq!(decode_balancer_data(&hop.legs[0].data).unwrap(), (vec![ids[0]], vec![token(1), WETH]));
+    }
+
+    #[test]
+    fn test_solidity_matches_encoding() {
+        let source = get_executor_contract_source();
+
+        assert!(source.contains(&format!("uint8 public constant PLAN_VERSION = {};", PLAN_VERSION)));
+        assert!(source.contains("function execute(bytes calldata plan)"));
+        assert!(source.contains("Plan memory p = abi.decode(plan[1:], (Plan));"));
+        assert!(source.contains(&format!("address public constant WETH = {};", WETH.to_checksum(None))));
+        for (name, id) in [
+            ("LENDER_BALANCER", lender_id(FlashLoanProvider::BalancerV2)),
+            ("LENDER_AAVE", lender_id(FlashLoanProvider::AaveV3)),
+            ("LENDER_UNISWAP_V3", lender_id(FlashLoanProvider::UniswapV3)),
+        ] {
+            assert!(source.contains(&format!("uint8 constant {} = {};", name, id)), "{} mismatch", name);
+        }
+        for (name, dex) in [
+            ("DEX_UNISWAP_V3", DexType::UniswapV3),
+            ("DEX_UNISWAP_V2", DexType::UniswapV2),
+            ("DEX_SUSHISWAP_V2", DexType::SushiswapV2),
+            ("DEX_PANCAKE_V3", DexType::PancakeSwapV3),
+            ("DEX_SUSHISWAP_V3", DexType::SushiswapV3),
+            ("DEX_BALANCER_V2", DexType::BalancerV2),
+            ("DEX_CURVE", DexType::Curve),
+            ("DEX_ERC4626", DexType::Erc4626),
+        ] {
+            assert!(source.contains(&format!("uint8 constant {} = {};", name, dex as u8)), "{} mismatch", name);
+        }
+        for field in [
+            "uint8 dexType;", "address pool;", "uint24 fee;", "uint16 shareBps;", "uint256 minAmountOut;", "bytes data;",
+            "Leg[] legs;", "uint8 lender;", "address lenderPool;", "uint256 minOut;", "Hop[] hops;",
+            "uint256 value;", "uint256 amountIn;", "Bribe bribe;",
+        ] {
+            assert!(source.contains(field), "missing {}", field);
+        }
+    }
+}
@@ -1,23 +1,28 @@
 //! Wallet Signer Module - Production Transaction Signing
 //!
 //! This module handles:
-//! - Loading private keys securely
+//! - Loading private keys securely (hex, or Web3 Secret Storage keystores)
 //! - Signing transactions for flash loan execution
 //! - Signing Flashbots bundle requests
 //!
 //! ⚠️  SECURITY WARNING:
 //! - Never log or expose private keys
-//! - Use environment variables, not hardcoded keys
+//! - Use environment variables, not hardcoded keys; prefer encrypted keystores
 //! - Consider hardware wallets for large amounts
 
-use alloy_primitives::{Address, Bytes, B256, U256, keccak256};
+use alloy_primitives::{Address, Bytes, U256, keccak256};
 use alloy_signer::Signer;
 use alloy_signer_local::PrivateKeySigner;
-use alloy_consensus::{TxLegacy, TxEip1559, SignableTransaction};
+use alloy_consensus::{TxLegacy, TxEip1559, TxEnvelope, SignableTransaction};
+use alloy_eips::eip2718::Encodable2718;
 use eyre::{eyre, Result};
+use std::path::Path;
 use std::str::FromStr;
 use tracing::{debug, info, warn};
 
+use super::passphrase_fd::read_fd;
+use crate::gas_oracle::FeeRecommendation;
+
 /// Wallet manager for signing operations
 pub struct WalletManager {
     /// Main wallet for profit withdrawal and contract ownership
@@ -41,21 +46,20 @@ impl WalletManager {
             .parse()
             .unwrap_or(1);
         
+        let mut passphrases = PassphraseSource::from_env();
+        
         // Load Flashbots signer (required for bundle submission)
         let flashbots_signer = match std::env::var("FLASHBOTS_SIGNER_KEY") {
-            Ok(key) => {
-                let key = key.trim_start_matches("0x");
-                match PrivateKeySigner::from_str(key) {
-                    Ok(signer) => {
-                        info!("✓ Flashbots signer loaded: {:?}", signer.address());
-                        Some(signer)
-                    }
-                    Err(e) => {
-                        warn!("Failed to parse FLASHBOTS_SIGNER_KEY: {}", e);
-                        None
-                    }
+            Ok(key) => match load_signer(&key, "Flashbots signer", &mut passphrases) {
+                Ok(signer) => {
+                    info!("✓ Flashbots signer loaded: {:?}", signer.address());
+                    Some(signer)
                 }
-            }
+                Err(e) => {
+                    warn!("Failed to load FLASHBOTS_SIGNER_KEY: {}", e);
+                    None
+                }
+            },
             Err(_) => {
                 debug!("FLASHBOTS_SIGNER_KEY not set");
                 None
@@ -64,19 +68,16 @@ impl WalletManager {
         
         // Load profit wallet (optional - only needed for production)
         let profit_wallet = match std::env::var("PROFIT_WALLET_PRIVATE_KEY") {
-            Ok(key) => {
-                let key = key.trim_start_matches("0x");
-                match PrivateKeySigner::from_str(key) {
-                    Ok(signer) => {
-                        info!("✓ Profit wallet loaded: {:?}", signer.address());
-                        Some(signer)
-                    }
-                    Err(e) => {
-                        warn!("Failed to parse PROFIT_WALLET_PRIVATE_KEY: {}", e);
-                        None
-                    }
+            Ok(key) => match load_signer(&key, "profit wallet", &mut passphrases) {
+                Ok(signer) => {
+                    info!("✓ Profit wallet loaded: {:?}", signer.address());
+                    Some(signer)
                 }
-            }
+                Err(e) => {
+                    warn!("Failed to load PROFIT_WALLET_PRIVATE_KEY: {}", e);
+                    None
+                }
+            },
             Err(_) => {
                 debug!("PROFIT_WALLET_PRIVATE_KEY not set (optional for simulation)");
                 None
@@ -185,34 +186,48 @@ impl WalletManager {
         Ok(formatted)
     }
     
-    /// Sign a transaction and return the raw signed bytes
+    /// Sign a transaction with the next local nonce and return the raw signed bytes
     pub async fn sign_transaction(
         &mut self,
         to: Address,
         calldata: Bytes,
         value: U256,
         gas_limit: u64,
-        gas_price: u128,
-        priority_fee: u128,
+        max_fee_per_gas: u128,
+        max_priority_fee_per_gas: u128,
     ) -> Result<Bytes> {
         // Check wallet exists first
         if self.profit_wallet.is_none() {
             return Err(eyre!("No profit wallet configured"));
         }
         
-        // Get nonce before borrowing signer (to satisfy borrow checker)
         let nonce = self.get_nonce();
-        
-        // Now get the signer reference
-        let signer = self.profit_wallet.as_ref().unwrap();
+        let fees = FeeRecommendation { target_block: 0, base_fee_wei: 0, max_fee_per_gas, max_priority_fee_per_gas };
+        self.sign_transaction_at(nonce, to, calldata, value, gas_limit, &fees).await
+    }
+    
+    /// Sign a transaction with an explicit (reserved) nonce and the fee caps
+    /// recommended for its target block
+    pub async fn sign_transaction_at(
+        &self,
+        nonce: u64,
+        to: Address,
+        calldata: Bytes,
+        value: U256,
+        gas_limit: u64,
+        fees: &FeeRecommendation,
+    ) -> Result<Bytes> {
+        let FeeRecommendation { max_fee_per_gas, max_priority_fee_per_gas, .. } = *fees;
+        let signer = self.profit_wallet.as_ref()
+            .ok_or_else(|| eyre!("No profit wallet configured"))?;
         
         // Build EIP-1559 transaction
         let tx = TxEip1559 {
             chain_id: self.chain_id,
             nonce,
             gas_limit,
-            max_fee_per_gas: gas_price,
-            max_priority_fee_per_gas: priority_fee,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
             to: alloy_primitives::TxKind::Call(to),
             value,
             input: calldata,
@@ -227,21 +242,15 @@ impl WalletManager {
             .map_err(|e| eyre!("Failed to sign transaction: {}", e))?;
         
         // Create signed transaction envelope
-        let signed = alloy_consensus::TxEnvelope::Eip1559(
-            alloy_consensus::Signed::new_unchecked(
-                tx, 
-                signature,
-                B256::from(signer.address().into_word())
-            )
-        );
+        let signed: TxEnvelope = tx.into_signed(signature).into();
         
-        // RLP encode the signed transaction
-        let mut encoded = Vec::new();
-        alloy_rlp::Encodable::encode(&signed, &mut encoded);
+        // EIP-2718 bytes (eth_sendRawTransaction / eth_sendBundle); their
+        // keccak256 is the transaction hash
+        let encoded = signed.encoded_2718();
         
         debug!(
-            "Signed EIP-1559 transaction: to={:?}, nonce={}, gas_limit={}, gas_price={}",
-            to, nonce, gas_limit, gas_price
+            "Signed EIP-1559 transaction: to={:?}, nonce={}, gas_limit={}, max_fee={}, tip={}",
+            to, nonce, gas_limit, max_fee_per_gas, max_priority_fee_per_gas
         );
         
         Ok(Bytes::from(encoded))
@@ -286,17 +295,11 @@ impl WalletManager {
             .map_err(|e| eyre!("Failed to sign transaction: {}", e))?;
         
         // Create signed transaction envelope
-        let signed = alloy_consensus::TxEnvelope::Legacy(
-            alloy_consensus::Signed::new_unchecked(
-                tx, 
-                signature,
-                B256::from(signer.address().into_word())
-            )
-        );
+        let signed: TxEnvelope = tx.into_signed(signature).into();
         
-        // RLP encode the signed transaction
-        let mut encoded = Vec::new();
-        alloy_rlp::Encodable::encode(&signed, &mut encoded);
+        // EIP-2718 bytes (eth_sendRawTransaction / eth_sendBundle); their
+        // keccak256 is the transaction hash
+        let encoded = signed.encoded_2718();
         
         debug!(
             "Signed legacy transaction: to={:?}, nonce={}, gas_limit={}, gas_price={}",
@@ -307,6 +310,70 @@ impl WalletManager {
     }
 }
 
+// ============================================
+// KEYSTORES
+// ============================================
+
+/// Where keystore passphrases come from
+///
+/// With `KEYSTORE_PASSWORD_FD` set, the descriptor is read once: one line per
+/// keystore, in loading order (Flashbots signer, then profit wallet), the
+/// last line serving any further keystores. Otherwise each keystore prompts
+/// on the terminal.
+pub enum PassphraseSource {
+    Lines { lines: Vec<String>, used: usize },
+    Prompt,
+}
+
+impl PassphraseSource {
+    pub fn from_env() -> Self {
+        match std::env::var("KEYSTORE_PASSWORD_FD") {
+            Ok(fd) => match fd.trim().parse::<i32>().map_err(|e| eyre!("{}", e)).and_then(|fd| read_fd(fd).map_err(Into::into)) {
+                Ok(content) => Self::from_content(&content),
+                Err(e) => {
+                    warn!("Cannot read KEYSTORE_PASSWORD_FD={}: {}", fd, e);
+                    Self::Prompt
+                }
+            },
+            Err(_) => Self::Prompt,
+        }
+    }
+    
+    fn from_content(content: &str) -> Self {
+        let lines = content.lines().map(|l| l.trim_end_matches('\r').to_string()).collect();
+        Self::Lines { lines, used: 0 }
+    }
+    
+    /// Passphrase for the next keystore
+    pub fn next(&mut self, label: &str) -> Result<String> {
+        match self {
+            Self::Lines { lines, used } => {
+                let passphrase = lines.get(*used).or(lines.last()).cloned()
+                    .ok_or_else(|| eyre!("No passphrase on KEYSTORE_PASSWORD_FD for the {} keystore", label))?;
+                *used += 1;
+                Ok(passphrase)
+            }
+            Self::Prompt => rpassword::prompt_password(format!("Passphrase for the {} keystore: ", label))
+                .map_err(|e| eyre!("Cannot prompt for the {} keystore passphrase: {}", label, e)),
+        }
+    }
+}
+
+/// Parse a hex private key, or decrypt the keystore file it names
+pub fn load_signer(key: &str, label: &str, passphrases: &mut PassphraseSource) -> Result<PrivateKeySigner> {
+    let path = Path::new(key.trim());
+    if !path.is_file() {
+        return PrivateKeySigner::from_str(key.trim().trim_start_matches("0x"))
+            .map_err(|e| eyre!("not a private key or keystore file: {}", e));
+    }
+    
+    let passphrase = passphrases.next(label)?;
+    let signer = PrivateKeySigner::decrypt_keystore(path, passphrase)
+        .map_err(|e| eyre!("cannot decrypt keystore {}: {}", path.display(), e))?;
+    debug!("Decrypted {} keystore {}", label, path.display());
+    Ok(signer)
+}
+
 /// Generate a new random wallet (for testing or creating new Flashbots signer)
 pub fn generate_new_wallet() -> Result<(String, Address)> {
     let signer = PrivateKeySigner::random();
@@ -346,4 +413,40 @@ mod tests {
         assert!(signature.contains(":")); // Format: address:signature
         println!("Signature: {}", signature);
     }
-}
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_signed_transaction_is_eip2718() {
+        use alloy_eips::eip2718::Decodable2718;
+        
+        let test_key = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+        let mut manager = WalletManager::new(Some(test_key), None, 1).unwrap();
+        
+        let raw = manager.sign_transaction(Address::repeat_byte(1), Bytes::from(vec![1, 2]), U256::ZERO, 100_000, 30_000_000_000, 1_000_000_000)
+            .await
+            .unwrap();
+        assert_eq!(raw[0], 0x02); // type byte, no RLP string header
+        
+        let decoded = TxEnvelope::decode_2718(&mut raw.as_ref()).unwrap();
+        assert_eq!(*decoded.tx_hash(), keccak256(&raw));
+    }
+    
+    #[test]
+    fn test_keystore_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("sniper-keystore-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (signer, _) = PrivateKeySigner::new_keystore(&dir, &mut rand::thread_rng(), "first", Some("fb.json")).unwrap();
+        let path = dir.join("fb.json");
+        
+        // One line per keystore; the last line covers the rest
+        let mut passphrases = PassphraseSource::from_content("first\nsecond\n");
+        let loaded = load_signer(path.to_str().unwrap(), "test", &mut passphrases).unwrap();
+        assert_eq!(loaded.address(), signer.address());
+        assert!(load_signer(path.to_str().unwrap(), "test", &mut passphrases).is_err());
+        
+        // Hex keys still load without touching the passphrases
+        let hex = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+        assert!(load_signer(hex, "test", &mut PassphraseSource::from_content("")).is_ok());
+        
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
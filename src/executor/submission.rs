@@ -0,0 +1,449 @@
+//! Multi-Builder Bundle Submission
+//!
+//! Broadcasts every bundle to the Flashbots relay and each configured block
+//! builder, for several consecutive target blocks at once. Each target block
+//! gets one `replacementUuid`: a newer bundle for the same block replaces the
+//! older one at the builders, and bundles built on a state that has since
+//! moved on, or trading pools a fresh simulation found unprofitable, are
+//! withdrawn with `eth_cancelBundle`.
+
+use alloy_primitives::Address;
+use eyre::{eyre, Result};
+use futures::future::join_all;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::config::Config;
+use super::flashbots::{FlashbotsBundle, SubmissionStrategy};
+use super::signer::WalletManager;
+
+// ============================================
+// RESPONSES
+// ============================================
+
+/// One builder's answer to one `eth_sendBundle`
+#[derive(Debug, Clone)]
+pub struct BuilderResponse {
+    pub builder: String,
+    pub block_number: u64,
+    pub bundle_hash: Option<String>,
+    pub error: Option<String>,
+}
+
+impl BuilderResponse {
+    pub fn accepted(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Outcome of broadcasting one bundle
+#[derive(Debug, Clone)]
+pub struct SubmissionReport {
+    pub target_blocks: Vec<u64>,
+    pub responses: Vec<BuilderResponse>,
+}
+
+impl SubmissionReport {
+    /// Number of (builder, block) submissions that were accepted
+    pub fn accepted(&self) -> usize {
+        self.responses.iter().filter(|r| r.accepted()).count()
+    }
+
+    /// First bundle hash returned for the earliest target block
+    pub fn bundle_hash(&self) -> Option<&str> {
+        self.target_blocks.iter().find_map(|&block| {
+            self.responses.iter()
+                .filter(|r| r.block_number == block)
+                .find_map(|r| r.bundle_hash.as_deref())
+        })
+    }
+
+    /// Distinct errors, for reporting a submission nobody accepted
+    pub fn errors(&self) -> Vec<String> {
+        let mut errors: Vec<String> = self.responses.iter()
+            .filter_map(|r| r.error.as_ref().map(|e| format!("{}: {}", r.builder, e)))
+            .collect();
+        errors.dedup();
+        errors
+    }
+}
+
+/// A bundle the builders currently hold for one target block
+#[derive(Debug, Clone)]
+struct ActiveBundle {
+    replacement_uuid: String,
+    /// Block whose state the bundle was simulated on
+    state_block: u64,
+    /// Builders that accepted it
+    builders: Vec<String>,
+    /// Pools its transactions trade
+    pools: Vec<Address>,
+}
+
+// ============================================
+// SUBMISSION MANAGER
+// ============================================
+
+/// Sends bundles to every builder for N consecutive blocks and tracks them
+pub struct SubmissionManager {
+    http_client: Client,
+    builders: Vec<String>,
+    strategy: SubmissionStrategy,
+    active: Mutex<HashMap<u64, ActiveBundle>>,
+}
+
+impl SubmissionManager {
+    /// Flashbots relay first, then the configured builders (deduplicated)
+    pub fn new(config: &Config) -> Self {
+        let mut builders = vec![config.flashbots_rpc_url.clone()];
+        for url in &config.builder_urls {
+            if !builders.contains(url) {
+                builders.push(url.clone());
+            }
+        }
+
+        Self {
+            http_client: Client::new(),
+            builders,
+            strategy: SubmissionStrategy::from_config(config),
+            active: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn builders(&self) -> &[String] {
+        &self.builders
+    }
+
+    pub fn strategy(&self) -> &SubmissionStrategy {
+        &self.strategy
+    }
+
+    /// Blocks a bundle for `first_block` is submitted for
+    pub fn target_blocks(&self, first_block: u64) -> Vec<u64> {
+        (0..self.strategy.target_blocks.max(1) as u64)
+            .map(|i| first_block + i)
+            .collect()
+    }
+
+    /// Broadcast `bundle` to every builder for its block and the following
+    /// `target_blocks - 1`, replacing whatever was submitted for those blocks
+    ///
+    /// `state_block` is the block the bundle was simulated on, `pools` the
+    /// pools its transactions trade.
+    pub async fn submit(
+        &self,
+        bundle: &FlashbotsBundle,
+        state_block: u64,
+        pools: &[Address],
+        wallet: &WalletManager,
+    ) -> Result<SubmissionReport> {
+        if !wallet.has_flashbots_signer() {
+            return Err(eyre!("Flashbots signer key not configured"));
+        }
+
+        let target_blocks = self.target_blocks(bundle.block_number);
+        let uuids = self.replacement_uuids(&target_blocks, state_block);
+
+        let mut requests = Vec::with_capacity(target_blocks.len() * self.builders.len());
+        for (&block, uuid) in target_blocks.iter().zip(&uuids) {
+            let mut params = FlashbotsBundle { block_number: block, ..bundle.clone() }.send_params();
+            params["replacementUuid"] = json!(uuid);
+            let body = rpc_body("eth_sendBundle", params);
+            let signature = wallet.sign_flashbots_request(&body).await?;
+
+            for builder in &self.builders {
+                let (body, signature) = (body.clone(), signature.clone());
+                requests.push(async move {
+                    let response = self.post(builder, body, &signature).await;
+                    parse_send_response(builder, block, response)
+                });
+            }
+        }
+
+        let responses = join_all(requests).await;
+
+        for response in &responses {
+            match &response.error {
+                None => debug!(
+                    "Bundle for block {} accepted by {} ({})",
+                    response.block_number,
+                    response.builder,
+                    response.bundle_hash.as_deref().unwrap_or("no hash")
+                ),
+                Some(e) => warn!("Bundle for block {} rejected by {}: {}", response.block_number, response.builder, e),
+            }
+        }
+
+        self.record(&target_blocks, &uuids, state_block, pools, &responses);
+
+        let report = SubmissionReport { target_blocks, responses };
+        info!(
+            "📡 Bundle sent to {} builder(s) for blocks {:?}: {}/{} accepted",
+            self.builders.len(),
+            report.target_blocks,
+            report.accepted(),
+            report.responses.len()
+        );
+        Ok(report)
+    }
+
+    /// Cancel every pending bundle that was simulated before `current_block`
+    ///
+    /// Returns how many bundles were cancelled.
+    pub async fn cancel_stale(&self, current_block: u64, wallet: &WalletManager) -> Result<usize> {
+        let stale: Vec<(u64, ActiveBundle)> = {
+            let mut active = self.active.lock().unwrap();
+            active.retain(|&block, _| block > current_block);
+            let blocks: Vec<u64> = active.iter()
+                .filter(|(_, bundle)| bundle.state_block < current_block)
+                .map(|(&block, _)| block)
+                .collect();
+            blocks.into_iter().filter_map(|b| active.remove(&b).map(|bundle| (b, bundle))).collect()
+        };
+
+        self.cancel(&stale, wallet).await?;
+        Ok(stale.len())
+    }
+
+    /// Cancel every pending bundle trading one of `pools`
+    ///
+    /// Returns how many bundles were cancelled.
+    pub async fn cancel_overlapping(&self, pools: &[Address], wallet: &WalletManager) -> Result<usize> {
+        let overlapping = self.take_overlapping(pools);
+        self.cancel(&overlapping, wallet).await?;
+        Ok(overlapping.len())
+    }
+
+    /// Stop tracking the bundles trading one of `pools`, returning them
+    fn take_overlapping(&self, pools: &[Address]) -> Vec<(u64, ActiveBundle)> {
+        let mut active = self.active.lock().unwrap();
+        let blocks: Vec<u64> = active.iter()
+            .filter(|(_, bundle)| bundle.pools.iter().any(|p| pools.contains(p)))
+            .map(|(&block, _)| block)
+            .collect();
+        blocks.into_iter().filter_map(|b| active.remove(&b).map(|bundle| (b, bundle))).collect()
+    }
+
+    async fn cancel(&self, bundles: &[(u64, ActiveBundle)], wallet: &WalletManager) -> Result<()> {
+        for (block, bundle) in bundles {
+            let body = rpc_body("eth_cancelBundle", json!({ "replacementUuid": bundle.replacement_uuid }));
+            let signature = wallet.sign_flashbots_request(&body).await?;
+
+            let cancels = bundle.builders.iter().map(|builder| {
+                let body = body.clone();
+                let signature = &signature;
+                async move { (builder, self.post(builder, body, signature).await) }
+            });
+            for (builder, result) in join_all(cancels).await {
+                if let Err(e) = result {
+                    warn!("Failed to cancel bundle for block {} at {}: {}", block, builder, e);
+                }
+            }
+            debug!("Cancelled bundle for block {} (simulated on {})", block, bundle.state_block);
+        }
+        Ok(())
+    }
+
+    /// Reuse the pending bundle's uuid for each block so builders replace it
+    fn replacement_uuids(&self, target_blocks: &[u64], state_block: u64) -> Vec<String> {
+        let mut active = self.active.lock().unwrap();
+        active.retain(|&block, _| block > state_block);
+        target_blocks.iter()
+            .map(|block| {
+                active.get(block)
+                    .map(|bundle| bundle.replacement_uuid.clone())
+                    .unwrap_or_else(new_replacement_uuid)
+            })
+            .collect()
+    }
+
+    /// Remember which builders hold a bundle for each block
+    fn record(
+        &self,
+        target_blocks: &[u64],
+        uuids: &[String],
+        state_block: u64,
+        pools: &[Address],
+        responses: &[BuilderResponse],
+    ) {
+        let mut active = self.active.lock().unwrap();
+        for (&block, uuid) in target_blocks.iter().zip(uuids) {
+            let accepted: Vec<String> = responses.iter()
+                .filter(|r| r.block_number == block && r.accepted())
+                .map(|r| r.builder.clone())
+                .collect();
+            if accepted.is_empty() {
+                continue;
+            }
+
+            let entry = active.entry(block).or_insert_with(|| ActiveBundle {
+                replacement_uuid: uuid.clone(),
+                state_block,
+                builders: vec![],
+                pools: vec![],
+            });
+            entry.state_block = state_block;
+            entry.pools = pools.to_vec();
+            for builder in accepted {
+                if !entry.builders.contains(&builder) {
+                    entry.builders.push(builder);
+                }
+            }
+        }
+    }
+
+    /// POST a signed JSON-RPC body, retrying transport errors
+    async fn post(&self, url: &str, body: String, signature: &str) -> Result<Value> {
+        let attempts = if self.strategy.retry_on_failure { self.strategy.max_retries + 1 } else { 1 };
+        let mut last_error = eyre!("no attempt made");
+
+        for attempt in 0..attempts {
+            let result = self.http_client
+                .post(url)
+                .header("Content-Type", "application/json")
+                .header("X-Flashbots-Signature", signature)
+                .body(body.clone())
+                .send()
+                .await;
+
+            match result {
+                Ok(response) => return Ok(response.json().await?),
+                Err(e) => {
+                    debug!("Request to {} failed (attempt {}): {}", url, attempt + 1, e);
+                    last_error = e.into();
+                }
+            }
+        }
+        Err(last_error)
+    }
+}
+
+fn rpc_body(method: &str, params: Value) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": [params]
+    }).to_string()
+}
+
+/// Builders answer `{"bundleHash": ..}`, a bare hash, or null
+fn parse_send_response(builder: &str, block_number: u64, response: Result<Value>) -> BuilderResponse {
+    let (bundle_hash, error) = match response {
+        Err(e) => (None, Some(e.to_string())),
+        Ok(body) => match body.get("error") {
+            Some(error) => (
+                None,
+                Some(error.get("message").and_then(|m| m.as_str()).unwrap_or("Unknown error").to_string()),
+            ),
+            None => {
+                let result = body.get("result");
+                let hash = result
+                    .and_then(|r| r.get("bundleHash").or(Some(r)))
+                    .and_then(|h| h.as_str())
+                    .map(String::from);
+                (hash, None)
+            }
+        },
+    };
+
+    BuilderResponse {
+        builder: builder.to_string(),
+        block_number,
+        bundle_hash,
+        error,
+    }
+}
+
+/// Random (version 4) UUID
+fn new_replacement_uuid() -> String {
+    let mut bytes: [u8; 16] = rand::random();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager() -> SubmissionManager {
+        let config = Config {
+            builder_urls: vec![
+                "https://rpc.beaverbuild.org".to_string(),
+                "https://relay.flashbots.net".to_string(),
+            ],
+            bundle_target_blocks: 3,
+            ..Default::default()
+        };
+        SubmissionManager::new(&config)
+    }
+
+    fn response(builder: &str, block: u64, hash: Option<&str>, error: Option<&str>) -> BuilderResponse {
+        BuilderResponse {
+            builder: builder.to_string(),
+            block_number: block,
+            bundle_hash: hash.map(String::from),
+            error: error.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_builders_and_target_blocks() {
+        let manager = manager();
+        assert_eq!(manager.builders(), ["https://relay.flashbots.net", "https://rpc.beaverbuild.org"]);
+        assert_eq!(manager.target_blocks(100), vec![100, 101, 102]);
+
+        let ok = parse_send_response("a", 100, Ok(json!({ "result": { "bundleHash": "0xab" } })));
+        assert_eq!(ok.bundle_hash.as_deref(), Some("0xab"));
+        let bare = parse_send_response("b", 100, Ok(json!({ "result": "0xcd" })));
+        assert_eq!(bare.bundle_hash.as_deref(), Some("0xcd"));
+        let null = parse_send_response("c", 100, Ok(json!({ "result": null })));
+        assert!(null.accepted() && null.bundle_hash.is_none());
+        let err = parse_send_response("d", 100, Ok(json!({ "error": { "code": -32000, "message": "bad" } })));
+        assert_eq!(err.error.as_deref(), Some("bad"));
+    }
+
+    #[test]
+    fn test_resubmission_reuses_replacement_uuid() {
+        let manager = manager();
+        let blocks = manager.target_blocks(100);
+        let uuids = manager.replacement_uuids(&blocks, 99);
+        assert_eq!(uuids[0].len(), 36);
+        assert_eq!(&uuids[0][14..15], "4");
+        assert_ne!(uuids[0], uuids[1]);
+
+        let responses = vec![
+            response("a", 100, Some("0x1"), None),
+            response("b", 100, None, Some("rejected")),
+            response("a", 101, Some("0x2"), None),
+        ];
+        let pools = [Address::repeat_byte(1), Address::repeat_byte(2)];
+        manager.record(&blocks, &uuids, 99, &pools, &responses);
+        let report = SubmissionReport { target_blocks: blocks.clone(), responses };
+        assert_eq!(report.accepted(), 2);
+        assert_eq!(report.bundle_hash(), Some("0x1"));
+
+        // Next block: 100 has passed, 101 is replaced in place, 102 was never accepted
+        let next = manager.target_blocks(101);
+        let replaced = manager.replacement_uuids(&next, 100);
+        assert_eq!(replaced[0], uuids[1]);
+        assert_ne!(replaced[1], uuids[2]);
+        assert!(!manager.active.lock().unwrap().contains_key(&100));
+        assert_eq!(manager.active.lock().unwrap()[&101].builders, vec!["a".to_string()]);
+
+        // Only bundles trading a pool that failed simulation are withdrawn
+        assert!(manager.take_overlapping(&[Address::repeat_byte(3)]).is_empty());
+        let overlapping = manager.take_overlapping(&[Address::repeat_byte(2)]);
+        assert_eq!(overlapping.iter().map(|(b, _)| *b).collect::<Vec<_>>(), vec![101]);
+        assert!(manager.active.lock().unwrap().is_empty());
+    }
+}
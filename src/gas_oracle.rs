@@ -3,8 +3,17 @@
 //! Fetches real-time gas prices from Etherscan for accurate profit calculations.
 //! Falls back to RPC provider if Etherscan fails.
 //!
+//! On rollups the L2 execution gas is only part of the bill: OP-stack chains and
+//! Arbitrum also charge an L1 data fee proportional to the calldata posted to L1.
+//! The oracle fetches the chain's L1 pricing parameters alongside the gas price
+//! so profit checks can use the full cost of a transaction.
+//!
 //! API: https://api.etherscan.io/v2/api?chainid=1&module=proxy&action=eth_gasPrice
 
+use alloy_primitives::{Address, Bytes, U256, address};
+use alloy_provider::{Provider, ProviderBuilder};
+use alloy_rpc_types::TransactionRequest;
+use alloy_sol_types::{sol, SolCall};
 use eyre::{eyre, Result};
 use reqwest::Client;
 use serde::Deserialize;
@@ -35,6 +44,65 @@ const MAX_GAS_GWEI: f64 = 1000.0;
 /// Default fallback gas price if all sources fail
 const FALLBACK_GAS_GWEI: f64 = 20.0;
 
+/// OP-stack GasPriceOracle predeploy
+const OP_GAS_PRICE_ORACLE: Address = address!("420000000000000000000000000000000000000F");
+
+/// Arbitrum NodeInterface precompile (only reachable via eth_call)
+const ARB_NODE_INTERFACE: Address = address!("00000000000000000000000000000000000000C8");
+
+/// Arbitrum ArbGasInfo precompile
+const ARB_GAS_INFO: Address = address!("000000000000000000000000000000000000006C");
+
+/// EIP-1559 elasticity multiplier (gas target = gas limit / 2)
+const ELASTICITY_MULTIPLIER: u64 = 2;
+
+/// EIP-1559 max base fee change denominator (12.5% per block)
+const BASE_FEE_MAX_CHANGE_DENOMINATOR: u128 = 8;
+
+/// Number of recent blocks sampled with eth_feeHistory
+const FEE_HISTORY_BLOCKS: u64 = 10;
+
+/// Reward percentiles requested from eth_feeHistory (slow / standard / fast)
+const FEE_HISTORY_PERCENTILES: [f64; 3] = [25.0, 50.0, 75.0];
+
+/// Bytes a signed EIP-1559 envelope adds on top of the calldata
+/// (nonce, gas fields, to, value, signature). Counted as non-zero bytes.
+const TX_ENVELOPE_OVERHEAD_BYTES: u64 = 68;
+
+// ============================================
+// L1 FEE INTERFACES
+// ============================================
+
+sol! {
+    /// OP-stack GasPriceOracle (0x42..0F)
+    interface IGasPriceOracle {
+        function getL1Fee(bytes memory data) external view returns (uint256);
+        function isEcotone() external view returns (bool);
+        function l1BaseFee() external view returns (uint256);
+        function blobBaseFee() external view returns (uint256);
+        function baseFeeScalar() external view returns (uint32);
+        function blobBaseFeeScalar() external view returns (uint32);
+        function overhead() external view returns (uint256);
+        function scalar() external view returns (uint256);
+    }
+
+    /// Arbitrum NodeInterface (0xC8)
+    interface INodeInterface {
+        function gasEstimateComponents(address to, bool contractCreation, bytes calldata data)
+            external payable returns (
+                uint64 gasEstimate,
+                uint64 gasEstimateForL1,
+                uint256 baseFee,
+                uint256 l1BaseFeeEstimate
+            );
+    }
+
+    /// Arbitrum ArbGasInfo (0x6C)
+    interface IArbGasInfo {
+        function getL1BaseFeeEstimate() external view returns (uint256);
+    }
+}
+
 // ============================================
 // API RESPONSE TYPES
 // ============================================
@@ -76,6 +144,260 @@ struct GasTrackerResult {
     gas_used_ratio: Option<String>,
 }
 
+// ============================================
+// CHAIN GAS MODEL
+// ============================================
+
+/// How a chain charges for a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GasModel {
+    /// Ethereum L1 - execution gas only
+    Ethereum,
+
+    /// OP-stack rollups (Optimism, Base, Zora, Mode...) - execution + L1 data fee
+    OpStack,
+
+    /// Arbitrum Nitro chains - execution + L1 poster fee
+    Arbitrum,
+}
+
+impl GasModel {
+    /// Pick the gas model for a chain ID
+    pub fn for_chain(chain_id: u64) -> Self {
+        match chain_id {
+            // Optimism, Base, Zora, Mode, Fraxtal + Sepolia testnets
+            10 | 8453 | 7777777 | 34443 | 252 | 11155420 | 84532 => GasModel::OpStack,
+            // Arbitrum One, Nova, Sepolia
+            42161 | 42170 | 421614 => GasModel::Arbitrum,
+            _ => GasModel::Ethereum,
+        }
+    }
+
+    pub fn has_l1_fee(&self) -> bool {
+        !matches!(self, GasModel::Ethereum)
+    }
+}
+
+impl std::fmt::Display for GasModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GasModel::Ethereum => write!(f, "L1"),
+            GasModel::OpStack => write!(f, "OP-stack"),
+            GasModel::Arbitrum => write!(f, "Arbitrum"),
+        }
+    }
+}
+
+/// L1 data fee parameters, fetched together with the gas price
+///
+/// Used to price calldata locally when the exact per-transaction RPC
+/// (`getL1Fee` / `gasEstimateComponents`) is unavailable.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum L1FeeParams {
+    /// No L1 data fee (Ethereum mainnet, or params could not be fetched)
+    #[default]
+    None,
+
+    /// OP-stack after Ecotone: blob-aware scalars (1e6 fixed point)
+    OpEcotone {
+        l1_base_fee_wei: u128,
+        blob_base_fee_wei: u128,
+        base_fee_scalar: u32,
+        blob_base_fee_scalar: u32,
+    },
+
+    /// OP-stack before Ecotone: fixed overhead + scalar (1e6 fixed point)
+    OpBedrock {
+        l1_base_fee_wei: u128,
+        overhead: u64,
+        scalar: u64,
+    },
+
+    /// Arbitrum: L1 price per calldata gas unit
+    Arbitrum {
+        l1_base_fee_estimate_wei: u128,
+    },
+}
+
+/// Calldata gas under EIP-2028 pricing (4 per zero byte, 16 per non-zero byte),
+/// including the signed envelope overhead
+pub fn calldata_gas(data: &[u8]) -> u64 {
+    let zeros = data.iter().filter(|b| **b == 0).count() as u64;
+    let non_zeros = data.len() as u64 - zeros;
+    zeros * 4 + (non_zeros + TX_ENVELOPE_OVERHEAD_BYTES) * 16
+}
+
+impl L1FeeParams {
+    /// Estimate the L1 data fee in wei for a transaction carrying `calldata`
+    pub fn l1_fee_wei(&self, calldata: &[u8]) -> u128 {
+        let data_gas = calldata_gas(calldata) as u128;
+        match *self {
+            L1FeeParams::None => 0,
+            L1FeeParams::OpEcotone {
+                l1_base_fee_wei,
+                blob_base_fee_wei,
+                base_fee_scalar,
+                blob_base_fee_scalar,
+            } => {
+                // Ecotone: compressed_size = data_gas / 16
+                // fee = compressed_size * (16 * base_scalar * l1_base_fee + blob_scalar * blob_base_fee) / 1e6
+                let weighted = 16 * base_fee_scalar as u128 * l1_base_fee_wei
+                    + blob_base_fee_scalar as u128 * blob_base_fee_wei;
+                data_gas * weighted / 16 / 1_000_000
+            }
+            L1FeeParams::OpBedrock { l1_base_fee_wei, overhead, scalar } => {
+                (data_gas + overhead as u128) * l1_base_fee_wei * scalar as u128 / 1_000_000
+            }
+            L1FeeParams::Arbitrum { l1_base_fee_estimate_wei } => {
+                data_gas * l1_base_fee_estimate_wei
+            }
+        }
+    }
+}
+
+/// Full cost of a transaction: L2 execution plus L1 data fee
+#[derive(Debug, Clone, Copy)]
+pub struct GasCostEstimate {
+    /// Execution gas cost in wei
+    pub execution_wei: u128,
+
+    /// L1 data fee in wei (0 on Ethereum)
+    pub l1_data_wei: u128,
+
+    /// ETH price used for USD conversion
+    pub eth_price_usd: f64,
+}
+
+impl GasCostEstimate {
+    pub fn total_wei(&self) -> u128 {
+        self.execution_wei + self.l1_data_wei
+    }
+
+    pub fn total_usd(&self) -> f64 {
+        self.total_wei() as f64 * 1e-18 * self.eth_price_usd
+    }
+
+    pub fn l1_data_usd(&self) -> f64 {
+        self.l1_data_wei as f64 * 1e-18 * self.eth_price_usd
+    }
+}
+
+// ============================================
+// EIP-1559 FEES
+// ============================================
+
+/// Base fee of the block after a parent with the given header values (EIP-1559)
+pub fn next_base_fee(parent_base_fee: u128, parent_gas_used: u64, parent_gas_limit: u64) -> u128 {
+    let gas_target = (parent_gas_limit / ELASTICITY_MULTIPLIER) as u128;
+    let gas_used = parent_gas_used as u128;
+    if gas_target == 0 || gas_used == gas_target {
+        return parent_base_fee;
+    }
+    
+    if gas_used > gas_target {
+        let delta = (parent_base_fee * (gas_used - gas_target) / gas_target
+            / BASE_FEE_MAX_CHANGE_DENOMINATOR)
+            .max(1);
+        parent_base_fee + delta
+    } else {
+        let delta = parent_base_fee * (gas_target - gas_used) / gas_target
+            / BASE_FEE_MAX_CHANGE_DENOMINATOR;
+        parent_base_fee.saturating_sub(delta)
+    }
+}
+
+/// Upper bound on the base fee `blocks_ahead` blocks after `base_fee`
+/// (every block full: +12.5% each)
+pub fn max_base_fee_after(base_fee: u128, blocks_ahead: u64) -> u128 {
+    let mut fee = base_fee;
+    for _ in 0..blocks_ahead {
+        fee += fee / BASE_FEE_MAX_CHANGE_DENOMINATOR;
+    }
+    fee
+}
+
+/// EIP-1559 fee data for the next block, built from the latest header and
+/// recent `eth_feeHistory` priority fee percentiles
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Eip1559Fees {
+    /// Number of the latest block the prediction is based on
+    pub latest_block: u64,
+    
+    /// Base fee of the latest block
+    pub latest_base_fee_wei: u128,
+    
+    /// Predicted base fee of `latest_block + 1`
+    pub next_base_fee_wei: u128,
+    
+    /// Median priority fee paid at the 25th / 50th / 75th percentile
+    /// over the last `FEE_HISTORY_BLOCKS` blocks
+    pub priority_fee_percentiles_wei: [u128; 3],
+}
+
+/// Fee caps to sign a transaction with for a target block
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeeRecommendation {
+    /// Block the transaction is aimed at
+    pub target_block: u64,
+    
+    /// Predicted base fee at `target_block`
+    pub base_fee_wei: u128,
+    
+    /// maxFeePerGas - covers the worst-case base fee growth up to `target_block`
+    pub max_fee_per_gas: u128,
+    
+    /// maxPriorityFeePerGas - tip to the block builder
+    pub max_priority_fee_per_gas: u128,
+}
+
+impl FeeRecommendation {
+    /// Expected price per gas if included at `target_block`
+    pub fn effective_gas_price(&self) -> u128 {
+        (self.base_fee_wei + self.max_priority_fee_per_gas).min(self.max_fee_per_gas)
+    }
+}
+
+impl Eip1559Fees {
+    /// Standard (median) priority fee
+    pub fn standard_priority_fee_wei(&self) -> u128 {
+        self.priority_fee_percentiles_wei[1]
+    }
+    
+    /// Fast (75th percentile) priority fee
+    pub fn fast_priority_fee_wei(&self) -> u128 {
+        self.priority_fee_percentiles_wei[2]
+    }
+    
+    /// Fee caps for a transaction targeting `target_block`
+    ///
+    /// The predicted base fee is used for the target if it is the next block;
+    /// `max_fee_per_gas` allows for the base fee rising every block until then.
+    pub fn recommend(&self, target_block: u64) -> FeeRecommendation {
+        let blocks_after_next = target_block.saturating_sub(self.latest_block + 1);
+        let max_base_fee = max_base_fee_after(self.next_base_fee_wei, blocks_after_next);
+        let priority = self.fast_priority_fee_wei();
+        
+        FeeRecommendation {
+            target_block,
+            base_fee_wei: self.next_base_fee_wei,
+            max_fee_per_gas: max_base_fee + priority,
+            max_priority_fee_per_gas: priority,
+        }
+    }
+}
+
+/// Median of one column of the eth_feeHistory reward matrix
+fn median_reward(rewards: &[Vec<u128>], column: usize) -> u128 {
+    let mut values: Vec<u128> = rewards.iter()
+        .filter_map(|r| r.get(column).copied())
+        .collect();
+    if values.is_empty() {
+        return 0;
+    }
+    values.sort_unstable();
+    values[values.len() / 2]
+}
+
 // ============================================
 // CACHED GAS PRICE
 // ============================================
@@ -102,6 +424,12 @@ pub struct GasPriceInfo {
     
     /// Source of the data
     pub source: GasSource,
+
+    /// L1 data fee parameters (rollups only)
+    pub l1_fee: L1FeeParams,
+    
+    /// Next-block EIP-1559 fee data (None if the chain/RPC doesn't provide it)
+    pub eip1559: Option<Eip1559Fees>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -131,22 +459,60 @@ impl GasPriceInfo {
         self.fast_gwei * 1.1
     }
     
-    /// Estimate gas cost in USD for a given gas amount
+    /// Fee caps for a transaction aimed at `target_block`
+    ///
+    /// Falls back to the legacy gas price (with a 10% tip) when no EIP-1559
+    /// data is available.
+    pub fn fee_recommendation(&self, target_block: u64) -> FeeRecommendation {
+        match self.eip1559 {
+            Some(fees) => fees.recommend(target_block),
+            None => {
+                let gas_price = (self.gas_price_gwei * 1e9) as u128;
+                FeeRecommendation {
+                    target_block,
+                    base_fee_wei: gas_price - gas_price / 10,
+                    max_fee_per_gas: gas_price,
+                    max_priority_fee_per_gas: gas_price / 10,
+                }
+            }
+        }
+    }
+    
+    /// Estimate execution gas cost in USD for a given gas amount
+    ///
+    /// Does NOT include the L1 data fee - use `estimate_total_cost` on rollups.
     pub fn estimate_cost_usd(&self, gas_units: u64, eth_price_usd: f64) -> f64 {
         let gas_eth = (gas_units as f64) * self.gas_price_gwei * 1e-9;
         gas_eth * eth_price_usd
     }
+
+    /// Estimate the full transaction cost (execution + L1 data fee) from the
+    /// cached L1 fee parameters
+    pub fn estimate_total_cost(
+        &self,
+        gas_units: u64,
+        calldata: &[u8],
+        eth_price_usd: f64,
+    ) -> GasCostEstimate {
+        GasCostEstimate {
+            execution_wei: (gas_units as f64 * self.gas_price_gwei * 1e9) as u128,
+            l1_data_wei: self.l1_fee.l1_fee_wei(calldata),
+            eth_price_usd,
+        }
+    }
 }
 
 // ============================================
 // GAS ORACLE
 // ============================================
 
+#[derive(Clone)]
 pub struct GasOracle {
     http_client: Client,
     api_key: Option<String>,
     chain_id: u64,
     rpc_url: String,
+    gas_model: GasModel,
     cache: Arc<RwLock<Option<GasPriceInfo>>>,
 }
 
@@ -163,6 +529,7 @@ impl GasOracle {
             api_key,
             chain_id,
             rpc_url,
+            gas_model: GasModel::for_chain(chain_id),
             cache: Arc::new(RwLock::new(None)),
         }
     }
@@ -178,6 +545,11 @@ impl GasOracle {
         Self::new(api_key, chain_id, rpc_url)
     }
     
+    /// Gas model for the configured chain
+    pub fn gas_model(&self) -> GasModel {
+        self.gas_model
+    }
+    
     /// Get current gas price (with caching)
     pub async fn get_gas_price(&self) -> GasPriceInfo {
         // Check cache first
@@ -203,8 +575,47 @@ impl GasOracle {
         info
     }
     
-    /// Fetch gas price (tries Etherscan first, then RPC, then fallback)
+    /// Fetch gas price plus, on rollups, the L1 data fee parameters
     async fn fetch_gas_price(&self) -> GasPriceInfo {
+        let mut info = self.fetch_base_gas_price().await;
+        
+        // Price the next block rather than the current gas price
+        match self.fetch_eip1559_fees().await {
+            Ok(fees) => {
+                let next = fees.recommend(fees.latest_block + 1);
+                debug!(
+                    "⛽ EIP-1559: next base fee {:.3} gwei, tip {:.3} gwei (block {})",
+                    fees.next_base_fee_wei as f64 / 1e9,
+                    next.max_priority_fee_per_gas as f64 / 1e9,
+                    fees.latest_block + 1
+                );
+                info.base_fee_gwei = fees.next_base_fee_wei as f64 / 1e9;
+                info.gas_price_gwei = (next.effective_gas_price() as f64 / 1e9)
+                    .clamp(MIN_GAS_GWEI, MAX_GAS_GWEI);
+                info.eip1559 = Some(fees);
+            }
+            Err(e) => {
+                debug!("EIP-1559 fee data unavailable, using legacy gas price: {}", e);
+            }
+        }
+        
+        if self.gas_model.has_l1_fee() {
+            match self.fetch_l1_fee_params().await {
+                Ok(params) => {
+                    debug!("⛽ L1 fee params ({}): {:?}", self.gas_model, params);
+                    info.l1_fee = params;
+                }
+                Err(e) => {
+                    warn!("L1 fee params fetch failed ({}): {}", self.gas_model, e);
+                }
+            }
+        }
+        
+        info
+    }
+    
+    /// Fetch gas price (tries Etherscan first, then RPC, then fallback)
+    async fn fetch_base_gas_price(&self) -> GasPriceInfo {
         // Try Etherscan first (if we have an API key)
         if let Some(ref api_key) = self.api_key {
             match self.fetch_from_etherscan(api_key).await {
@@ -242,6 +653,8 @@ impl GasOracle {
             base_fee_gwei: FALLBACK_GAS_GWEI * 0.7,
             fetched_at: Instant::now(),
             source: GasSource::Fallback,
+            l1_fee: L1FeeParams::None,
+            eip1559: None,
         }
     }
     
@@ -286,13 +699,13 @@ impl GasOracle {
             base_fee_gwei: gas_gwei * 0.7,
             fetched_at: Instant::now(),
             source: GasSource::Etherscan,
+            l1_fee: L1FeeParams::None,
+            eip1559: None,
         })
     }
     
     /// Fetch gas price from RPC provider
     async fn fetch_from_rpc(&self) -> Result<GasPriceInfo> {
-        use alloy_provider::{Provider, ProviderBuilder};
-        
         let provider = ProviderBuilder::new()
             .on_http(self.rpc_url.parse()?);
         
@@ -310,9 +723,160 @@ impl GasOracle {
             base_fee_gwei: gas_gwei * 0.7,
             fetched_at: Instant::now(),
             source: GasSource::RpcProvider,
+            l1_fee: L1FeeParams::None,
+            eip1559: None,
+        })
+    }
+    
+    /// Predict next-block fees from the latest header and eth_feeHistory
+    async fn fetch_eip1559_fees(&self) -> Result<Eip1559Fees> {
+        use alloy_eips::BlockNumberOrTag;
+        
+        let provider = ProviderBuilder::new()
+            .on_http(self.rpc_url.parse()?);
+        
+        let block = provider.get_block_by_number(BlockNumberOrTag::Latest).await?
+            .ok_or_else(|| eyre!("Latest block not found"))?;
+        let header = &block.header;
+        let base_fee = header.base_fee_per_gas
+            .ok_or_else(|| eyre!("Latest block has no base fee (pre-London chain)"))? as u128;
+        
+        let history = provider.get_fee_history(
+            FEE_HISTORY_BLOCKS,
+            BlockNumberOrTag::Latest,
+            &FEE_HISTORY_PERCENTILES,
+        ).await?;
+        let rewards = history.reward.unwrap_or_default();
+        
+        Ok(Eip1559Fees {
+            latest_block: header.number,
+            latest_base_fee_wei: base_fee,
+            next_base_fee_wei: next_base_fee(base_fee, header.gas_used, header.gas_limit),
+            priority_fee_percentiles_wei: [
+                median_reward(&rewards, 0),
+                median_reward(&rewards, 1),
+                median_reward(&rewards, 2),
+            ],
         })
     }
     
+    /// eth_call helper for the L1 fee predeploys
+    async fn call_contract(&self, to: Address, calldata: Vec<u8>) -> Result<Bytes> {
+        let provider = ProviderBuilder::new()
+            .on_http(self.rpc_url.parse()?);
+        
+        let tx = TransactionRequest::default()
+            .to(to)
+            .input(calldata.into());
+        
+        provider.call(tx).await
+            .map_err(|e| eyre!("eth_call to {:?} failed: {}", to, e))
+    }
+    
+    /// Fetch the chain's L1 data fee parameters
+    async fn fetch_l1_fee_params(&self) -> Result<L1FeeParams> {
+        match self.gas_model {
+            GasModel::Ethereum => Ok(L1FeeParams::None),
+            GasModel::OpStack => self.fetch_op_fee_params().await,
+            GasModel::Arbitrum => {
+                let out = self.call_contract(
+                    ARB_GAS_INFO,
+                    IArbGasInfo::getL1BaseFeeEstimateCall {}.abi_encode(),
+                ).await?;
+                let fee = IArbGasInfo::getL1BaseFeeEstimateCall::abi_decode_returns(&out)?;
+                Ok(L1FeeParams::Arbitrum {
+                    l1_base_fee_estimate_wei: fee.to(),
+                })
+            }
+        }
+    }
+    
+    /// Fetch OP-stack GasPriceOracle scalars (Ecotone if active, Bedrock otherwise)
+    async fn fetch_op_fee_params(&self) -> Result<L1FeeParams> {
+        let out = self.call_contract(OP_GAS_PRICE_ORACLE, IGasPriceOracle::l1BaseFeeCall {}.abi_encode()).await?;
+        let l1_base_fee_wei: u128 = IGasPriceOracle::l1BaseFeeCall::abi_decode_returns(&out)?.to();
+        
+        // isEcotone() reverts on pre-Ecotone oracles - treat that as Bedrock
+        let is_ecotone = match self.call_contract(OP_GAS_PRICE_ORACLE, IGasPriceOracle::isEcotoneCall {}.abi_encode()).await {
+            Ok(out) => IGasPriceOracle::isEcotoneCall::abi_decode_returns(&out).unwrap_or(false),
+            Err(_) => false,
+        };
+        
+        if is_ecotone {
+            let out = self.call_contract(OP_GAS_PRICE_ORACLE, IGasPriceOracle::blobBaseFeeCall {}.abi_encode()).await?;
+            let blob_base_fee_wei: u128 = IGasPriceOracle::blobBaseFeeCall::abi_decode_returns(&out)?.to();
+            let out = self.call_contract(OP_GAS_PRICE_ORACLE, IGasPriceOracle::baseFeeScalarCall {}.abi_encode()).await?;
+            let base_fee_scalar = IGasPriceOracle::baseFeeScalarCall::abi_decode_returns(&out)?;
+            let out = self.call_contract(OP_GAS_PRICE_ORACLE, IGasPriceOracle::blobBaseFeeScalarCall {}.abi_encode()).await?;
+            let blob_base_fee_scalar = IGasPriceOracle::blobBaseFeeScalarCall::abi_decode_returns(&out)?;
+            
+            Ok(L1FeeParams::OpEcotone {
+                l1_base_fee_wei,
+                blob_base_fee_wei,
+                base_fee_scalar,
+                blob_base_fee_scalar,
+            })
+        } else {
+            let out = self.call_contract(OP_GAS_PRICE_ORACLE, IGasPriceOracle::overheadCall {}.abi_encode()).await?;
+            let overhead: u64 = IGasPriceOracle::overheadCall::abi_decode_returns(&out)?.to();
+            let out = self.call_contract(OP_GAS_PRICE_ORACLE, IGasPriceOracle::scalarCall {}.abi_encode()).await?;
+            let scalar: u64 = IGasPriceOracle::scalarCall::abi_decode_returns(&out)?.to();
+            
+            Ok(L1FeeParams::OpBedrock { l1_base_fee_wei, overhead, scalar })
+        }
+    }
+    
+    /// Exact L1 data fee for a transaction, asked from the chain itself
+    ///
+    /// OP-stack: `GasPriceOracle.getL1Fee(data)`.
+    /// Arbitrum: `NodeInterface.gasEstimateComponents(to, false, data)`.
+    async fn fetch_exact_l1_fee_wei(&self, to: Address, calldata: &Bytes) -> Result<u128> {
+        match self.gas_model {
+            GasModel::Ethereum => Ok(0),
+            GasModel::OpStack => {
+                let call = IGasPriceOracle::getL1FeeCall { data: calldata.clone() };
+                let out = self.call_contract(OP_GAS_PRICE_ORACLE, call.abi_encode()).await?;
+                let fee: U256 = IGasPriceOracle::getL1FeeCall::abi_decode_returns(&out)?;
+                Ok(fee.to())
+            }
+            GasModel::Arbitrum => {
+                let call = INodeInterface::gasEstimateComponentsCall {
+                    to,
+                    contractCreation: false,
+                    data: calldata.clone(),
+                };
+                let out = self.call_contract(ARB_NODE_INTERFACE, call.abi_encode()).await?;
+                let components = INodeInterface::gasEstimateComponentsCall::abi_decode_returns(&out)?;
+                let base_fee: u128 = components.baseFee.to();
+                Ok(components.gasEstimateForL1 as u128 * base_fee)
+            }
+        }
+    }
+    
+    /// Full transaction cost for `calldata` sent to `to`
+    ///
+    /// Uses the chain's exact L1 fee method when reachable, and falls back to
+    /// the cached L1 fee parameters otherwise.
+    pub async fn estimate_total_cost(
+        &self,
+        gas_info: &GasPriceInfo,
+        gas_units: u64,
+        to: Address,
+        calldata: &Bytes,
+        eth_price_usd: f64,
+    ) -> GasCostEstimate {
+        let mut estimate = gas_info.estimate_total_cost(gas_units, calldata, eth_price_usd);
+        
+        if self.gas_model.has_l1_fee() {
+            match self.fetch_exact_l1_fee_wei(to, calldata).await {
+                Ok(l1_fee) => estimate.l1_data_wei = l1_fee,
+                Err(e) => trace!("Exact L1 fee unavailable, using cached params: {}", e),
+            }
+        }
+        
+        estimate
+    }
+    
     /// Get gas tracker data (safe/standard/fast prices)
     /// Note: This uses 1 API call, but gives more detail
     #[allow(dead_code)]
@@ -365,6 +929,8 @@ impl GasOracle {
             base_fee_gwei: base_fee_gwei.clamp(MIN_GAS_GWEI, MAX_GAS_GWEI),
             fetched_at: Instant::now(),
             source: GasSource::Etherscan,
+            l1_fee: L1FeeParams::None,
+            eip1559: None,
         })
     }
 }
@@ -387,6 +953,8 @@ mod tests {
             base_fee_gwei: 14.0,
             fetched_at: Instant::now(),
             source: GasSource::Fallback,
+            l1_fee: L1FeeParams::None,
+            eip1559: None,
         };
         
         assert!(!info.is_stale());
@@ -402,6 +970,8 @@ mod tests {
             base_fee_gwei: 14.0,
             fetched_at: Instant::now(),
             source: GasSource::Fallback,
+            l1_fee: L1FeeParams::None,
+            eip1559: None,
         };
         
         // 200,000 gas at 20 gwei, ETH = $3500
@@ -410,4 +980,80 @@ mod tests {
         let cost = info.estimate_cost_usd(200_000, 3500.0);
         assert!((cost - 14.0).abs() < 0.01);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_l1_fee_ecotone() {
+        // 1000 non-zero bytes of calldata on Base-like scalars
+        let calldata = vec![0xffu8; 1000];
+        let params = L1FeeParams::OpEcotone {
+            l1_base_fee_wei: 10_000_000_000, // 10 gwei
+            blob_base_fee_wei: 1,
+            base_fee_scalar: 1368,
+            blob_base_fee_scalar: 810949,
+        };
+        
+        // data_gas = (1000 + 68) * 16 = 17088
+        // weighted = 16 * 1368 * 10e9 + 810949 * 1
+        // fee = 17088 * weighted / 16 / 1e6
+        let weighted = 16 * 1368 * 10_000_000_000u128 + 810_949;
+        let expected = 17088 * weighted / 16 / 1_000_000;
+        assert_eq!(params.l1_fee_wei(&calldata), expected);
+        
+        let info = GasPriceInfo {
+            gas_price_gwei: 0.0005,
+            safe_gwei: 0.0005,
+            standard_gwei: 0.0005,
+            fast_gwei: 0.0005,
+            base_fee_gwei: 0.0005,
+            fetched_at: Instant::now(),
+            source: GasSource::Fallback,
+            l1_fee: params,
+            eip1559: None,
+        };
+        
+        // On a rollup the L1 data fee dominates execution cost
+        let cost = info.estimate_total_cost(300_000, &calldata, 3500.0);
+        assert_eq!(cost.l1_data_wei, expected);
+        assert!(cost.l1_data_wei > cost.execution_wei);
+        assert!(cost.total_usd() > info.estimate_cost_usd(300_000, 3500.0));
+    }
+    
+    #[test]
+    fn test_gas_model_for_chain() {
+        assert_eq!(GasModel::for_chain(1), GasModel::Ethereum);
+        assert_eq!(GasModel::for_chain(8453), GasModel::OpStack);
+        assert_eq!(GasModel::for_chain(42161), GasModel::Arbitrum);
+        assert_eq!(L1FeeParams::None.l1_fee_wei(&[1, 2, 3]), 0);
+    }
+    
+    #[test]
+    fn test_next_base_fee() {
+        let base = 10_000_000_000u128; // 10 gwei
+        // At target: unchanged
+        assert_eq!(next_base_fee(base, 15_000_000, 30_000_000), base);
+        // Full block: +12.5%
+        assert_eq!(next_base_fee(base, 30_000_000, 30_000_000), 11_250_000_000);
+        // Empty block: -12.5%
+        assert_eq!(next_base_fee(base, 0, 30_000_000), 8_750_000_000);
+    }
+    
+    #[test]
+    fn test_fee_recommendation() {
+        let fees = Eip1559Fees {
+            latest_block: 100,
+            latest_base_fee_wei: 8_000_000_000,
+            next_base_fee_wei: 9_000_000_000,
+            priority_fee_percentiles_wei: [100_000_000, 500_000_000, 1_000_000_000],
+        };
+        
+        // Next block: no extra headroom beyond the prediction
+        let next = fees.recommend(101);
+        assert_eq!(next.max_priority_fee_per_gas, 1_000_000_000);
+        assert_eq!(next.max_fee_per_gas, 10_000_000_000);
+        assert_eq!(next.effective_gas_price(), 10_000_000_000);
+        
+        // Two blocks out: allow one more full block of base fee growth
+        let later = fees.recommend(102);
+        assert_eq!(later.max_fee_per_gas, 9_000_000_000 * 9 / 8 + 1_000_000_000);
+    }
+}
@@ -4,7 +4,7 @@
 //! Shows detailed scan info: gas, cycles found, best candidates
 //! Only alerts on actual profitable opportunities
 
-use alloy_primitives::Address;
+use alloy_primitives::{Address, U256};
 use color_eyre::eyre::Result;
 use console::style;
 use std::collections::HashMap;
@@ -19,13 +19,15 @@ mod tokens;
 mod simulator;
 mod executor;
 mod gas_oracle;
+mod swap_gas;
 
-use brain::{BoundedBellmanFord, ProfitFilter, ArbitrageCycle};
+use brain::{best_disjoint_set, CandidateSelector, CycleIndex, ProfitFilter, RiskPolicy, ArbitrageCycle, MIN_REPORTED_RETURN};
 use cartographer::{ArbitrageGraph, ExpandedPoolFetcher, Dex, build_expanded_symbol_map};
-use config::{Config, ExecutionMode};
-use simulator::SwapSimulator;
-use executor::ExecutionEngine;
+use config::{Config, EdgeWeightMode, ExecutionMode};
+use simulator::{SplitRouter, SwapSimulator, TradeSizer};
+use executor::{cycle_lender_exclusions, ExecutionEngine, FlashLiquidityRegistry};
 use gas_oracle::{GasOracle, GasPriceInfo};
+use swap_gas::SwapGasModel;
 
 fn print_banner() {
     println!();
@@ -174,11 +176,21 @@ async fn main() -> Result<()> {
         config.rpc_url.clone(),
     );
 
+    // Per-swap gas model, calibrated across runs
+    let gas_model = SwapGasModel::load(&config.gas_model_path, config.flash_loan_provider);
+
     let token_symbols = build_token_symbols();
-    let engine = ExecutionEngine::new(config.clone());
+    // Live lender liquidity: picks start tokens, sizes trades and chooses lenders
+    let flash_liquidity = FlashLiquidityRegistry::new(config.rpc_url.clone(), config.flash_loan_provider);
+    let engine = ExecutionEngine::new(config.clone(), gas_oracle.clone(), gas_model.clone(), flash_liquidity);
     let mut stats = Stats::new();
     let mut consecutive_failures = 0u32;
 
+    // Cycle catalog persists across scans; only changed pools are re-scored
+    // Risk policy (token categories, whitelist / blacklist) is enforced during the search
+    let mut cycle_index = CycleIndex::new(config.max_hops)
+        .with_policy(RiskPolicy::from_config(&config)?);
+
     // Show config summary
     let gas_source = if config.etherscan_api_key.is_some() {
         "Etherscan API ✓"
@@ -206,7 +218,7 @@ async fn main() -> Result<()> {
 
         let scan_start = Instant::now();
         
-        match run_scan(&config, &token_symbols, &engine, &gas_oracle, &mut stats).await {
+        match run_scan(&config, &token_symbols, &engine, &gas_oracle, &gas_model, &mut cycle_index, &mut stats).await {
             Ok(result) => {
                 consecutive_failures = 0;
                 
@@ -317,6 +329,8 @@ async fn run_scan(
     token_symbols: &HashMap<Address, &'static str>,
     engine: &ExecutionEngine,
     gas_oracle: &GasOracle,
+    gas_model: &SwapGasModel,
+    cycle_index: &mut CycleIndex,
     stats: &mut Stats,
 ) -> Result<ScanResult> {
     stats.total_scans += 1;
@@ -378,9 +392,32 @@ async fn run_scan(
     
     let eth_price = get_eth_price_from_pools(&pools);
     stats.last_eth_price = eth_price;
+    engine.set_eth_price(eth_price);
+
+    // USD price anchors for token pricing through the graph
+    let anchors: Vec<(Address, f64)> = [
+        ("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2", eth_price), // WETH
+        ("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48", 1.0),       // USDC
+        ("0xdAC17F958D2ee523a2206206994597C13D831ec7", 1.0),       // USDT
+        ("0x6B175474E89094C44Da98b954EedcdeCB5BE3830", 1.0),       // DAI
+    ]
+    .iter()
+    .filter_map(|(addr, usd)| addr.parse().ok().map(|a| (a, *usd)))
+    .collect();
 
     // Build graph
-    let graph = ArbitrageGraph::from_pools(&pools);
+    let graph = match config.edge_weight_mode {
+        EdgeWeightMode::Spot => ArbitrageGraph::from_pools(&pools),
+        EdgeWeightMode::PriceImpact => {
+            ArbitrageGraph::from_pools_at_size(&pools, config.reference_trade_usd, &anchors)
+        }
+    };
+
+    // Pool depths for the risk policy's minimum-depth rules
+    let token_prices = graph.token_prices_usd(&anchors);
+    if let Some(policy) = cycle_index.policy_mut() {
+        policy.set_pool_depths(&pools, &token_prices);
+    }
     // Debug: List tokens in graph
     let symbol_map = build_token_symbols(); // or build_expanded_symbol_map()
     println!("\n=== TOKENS IN GRAPH ({}) ===", graph.node_count());
@@ -389,24 +426,33 @@ async fn run_scan(
         println!("  {}: {:?}", sym, addr);
     }
     println!("===========================\n");
-    // Find cycles
-    let bellman_ford = BoundedBellmanFord::new(&graph, config.max_hops);
+    // Find cycles (catalog refresh: full enumeration only when the pool set changes)
     let base_tokens = config.base_token_addresses();
-    // Add expanded base tokens for cycle search
-    let mut expanded_bases = base_tokens.clone();
-    // Add USDS, sUSDS, crvUSD as cycle starting points
-    if let Ok(addr) = "0xdC035D45d973E3EC169d2276DDab16f1e407384F".parse() {
-        expanded_bases.push(addr); // USDS
-    }
-    if let Ok(addr) = "0xa3931d71877C0E7a3148CB7Eb4463524FEc27fbD".parse() {
-        expanded_bases.push(addr); // sUSDS  
+    // Cycles start from every token that can be flash-borrowed in size
+    let graph_tokens: Vec<Address> = graph.token_to_node.keys().copied().collect();
+    if let Err(e) = engine.flash_liquidity().refresh(&graph_tokens, &pools).await {
+        debug!("Flash liquidity refresh failed: {}", e);
     }
-    if let Ok(addr) = "0xf939E0A03FB07F59A73314E73794Be0E57ac1b4E".parse() {
-        expanded_bases.push(addr); // crvUSD
+    let mut expanded_bases = engine.flash_liquidity()
+        .start_tokens(&token_prices, config.min_flash_liquidity_usd);
+    if expanded_bases.is_empty() {
+        expanded_bases = base_tokens.clone(); // No lender data: configured bases only
     }
-    let cycles: Vec<_> = bellman_ford.find_all_cycles(&expanded_bases)
-        .into_iter()
+    debug!("{} flash-loanable start tokens", expanded_bases.len());
+    let refresh = cycle_index.refresh(&graph, &pools, &expanded_bases);
+    println!(
+        "DEBUG: Cycle index {} - {} pools changed, {} cycles re-scored in {:.2}ms",
+        if refresh.rebuilt { "rebuilt" } else { "updated" },
+        refresh.changed_pools,
+        refresh.rescored_cycles,
+        refresh.elapsed_us as f64 / 1000.0
+    );
+    let cycles: Vec<_> = cycle_index.cycles().iter()
+        .filter(|c| c.expected_return > MIN_REPORTED_RETURN && c.is_valid())
         .filter(|c| !config.is_cycle_blacklisted(&c.path))
+        // Cataloged cycles are re-checked: pool depths move between rebuilds
+        .filter(|c| cycle_index.policy().is_none_or(|p| p.allows_cycle(c)))
+        .cloned()
         .collect();
 
     let cycles_found = cycles.len();
@@ -464,10 +510,12 @@ async fn run_scan(
     // - Min return: 0.01% (1.0001) to filter out noise/rounding errors
     // - No max filter needed: accurate get_dy pricing from Curve pools
     //   eliminates false positives from balance-ratio mispricing
-    let candidates: Vec<_> = sorted.into_iter()
+    // Diversify: a single mispriced pool may only fill a few candidate slots
+    let ranked: Vec<_> = sorted.into_iter()
         .filter(|c| c.expected_return > 1.0001)
-        .take(5)
         .collect();
+    let candidates = CandidateSelector::new(config.max_candidates, config.max_cycles_per_pool)
+        .select(&ranked);
 
     println!("DEBUG: {} candidates after filter (return > 1.0001)", candidates.len());
 
@@ -501,7 +549,11 @@ async fn run_scan(
     //     }
     // }
     // Create simulator with REAL gas price
-    let swap_sim = SwapSimulator::new(&config.rpc_url).await?;
+    let mut swap_sim = SwapSimulator::new(&config.rpc_url).await?;
+    swap_sim.set_eth_price(eth_price);
+    swap_sim.set_token_prices(token_prices);
+    swap_sim.set_gas_model(gas_model.clone());
+    swap_sim.set_parallel_pools(&graph, &candidates);
     // Note: We calculate gas cost separately using gas_info for accuracy
 
     // === PREFETCH V2 RESERVES (OPTIMIZATION) ===
@@ -522,34 +574,79 @@ async fn run_scan(
         }
     }
 
+    let trade_sizer = TradeSizer::default();
+    let split_router = SplitRouter::default();
+
     let mut best_gross_profit = 0.0f64;
     let mut best_net_profit = f64::NEG_INFINITY;
     let mut best_path = String::new();
     let mut profitable_count = 0;
     let mut candidates_simulated = 0;
+    let mut profitable = Vec::new();
 
     // === SIMULATE EACH CANDIDATE ===
     for cycle in &candidates {
         candidates_simulated += 1;
         stats.simulations_run += 1;
         
-        let tier = swap_sim.get_cycle_liquidity_tier(cycle);
-        let target_usd = tier.recommended_amount_usd().min(config.default_simulation_usd);
+        // Size is bounded by config, the risk policy and what can actually be borrowed
+        let start_token = cycle.path[0];
+        let lender_exclusions = cycle_lender_exclusions(cycle);
+        let mut max_input_usd = config.max_flash_loan_usd
+            .min(cycle_index.policy().map_or(f64::INFINITY, |p| p.max_notional_usd(cycle)));
+        if engine.flash_liquidity().is_loaded() {
+            let available = engine.flash_liquidity()
+                .max_borrowable(&start_token, &lender_exclusions)
+                .unwrap_or(U256::ZERO);
+            let decimals = cartographer::get_token_decimals(&start_token);
+            let available_tokens: f64 = available.to_string().parse::<f64>().unwrap_or(0.0)
+                / 10f64.powi(decimals as i32);
+            max_input_usd = max_input_usd.min(available_tokens * swap_sim.token_price_usd(start_token));
+        }
         
-        // Run simulation
-        let sim = swap_sim.simulate_cycle(cycle, target_usd).await;
+        // Find the profit-maximizing size over the quoting engine
+        let mut sized = trade_sizer.optimize(&swap_sim, cycle, max_input_usd).await;
+        
+        // Profitable on a single pool per hop: see if splitting hops across
+        // parallel pools lets it go bigger
+        if sized.simulation.return_multiplier() > 1.0 && swap_sim.has_parallel_pools(cycle) {
+            let split = trade_sizer.optimize_split(&swap_sim, cycle, max_input_usd, &split_router).await;
+            if split.simulation.profit_usd > sized.simulation.profit_usd {
+                debug!(
+                    "Split route beats single: ${:.2} vs ${:.2} at ${:.0}",
+                    split.simulation.profit_usd, sized.simulation.profit_usd, split.optimal_input_usd()
+                );
+                sized = split;
+            }
+        }
+        let sim = sized.simulation.clone();
+        let target_usd = sim.input_usd;
 
         if !sim.simulation_success {
             continue;
         }
 
         // Calculate ACCURATE gas cost with REAL gas price
-        let gas_cost_usd = gas_info.estimate_cost_usd(sim.total_gas_used, eth_price);
+        // On rollups this includes the L1 data fee for the executor calldata
+        let min_profit = sim.start_token_amount(config.min_profit_usd);
+        let gas_cost = match engine.flash_loan_builder().estimate_calldata(cycle, sim.route.as_ref(), sim.input_amount, min_profit) {
+            Ok((to, calldata)) => {
+                gas_oracle.estimate_total_cost(&gas_info, sim.total_gas_used, to, &calldata, eth_price).await
+            }
+            Err(e) => {
+                debug!("Calldata encoding failed, using execution gas only: {}", e);
+                gas_info.estimate_total_cost(sim.total_gas_used, &[], eth_price)
+            }
+        };
+        let gas_cost_usd = gas_cost.total_usd();
         
         // Calculate profits
         let gross_return = sim.return_multiplier();
         let gross_profit_usd = target_usd * (gross_return - 1.0);
-        let net_profit_usd = gross_profit_usd - gas_cost_usd;
+        let flash_fee_usd = engine.flash_liquidity()
+            .best_lender(&start_token, sim.input_amount, &lender_exclusions)
+            .map_or(0.0, |lender| target_usd * lender.fee_fraction());
+        let net_profit_usd = gross_profit_usd - gas_cost_usd - flash_fee_usd;
         
         let path_str = format_path_short(cycle, token_symbols);
         
@@ -590,49 +687,16 @@ async fn run_scan(
             )).green().bold());
             println!("{}", style("╠════════════════════════════════════════════════════════════════╣").green());
             println!("║  Path: {} ({})", style(&path_str).cyan(), style(&dex_str).magenta());
-            println!("║  Return: {:.4}x │ Gross: ${:.2} │ Gas: ${:.2} @ {:.2} gwei",
-                gross_return, gross_profit_usd, gas_cost_usd, gas_gwei);
+            println!("║  Return: {:.4}x │ Gross: ${:.2} │ Gas: ${:.2} @ {:.2} gwei │ Flash fee: ${:.2}",
+                gross_return, gross_profit_usd, gas_cost_usd, gas_gwei, flash_fee_usd);
             println!("║  Input: ${:.0} │ Gas units: {} │ ETH: ${:.0}",
                 target_usd, sim.total_gas_used, eth_price);
-            println!("{}", style("╚════════════════════════════════════════════════════════════════╝").green().bold());
-
-            // Execute
-            stats.executions_attempted += 1;
-            
-            match engine.execute(cycle, &sim, 0).await {
-                Ok(result) => {
-                    match &result {
-                        executor::ExecutionResult::Simulated { expected_profit_usd, would_execute } => {
-                            println!("   {} Simulation mode: profit ${:.2}, execute={}", 
-                                style("📋").dim(), expected_profit_usd, would_execute);
-                        }
-                        executor::ExecutionResult::DryRun { simulation_passed, gas_used, .. } => {
-                            println!("   {} Dry run: passed={}, gas={:?}", 
-                                style("🔬").dim(), simulation_passed, gas_used);
-                        }
-                        executor::ExecutionResult::Submitted { bundle_hash, target_block, .. } => {
-                            println!("   {} SUBMITTED to block {}: {}", 
-                                style("🚀").green().bold(), target_block, bundle_hash);
-                        }
-                        executor::ExecutionResult::Included { block_number, actual_profit_wei, .. } => {
-                            println!("   {} INCLUDED in block {}! Profit: {} wei", 
-                                style("✅").green().bold(), block_number, actual_profit_wei);
-                        }
-                        executor::ExecutionResult::Skipped { reason } => {
-                            println!("   {} Skipped: {}", style("⏭").yellow(), reason);
-                        }
-                        executor::ExecutionResult::Aborted { reason } => {
-                            println!("   {} Aborted: {}", style("⛔").red(), reason);
-                        }
-                        executor::ExecutionResult::Failed { reason } => {
-                            println!("   {} Failed: {}", style("✗").red(), reason);
-                        }
-                    }
-                }
-                Err(e) => {
-                    println!("   {} Execution error: {}", style("✗").red(), e);
-                }
+            println!("║  Size curve: {}{}", sized.format_curve(),
+                if sized.is_capped() { " (capped)" } else { "" });
+            if gas_cost.l1_data_wei > 0 {
+                println!("║  L1 data fee: ${:.2} ({})", gas_cost.l1_data_usd(), gas_oracle.gas_model());
             }
+            println!("{}", style("╚════════════════════════════════════════════════════════════════╝").green().bold());
 
             // Log to file with accurate data
             if config.simulation_log {
@@ -641,11 +705,66 @@ async fn run_scan(
                     net_profit_usd, gas_gwei, eth_price, token_symbols
                 );
             }
-            
+
+            profitable.push((cycle, sim, net_profit_usd));
             println!();
         }
     }
 
+    // === RESOLVE EARLIER SUBMISSIONS ===
+    let reports = engine.poll_inclusions().await;
+    for report in &reports {
+        print_execution_result(&report.result());
+    }
+    if !reports.is_empty() {
+        let totals = engine.inclusion_stats();
+        println!("   {} Realized ${:.2} of ${:.2} expected ({} included, {} outbid, {} not included, {} reverted, {} replaced, {} superseded)",
+            style("💰").dim(), totals.realized_usd, totals.expected_usd,
+            totals.included, totals.outbid, totals.not_included, totals.reverted, totals.replaced,
+            totals.superseded);
+    }
+
+    // === EXECUTE THE BEST POOL-DISJOINT SET ===
+    // Cycles sharing a pool would trade against each other in the same block,
+    // split-route legs included
+    let traded_pools: Vec<Vec<Address>> = profitable.iter()
+        .map(|(cycle, sim, _)| {
+            let mut pools = cycle.pools.clone();
+            for pool in sim.route.iter().flat_map(|route| route.pools()) {
+                if !pools.contains(&pool) {
+                    pools.push(pool);
+                }
+            }
+            pools
+        })
+        .collect();
+    let pool_sets: Vec<(&[Address], f64)> = traded_pools.iter()
+        .zip(&profitable)
+        .map(|(pools, (_, _, net_profit_usd))| (pools.as_slice(), *net_profit_usd))
+        .collect();
+    let selected = best_disjoint_set(&pool_sets);
+    if selected.len() < profitable.len() {
+        println!("   {} {} of {} profitable cycles are pool-disjoint; executing those",
+            style("⚖").dim(), selected.len(), profitable.len());
+    }
+
+    if !selected.is_empty() {
+        // One bundle: the pool-disjoint cycles land together behind a single bribe
+        let batch: Vec<_> = selected.iter()
+            .map(|&i| (profitable[i].0, &profitable[i].1))
+            .collect();
+        stats.executions_attempted += 1;
+        
+        match engine.execute_batch(&batch, 0).await {
+            Ok(result) => print_execution_result(&result),
+            Err(e) => {
+                println!("   {} Execution error: {}", style("✗").red(), e);
+            }
+        }
+
+        println!();
+    }
+
     Ok(ScanResult {
         cycles_found,
         candidates_simulated,
@@ -661,6 +780,37 @@ async fn run_scan(
     })
 }
 
+/// One line per execution outcome
+fn print_execution_result(result: &executor::ExecutionResult) {
+    match result {
+        executor::ExecutionResult::Simulated { expected_profit_usd, would_execute } => {
+            println!("   {} Simulation mode: profit ${:.2}, execute={}", 
+                style("📋").dim(), expected_profit_usd, would_execute);
+        }
+        executor::ExecutionResult::DryRun { simulation_passed, gas_used, .. } => {
+            println!("   {} Dry run: passed={}, gas={:?}", 
+                style("🔬").dim(), simulation_passed, gas_used);
+        }
+        executor::ExecutionResult::Submitted { bundle_hash, target_block, .. } => {
+            println!("   {} SUBMITTED to block {}: {}", 
+                style("🚀").green().bold(), target_block, bundle_hash);
+        }
+        executor::ExecutionResult::Included { block_number, actual_profit_wei, .. } => {
+            println!("   {} INCLUDED in block {}! Profit: {} wei", 
+                style("✅").green().bold(), block_number, actual_profit_wei);
+        }
+        executor::ExecutionResult::Skipped { reason } => {
+            println!("   {} Skipped: {}", style("⏭").yellow(), reason);
+        }
+        executor::ExecutionResult::Aborted { reason } => {
+            println!("   {} Aborted: {}", style("⛔").red(), reason);
+        }
+        executor::ExecutionResult::Failed { reason } => {
+            println!("   {} Failed: {}", style("✗").red(), reason);
+        }
+    }
+}
+
 /// Log opportunity to file with accurate gas pricing
 fn log_opportunity(
     config: &Config,
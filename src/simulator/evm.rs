@@ -1,242 +1,388 @@
-//! REVM Setup - Phase 3
+//! REVM Fork Simulation - Phase 3
 //!
-//! Spins up a local EVM instance that forks the current block.
-//! Uses AlloyDB to fetch state on-demand from the RPC provider.
+//! Runs the exact executor transaction(s) in a local EVM on a fork of the
+//! latest block before anything is signed. State is fetched on demand over
+//! RPC (AlloyDB) and cached per fork block, so the bundles simulated within
+//! one block only pay for each account / slot once.
+//!
+//! Reports per transaction: success, return data, gas used, decoded revert
+//! reason and balance changes (ETH and the cycle's tokens) for the sender,
+//! the executor and the block's coinbase (to check bribes arrive). If the
+//! executor isn't deployed on the fork, runtime bytecode from
+//! `EXECUTOR_BYTECODE_PATH` is injected at its address, owned by the sender.
 
-use alloy_primitives::{Address, Bytes, U256, keccak256};
-use alloy_provider::{Provider, ProviderBuilder, RootProvider};
 use alloy_network::Ethereum;
-use alloy_transport_http::{Client, Http};
+use alloy_primitives::{Address, Bytes, I256, TxKind, B256, U256};
+use alloy_provider::{DynProvider, Provider, ProviderBuilder};
+use alloy_sol_types::{decode_revert_reason, sol, SolCall};
 use eyre::{eyre, Result};
+use revm::context::result::{ExecutionResult, Output};
+use revm::context::{BlockEnv, CfgEnv, TxEnv};
+use revm::database::{AlloyDB, BlockId, CacheDB, DBTransportError, WrapDatabaseAsync};
+use revm::state::{AccountInfo, Bytecode};
 use revm::{
-    db::{AlloyDB, CacheDB},
-    primitives::{
-        AccountInfo, Bytecode, ExecutionResult, Output, TransactTo,
-        SpecId,
-    },
-    Evm,
+    Context, Database, DatabaseRef, ExecuteCommitEvm, ExecuteEvm, MainBuilder, MainContext, MainnetEvm,
 };
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
 use tracing::{debug, info};
 
-/// Type alias for our cached database backed by Alloy RPC
-pub type AlloyCacheDB = CacheDB<AlloyDB<Http<Client>, Ethereum, Arc<RootProvider<Http<Client>>>>>;
+use crate::executor::{FlashLoanTransaction, EXECUTOR_OWNER_SLOT};
 
-/// The EVM Simulator - wraps REVM with AlloyDB for mainnet forking
-pub struct EvmSimulator {
-    cache_db: AlloyCacheDB,
-    provider: Arc<RootProvider<Http<Client>>>,
+sol! {
+    interface IForkBalance {
+        function balanceOf(address account) external view returns (uint256);
+    }
 }
 
-impl EvmSimulator {
-    /// Create a new simulator connected to the given RPC URL
-    pub async fn new(rpc_url: &str) -> Result<Self> {
-        info!("Initializing REVM simulator with RPC: {}", rpc_url);
-        
-        let provider = ProviderBuilder::new()
-            .on_http(rpc_url.parse()?);
-        let provider = Arc::new(provider);
-        
-        // Get current block number for logging
-        let block_number = provider.get_block_number().await?;
-        info!("Forking at block: {}", block_number);
-        
-        // Create AlloyDB that fetches state on-demand
-        let alloy_db = AlloyDB::new(provider.clone(), Default::default());
-        let cache_db = CacheDB::new(alloy_db);
-        
-        Ok(Self {
-            cache_db,
-            provider,
-        })
+/// Gas limit for balance reads inside the fork
+const READ_GAS_LIMIT: u64 = 100_000;
+
+/// Seconds between the fork block and the simulated one
+const BLOCK_TIME_SECS: u64 = 12;
+
+// ============================================
+// RESULTS
+// ============================================
+
+/// Balance change of one account in one asset (`Address::ZERO` = ETH)
+#[derive(Debug, Clone, PartialEq)]
+pub struct BalanceDelta {
+    pub token: Address,
+    pub account: Address,
+    pub before: U256,
+    pub after: U256,
+}
+
+impl BalanceDelta {
+    pub fn delta(&self) -> I256 {
+        I256::from_raw(self.after).wrapping_sub(I256::from_raw(self.before))
     }
-    
-    /// Execute a call without committing state changes
-    /// Returns the output bytes on success
-    pub fn call(
-        &mut self,
-        from: Address,
-        to: Address,
-        calldata: Bytes,
-        value: U256,
-    ) -> Result<Bytes> {
-        let mut evm = Evm::builder()
-            .with_db(&mut self.cache_db)
-            .with_spec_id(SpecId::CANCUN)
-            .modify_tx_env(|tx| {
-                tx.caller = from;
-                tx.transact_to = TransactTo::Call(to);
-                tx.data = calldata;
-                tx.value = value;
-                tx.gas_limit = 1_000_000;
-            })
-            .build();
-        
-        let result = evm.transact()?;
-        
-        match result.result {
-            ExecutionResult::Success { output: Output::Call(value), .. } => Ok(value),
-            ExecutionResult::Success { .. } => Err(eyre!("Unexpected success type")),
-            ExecutionResult::Revert { output, .. } => {
-                Err(eyre!("Call reverted: 0x{}", hex::encode(&output)))
-            }
-            ExecutionResult::Halt { reason, .. } => {
-                Err(eyre!("Call halted: {:?}", reason))
-            }
+}
+
+/// Outcome of one transaction on the fork
+#[derive(Debug, Clone)]
+pub struct ForkSimulation {
+    pub success: bool,
+    pub output: Bytes,
+    pub gas_used: u64,
+    pub revert_reason: Option<String>,
+    pub balance_deltas: Vec<BalanceDelta>,
+    /// Fee recipient of the simulated block
+    pub coinbase: Address,
+}
+
+impl ForkSimulation {
+    /// ETH the coinbase received (gas is priced at zero, so only transfers)
+    pub fn coinbase_payment(&self) -> U256 {
+        let delta = self.delta_of(Address::ZERO, self.coinbase);
+        if delta.is_negative() { U256::ZERO } else { delta.into_raw() }
+    }
+
+    /// Net change of `account`'s balance of `token`
+    pub fn delta_of(&self, token: Address, account: Address) -> I256 {
+        self.balance_deltas.iter()
+            .find(|d| d.token == token && d.account == account)
+            .map_or(I256::ZERO, |d| d.delta())
+    }
+}
+
+/// Readable revert reason (Error(string), Panic(uint) or raw data)
+fn revert_reason(output: &[u8]) -> String {
+    decode_revert_reason(output).unwrap_or_else(|| format!("0x{}", hex::encode(output)))
+}
+
+// ============================================
+// FORK DATABASE
+// ============================================
+
+/// RPC-backed state at one block, with everything fetched kept in memory
+struct ForkDb {
+    block: u64,
+    remote: WrapDatabaseAsync<AlloyDB<Ethereum, DynProvider>>,
+    accounts: RwLock<HashMap<Address, Option<AccountInfo>>>,
+    storage: RwLock<HashMap<(Address, U256), U256>>,
+    contracts: RwLock<HashMap<B256, Bytecode>>,
+    block_hashes: RwLock<HashMap<u64, B256>>,
+}
+
+impl DatabaseRef for ForkDb {
+    type Error = DBTransportError;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        if let Some(info) = self.accounts.read().unwrap().get(&address) {
+            return Ok(info.clone());
         }
+        let info = self.remote.basic_ref(address)?;
+        if let Some(code) = info.as_ref().and_then(|i| i.code.clone()) {
+            self.contracts.write().unwrap().insert(code.hash_slow(), code);
+        }
+        self.accounts.write().unwrap().insert(address, info.clone());
+        Ok(info)
     }
-    
-    /// Execute a call that we expect to revert (for quoter pattern)
-    /// Returns the revert data which contains the output
-    pub fn call_expecting_revert(
-        &mut self,
-        from: Address,
-        to: Address,
-        calldata: Bytes,
-    ) -> Result<Bytes> {
-        let mut evm = Evm::builder()
-            .with_db(&mut self.cache_db)
-            .with_spec_id(SpecId::CANCUN)
-            .modify_tx_env(|tx| {
-                tx.caller = from;
-                tx.transact_to = TransactTo::Call(to);
-                tx.data = calldata;
-                tx.value = U256::ZERO;
-                tx.gas_limit = 1_000_000;
-            })
-            .build();
-        
-        let result = evm.transact()?;
-        
-        match result.result {
-            ExecutionResult::Revert { output, .. } => Ok(output),
-            ExecutionResult::Success { .. } => {
-                Err(eyre!("Expected revert but call succeeded"))
-            }
-            ExecutionResult::Halt { reason, .. } => {
-                Err(eyre!("Call halted: {:?}", reason))
-            }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        // Code arrives with the account in `basic_ref`
+        Ok(self.contracts.read().unwrap().get(&code_hash).cloned().unwrap_or_default())
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        if let Some(value) = self.storage.read().unwrap().get(&(address, index)) {
+            return Ok(*value);
+        }
+        let value = self.remote.storage_ref(address, index)?;
+        self.storage.write().unwrap().insert((address, index), value);
+        Ok(value)
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        if let Some(hash) = self.block_hashes.read().unwrap().get(&number) {
+            return Ok(*hash);
+        }
+        let hash = self.remote.block_hash_ref(number)?;
+        self.block_hashes.write().unwrap().insert(number, hash);
+        Ok(hash)
+    }
+}
+
+// ============================================
+// SIMULATOR
+// ============================================
+
+/// Fork simulator for executor transactions
+pub struct EvmSimulator {
+    rpc_url: String,
+    chain_id: u64,
+    executor_code: Option<Bytes>,
+    fork: Mutex<Option<(Arc<ForkDb>, BlockEnv)>>,
+}
+
+impl EvmSimulator {
+    pub fn new(rpc_url: String, chain_id: u64) -> Self {
+        Self {
+            rpc_url,
+            chain_id,
+            executor_code: None,
+            fork: Mutex::new(None),
         }
     }
-    
-    /// Execute a call and commit the state changes
-    /// Returns (output bytes, gas used)
-    pub fn call_and_commit(
-        &mut self,
+
+    /// Runtime bytecode injected at the executor address when it has no code
+    pub fn with_executor_code(mut self, code: Bytes) -> Self {
+        self.executor_code = Some(code);
+        self
+    }
+
+    /// Run `txs` in order from `from` on a fork of `block` (0 = latest),
+    /// as if included in the next block
+    ///
+    /// Balances of `from`, `executor` and the coinbase are tracked in ETH and `tokens`.
+    pub async fn simulate_bundle(
+        &self,
+        txs: &[&FlashLoanTransaction],
         from: Address,
-        to: Address,
-        calldata: Bytes,
-        value: U256,
-    ) -> Result<(Bytes, u64)> {
-        let mut evm = Evm::builder()
-            .with_db(&mut self.cache_db)
-            .with_spec_id(SpecId::CANCUN)
-            .modify_tx_env(|tx| {
-                tx.caller = from;
-                tx.transact_to = TransactTo::Call(to);
-                tx.data = calldata;
-                tx.value = value;
-                tx.gas_limit = 1_000_000;
+        executor: Address,
+        tokens: &[Address],
+        block: u64,
+    ) -> Result<Vec<ForkSimulation>> {
+        let (fork, block_env) = self.fork_at(block).await?;
+        let executor_code = self.executor_code.clone();
+        let chain_id = self.chain_id;
+        let txs: Vec<TxEnv> = txs.iter()
+            .map(|tx| TxEnv {
+                caller: from,
+                kind: TxKind::Call(tx.to),
+                data: tx.calldata.clone(),
+                value: tx.value,
+                gas_limit: tx.gas_limit,
+                gas_price: 0,
+                chain_id: None,
+                ..Default::default()
             })
-            .build();
-        
-        let result = evm.transact_commit()?;
-        
-        match result {
-            ExecutionResult::Success { output: Output::Call(value), gas_used, .. } => {
-                Ok((value, gas_used))
-            }
-            ExecutionResult::Success { gas_used, .. } => {
-                Ok((Bytes::new(), gas_used))
-            }
-            ExecutionResult::Revert { output, gas_used, .. } => {
-                Err(eyre!("Call reverted after {} gas: 0x{}", gas_used, hex::encode(&output)))
+            .collect();
+        let coinbase = block_env.beneficiary;
+        let accounts = [from, executor, coinbase];
+        let tokens = tokens.to_vec();
+
+        // State is fetched synchronously as the EVM runs: keep it off the async workers
+        tokio::task::spawn_blocking(move || {
+            let mut db = CacheDB::new(fork.as_ref());
+
+            if let Some(code) = executor_code {
+                let deployed = db.basic(executor)?.and_then(|a| a.code).is_some_and(|c| !c.is_empty());
+                if !deployed {
+                    debug!("Injecting executor bytecode at {:?}", executor);
+                    let existing = db.basic(executor)?.unwrap_or_default();
+                    db.insert_account_info(executor, AccountInfo {
+                        code: Some(Bytecode::new_raw(code)),
+                        ..existing
+                    });
+                    db.insert_account_storage(executor, EXECUTOR_OWNER_SLOT, U256::from_be_slice(from.as_slice()))?;
+                }
             }
-            ExecutionResult::Halt { reason, gas_used, .. } => {
-                Err(eyre!("Call halted after {} gas: {:?}", gas_used, reason))
+
+            let mut results = Vec::with_capacity(txs.len());
+            for tx in txs {
+                let before = read_balances(&mut db, &block_env, chain_id, &accounts, &tokens)?;
+
+                let result = build_evm(&mut db, &block_env, chain_id)
+                    .transact_commit(tx)
+                    .map_err(|e| eyre!("EVM error: {}", e))?;
+
+                let after = read_balances(&mut db, &block_env, chain_id, &accounts, &tokens)?;
+                let balance_deltas = before.into_iter()
+                    .zip(after)
+                    .map(|((token, account, before), (_, _, after))| BalanceDelta { token, account, before, after })
+                    .filter(|d| d.before != d.after)
+                    .collect();
+
+                results.push(match result {
+                    ExecutionResult::Success { gas_used, output, .. } => ForkSimulation {
+                        success: true,
+                        output: match output {
+                            Output::Call(data) => data,
+                            Output::Create(data, _) => data,
+                        },
+                        gas_used,
+                        revert_reason: None,
+                        balance_deltas,
+                        coinbase,
+                    },
+                    ExecutionResult::Revert { gas_used, output } => ForkSimulation {
+                        success: false,
+                        revert_reason: Some(revert_reason(&output)),
+                        output,
+                        gas_used,
+                        balance_deltas,
+                        coinbase,
+                    },
+                    ExecutionResult::Halt { reason, gas_used } => ForkSimulation {
+                        success: false,
+                        output: Bytes::new(),
+                        gas_used,
+                        revert_reason: Some(format!("halted: {:?}", reason)),
+                        balance_deltas,
+                        coinbase,
+                    },
+                });
             }
-        }
+            Ok(results)
+        })
+        .await?
     }
-    
-    /// Insert a mock account with custom bytecode
-    /// Useful for deploying custom quoter contracts
-    pub fn insert_account_with_bytecode(
-        &mut self,
-        address: Address,
-        bytecode: Bytes,
-    ) -> Result<()> {
-        let bytecode = Bytecode::new_raw(bytecode);
-        let code_hash = bytecode.hash_slow();
-        
-        let acc_info = AccountInfo {
-            balance: U256::ZERO,
-            nonce: 0,
-            code: Some(bytecode),
-            code_hash,
+
+    /// Cached fork of `block` (0 = latest) and the environment of the block after it
+    async fn fork_at(&self, block: u64) -> Result<(Arc<ForkDb>, BlockEnv)> {
+        let provider = ProviderBuilder::new()
+            .on_http(self.rpc_url.parse()?)
+            .erased();
+        let block = if block == 0 { provider.get_block_number().await? } else { block };
+
+        if let Some((fork, env)) = self.fork.lock().unwrap().as_ref().filter(|(f, _)| f.block == block) {
+            return Ok((fork.clone(), env.clone()));
+        }
+
+        let header = provider.get_block_by_number(block.into()).await?
+            .ok_or_else(|| eyre!("Block {} not found", block))?
+            .header;
+        let env = BlockEnv {
+            number: U256::from(block + 1),
+            beneficiary: header.beneficiary,
+            timestamp: U256::from(header.timestamp + BLOCK_TIME_SECS),
+            gas_limit: header.gas_limit,
+            basefee: 0,
+            prevrandao: Some(header.mix_hash),
+            ..Default::default()
         };
-        
-        self.cache_db.insert_account_info(address, acc_info);
-        Ok(())
-    }
-    
-    /// Insert a storage value for an account
-    pub fn insert_storage(
-        &mut self,
-        address: Address,
-        slot: U256,
-        value: U256,
-    ) -> Result<()> {
-        self.cache_db.insert_account_storage(address, slot, value)?;
-        Ok(())
-    }
-    
-    /// Insert a mapping storage slot (for ERC20 balances, etc.)
-    /// Uses keccak256(abi.encode(key, slot)) as per Solidity storage layout
-    pub fn insert_mapping_storage(
-        &mut self,
-        contract: Address,
-        mapping_slot: U256,
-        key: Address,
-        value: U256,
-    ) -> Result<()> {
-        // Solidity mapping storage: keccak256(abi.encode(key, slot))
-        let mut encoded = [0u8; 64];
-        encoded[12..32].copy_from_slice(key.as_slice());
-        mapping_slot.to_be_bytes::<32>().iter().enumerate().for_each(|(i, b)| {
-            encoded[32 + i] = *b;
+
+        let remote = WrapDatabaseAsync::new(AlloyDB::new(provider, BlockId::number(block)))
+            .ok_or_else(|| eyre!("Fork simulation needs a multi-threaded tokio runtime"))?;
+        let fork = Arc::new(ForkDb {
+            block,
+            remote,
+            accounts: RwLock::default(),
+            storage: RwLock::default(),
+            contracts: RwLock::default(),
+            block_hashes: RwLock::default(),
         });
-        
-        let hashed_slot = keccak256(&encoded);
-        self.cache_db.insert_account_storage(contract, U256::from_be_bytes(hashed_slot.0), value)?;
-        
-        Ok(())
+        info!("Forked state at block {}", block);
+
+        *self.fork.lock().unwrap() = Some((fork.clone(), env.clone()));
+        Ok((fork, env))
     }
-    
-    /// Get the underlying provider for RPC calls
-    pub fn provider(&self) -> &Arc<RootProvider<Http<Client>>> {
-        &self.provider
+}
+
+/// Mainnet EVM over a fork database
+type ForkEvm<DB> = MainnetEvm<Context<BlockEnv, TxEnv, CfgEnv, DB>>;
+
+/// Mainnet EVM over `db`, executing in `block_env` with gas paid at price 0
+fn build_evm<DB: Database>(db: DB, block_env: &BlockEnv, chain_id: u64) -> ForkEvm<DB> {
+    Context::mainnet()
+        .with_db(db)
+        .with_block(block_env.clone())
+        .modify_cfg_chained(|cfg| {
+            cfg.chain_id = chain_id;
+            cfg.disable_nonce_check = true;
+        })
+        .build_mainnet()
+}
+
+/// (token, account, balance) for ETH and each token, for each account
+fn read_balances<DB: Database>(
+    db: &mut DB,
+    block_env: &BlockEnv,
+    chain_id: u64,
+    accounts: &[Address],
+    tokens: &[Address],
+) -> Result<Vec<(Address, Address, U256)>> {
+    let mut balances = Vec::with_capacity(accounts.len() * (tokens.len() + 1));
+    for &account in accounts {
+        let eth = db.basic(account)
+            .map_err(|e| eyre!("Fork state error: {}", e))?
+            .map_or(U256::ZERO, |a| a.balance);
+        balances.push((Address::ZERO, account, eth));
     }
-    
-    /// Get current gas price in gwei
-    pub async fn gas_price_gwei(&self) -> Result<f64> {
-        let gas_price = self.provider.get_gas_price().await?;
-        Ok(gas_price as f64 / 1e9)
+
+    let mut evm = build_evm(&mut *db, block_env, chain_id);
+    for &token in tokens {
+        for &account in accounts {
+            let call = TxEnv {
+                kind: TxKind::Call(token),
+                data: IForkBalance::balanceOfCall { account }.abi_encode().into(),
+                gas_limit: READ_GAS_LIMIT,
+                gas_price: 0,
+                chain_id: None,
+                ..Default::default()
+            };
+            let balance = match evm.transact(call).map_err(|e| eyre!("EVM error: {}", e))?.result {
+                ExecutionResult::Success { output: Output::Call(data), .. } => {
+                    IForkBalance::balanceOfCall::abi_decode_returns(&data).unwrap_or_default()
+                }
+                _ => U256::ZERO,
+            };
+            balances.push((token, account, balance));
+        }
     }
+    Ok(balances)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    
-    #[tokio::test]
-    async fn test_simulator_creation() {
-        // This test requires RPC_URL to be set
-        if std::env::var("RPC_URL").is_err() {
-            return;
-        }
-        
-        let rpc_url = std::env::var("RPC_URL").unwrap();
-        let sim = EvmSimulator::new(&rpc_url).await;
-        assert!(sim.is_ok());
+    use alloy_sol_types::{Revert, SolError};
+
+    #[test]
+    fn test_revert_reason_and_delta() {
+        let data = Revert::from("Insufficient output").abi_encode();
+        assert_eq!(revert_reason(&data), "revert: Insufficient output");
+        assert_eq!(revert_reason(&[0xff, 0xfe]), "0xfffe");
+
+        let delta = BalanceDelta {
+            token: Address::ZERO,
+            account: Address::ZERO,
+            before: U256::from(100),
+            after: U256::from(40),
+        };
+        assert_eq!(delta.delta(), I256::try_from(-60).unwrap());
     }
 }
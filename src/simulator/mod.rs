@@ -2,8 +2,16 @@
 //! 
 //! Uses alloy Provider's call() for simulation.
 
+mod evm;
+mod optimizer;
 mod quoter;
+mod split_route;
 pub mod swap_simulator;
 
+pub use evm::EvmSimulator;
+pub use optimizer::TradeSizer;
 pub use quoter::UniV3Quoter;
+pub use split_route::{SplitRouter, SplitRoute};
+#[cfg(test)]
+pub(crate) use split_route::{RouteHop, RouteLeg};
 pub use swap_simulator::{SwapSimulator, ArbitrageSimulation, SwapResult, LiquidityTier};
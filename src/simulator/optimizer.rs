@@ -0,0 +1,239 @@
+//! Trade Size Optimizer
+//!
+//! Cycle output is concave in the input amount (every hop has diminishing
+//! marginal output), so profit has a single maximum. We find it with a
+//! golden-section search over the real quoting engine, in log-space so the
+//! search is equally precise for $100 and $100k trades.
+
+use std::cell::RefCell;
+use std::future::Future;
+use tracing::debug;
+
+use super::split_route::SplitRouter;
+use super::swap_simulator::{ArbitrageSimulation, SwapSimulator};
+use crate::brain::ArbitrageCycle;
+
+/// 1 / golden ratio
+const INV_PHI: f64 = 0.618_033_988_749_895;
+
+/// One sampled point of the profit curve
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProfitPoint {
+    pub input_usd: f64,
+    pub profit_usd: f64,
+}
+
+/// Result of sizing a cycle: the best simulation plus every sampled point
+#[derive(Debug, Clone)]
+pub struct SizedTrade {
+    /// Simulation at the profit-maximizing input
+    pub simulation: ArbitrageSimulation,
+
+    /// Sampled profit curve, sorted by input
+    pub curve: Vec<ProfitPoint>,
+
+    /// Upper bound the search was run against (config cap / flash liquidity)
+    pub max_input_usd: f64,
+}
+
+impl SizedTrade {
+    pub fn optimal_input_usd(&self) -> f64 {
+        self.simulation.input_usd
+    }
+
+    /// Whether the optimum sits at the upper bound (more size would pay more)
+    pub fn is_capped(&self) -> bool {
+        self.optimal_input_usd() >= self.max_input_usd * 0.99
+    }
+
+    /// Compact "input → profit" listing for logs
+    pub fn format_curve(&self) -> String {
+        self.curve.iter()
+            .map(|p| format!("${:.0}→{:+.2}", p.input_usd, p.profit_usd))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Golden-section trade sizer
+#[derive(Debug, Clone)]
+pub struct TradeSizer {
+    /// Smallest trade worth considering (USD)
+    pub min_input_usd: f64,
+
+    /// Stop once the bracket is within this ratio (1.05 = 5%)
+    pub tolerance_ratio: f64,
+
+    /// Hard cap on simulations per cycle
+    pub max_evaluations: usize,
+}
+
+impl Default for TradeSizer {
+    fn default() -> Self {
+        Self {
+            min_input_usd: 50.0,
+            tolerance_ratio: 1.05,
+            max_evaluations: 14,
+        }
+    }
+}
+
+impl TradeSizer {
+    /// Find the profit-maximizing input for `cycle`, at most `max_input_usd`
+    pub async fn optimize(
+        &self,
+        simulator: &SwapSimulator,
+        cycle: &ArbitrageCycle,
+        max_input_usd: f64,
+    ) -> SizedTrade {
+        self.optimize_route(simulator, cycle, max_input_usd, None).await
+    }
+
+    /// Like `optimize`, with every hop split across its parallel pools
+    pub async fn optimize_split(
+        &self,
+        simulator: &SwapSimulator,
+        cycle: &ArbitrageCycle,
+        max_input_usd: f64,
+        router: &SplitRouter,
+    ) -> SizedTrade {
+        self.optimize_route(simulator, cycle, max_input_usd, Some(router)).await
+    }
+
+    async fn optimize_route(
+        &self,
+        simulator: &SwapSimulator,
+        cycle: &ArbitrageCycle,
+        max_input_usd: f64,
+        router: Option<&SplitRouter>,
+    ) -> SizedTrade {
+        let best: RefCell<Option<ArbitrageSimulation>> = RefCell::new(None);
+        let simulate = |input_usd: f64| async move {
+            match router {
+                Some(router) => simulator.simulate_split_cycle_at(cycle, input_usd, router).await,
+                None => simulator.simulate_cycle_at(cycle, input_usd).await,
+            }
+        };
+
+        let curve = self.search(max_input_usd, |input_usd| {
+            let best = &best;
+            async move {
+                let sim = simulate(input_usd).await;
+                let profit = sim.profit_usd;
+                let mut best = best.borrow_mut();
+                if best.as_ref().is_none_or(|b| profit > b.profit_usd) {
+                    *best = Some(sim);
+                }
+                profit
+            }
+        }).await;
+
+        let simulation = match best.into_inner() {
+            Some(sim) => sim,
+            None => simulate(max_input_usd.max(self.min_input_usd)).await,
+        };
+
+        let sized = SizedTrade {
+            simulation,
+            curve,
+            max_input_usd,
+        };
+
+        debug!(
+            "Sized {}-hop cycle: ${:.0} (max ${:.0}) in {} sims: {}",
+            cycle.hop_count(),
+            sized.optimal_input_usd(),
+            max_input_usd,
+            sized.curve.len(),
+            sized.format_curve()
+        );
+
+        sized
+    }
+
+    /// Golden-section search for the maximum of `eval` on
+    /// [min_input_usd, max_input_usd] (log-space); returns all sampled points
+    pub async fn search<F, Fut>(&self, max_input_usd: f64, mut eval: F) -> Vec<ProfitPoint>
+    where
+        F: FnMut(f64) -> Fut,
+        Fut: Future<Output = f64>,
+    {
+        let mut curve = Vec::new();
+
+        if max_input_usd <= self.min_input_usd {
+            let profit_usd = eval(max_input_usd).await;
+            curve.push(ProfitPoint { input_usd: max_input_usd, profit_usd });
+            return curve;
+        }
+
+        let mut lo = self.min_input_usd.ln();
+        let mut hi = max_input_usd.ln();
+        let tolerance = self.tolerance_ratio.ln();
+
+        let mut c = hi - INV_PHI * (hi - lo);
+        let mut d = lo + INV_PHI * (hi - lo);
+        let mut fc = eval(c.exp()).await;
+        let mut fd = eval(d.exp()).await;
+        curve.push(ProfitPoint { input_usd: c.exp(), profit_usd: fc });
+        curve.push(ProfitPoint { input_usd: d.exp(), profit_usd: fd });
+
+        while hi - lo > tolerance && curve.len() < self.max_evaluations {
+            // Ties (incl. both failing) shrink towards smaller sizes
+            if fc >= fd {
+                hi = d;
+                d = c;
+                fd = fc;
+                c = hi - INV_PHI * (hi - lo);
+                fc = eval(c.exp()).await;
+                curve.push(ProfitPoint { input_usd: c.exp(), profit_usd: fc });
+            } else {
+                lo = c;
+                c = d;
+                fc = fd;
+                d = lo + INV_PHI * (hi - lo);
+                fd = eval(d.exp()).await;
+                curve.push(ProfitPoint { input_usd: d.exp(), profit_usd: fd });
+            }
+        }
+
+        // The bracket may have converged onto the cap: sample it explicitly
+        if (hi - max_input_usd.ln()).abs() < f64::EPSILON && fd > fc && curve.len() < self.max_evaluations {
+            let profit_usd = eval(max_input_usd).await;
+            curve.push(ProfitPoint { input_usd: max_input_usd, profit_usd });
+        }
+
+        curve.sort_by(|a, b| a.input_usd.total_cmp(&b.input_usd));
+        curve
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_finds_concave_maximum() {
+        // Constant-product style: out = k·x / (L + x); profit peaks at (sqrt(k) - 1)·L
+        let (k, liquidity): (f64, f64) = (1.02, 200_000.0);
+        let optimum = (k.sqrt() - 1.0) * liquidity;
+        let profit = |x: f64| k * liquidity * x / (liquidity + x) - x;
+
+        let sizer = TradeSizer { max_evaluations: 40, tolerance_ratio: 1.01, ..Default::default() };
+        let curve = tokio_test::block_on(sizer.search(100_000.0, |x| async move { profit(x) }));
+
+        let best = curve.iter().max_by(|a, b| a.profit_usd.total_cmp(&b.profit_usd)).unwrap();
+        assert!((best.input_usd - optimum).abs() / optimum < 0.02, "{} vs {}", best.input_usd, optimum);
+        assert!(curve.windows(2).all(|w| w[0].input_usd <= w[1].input_usd));
+    }
+
+    #[test]
+    fn test_search_respects_cap() {
+        // Monotonically increasing profit: optimum is the cap
+        let sizer = TradeSizer::default();
+        let curve = tokio_test::block_on(sizer.search(5_000.0, |x| async move { x * 0.001 }));
+
+        assert!(curve.iter().all(|p| p.input_usd <= 5_000.0 + 1e-6));
+        let best = curve.iter().max_by(|a, b| a.profit_usd.total_cmp(&b.profit_usd)).unwrap();
+        assert!(best.input_usd > 5_000.0 * 0.95);
+    }
+}
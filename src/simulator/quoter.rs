@@ -1,6 +1,7 @@
 //! UniswapV3/V2 Quoter - Provider-based Simulation
 //!
-//! Uses the official Uniswap QuoterV2 contract via eth_call for V3 quotes.
+//! Uses each V3 fork's own QuoterV2 (Uniswap, PancakeSwap, SushiSwap) via
+//! eth_call for V3 quotes, after checking the pool belongs to that fork's factory.
 //! Uses constant product formula for V2 quotes.
 //!
 //! OPTIMIZATIONS:
@@ -20,6 +21,8 @@ use std::time::{Duration, Instant};
 use tracing::debug;
 use lazy_static::lazy_static;
 
+use crate::cartographer::Dex;
+
 /// Cache duration for reserves (should match or be slightly less than scan interval)
 const RESERVES_CACHE_DURATION_SECS: u64 = 15;
 
@@ -44,6 +47,9 @@ lazy_static! {
     /// Global cache for V3 pool fees (immutable per pool)
     static ref FEE_CACHE: RwLock<HashMap<Address, u32>> = RwLock::new(HashMap::new());
 
+    /// Global cache of pools checked against their fork's factory (immutable per pool)
+    static ref FACTORY_POOL_CACHE: RwLock<HashMap<Address, bool>> = RwLock::new(HashMap::new());
+
     /// Global cache for V2 reserves (short TTL - scan duration)
     static ref RESERVES_CACHE: RwLock<HashMap<Address, CachedReserves>> = RwLock::new(HashMap::new());
 }
@@ -82,6 +88,12 @@ sol! {
         function token1() external view returns (address);
     }
     
+    /// Uniswap V3 Factory interface (same on PancakeSwap / SushiSwap forks)
+    #[derive(Debug)]
+    interface IUniswapV3Factory {
+        function getPool(address tokenA, address tokenB, uint24 fee) external view returns (address pool);
+    }
+
     /// Uniswap V2 Pair interface
     #[derive(Debug)]
     interface IUniswapV2Pair {
@@ -123,10 +135,68 @@ pub struct QuoteResult {
     pub pool: Address,
     pub zero_for_one: bool,
     pub gas_estimate: u64,
+    /// Initialized ticks crossed (V3 only)
+    pub ticks_crossed: u32,
+}
+
+// ============================================
+// V3 FORK DEPLOYMENTS
+// ============================================
+
+/// Mainnet contracts of one Uniswap V3 fork
+#[derive(Debug, Clone, Copy)]
+pub struct V3Deployment {
+    pub factory: Address,
+    /// QuoterV2 (`quoteExactInputSingle` with the Uniswap params struct)
+    pub quoter: Address,
+    /// Fee tiers enabled on the factory, in hundredths of a bip
+    pub fee_tiers: &'static [u32],
 }
 
-/// Official Uniswap V3 QuoterV2 address on mainnet
-const QUOTER_V2: Address = address!("61fFE014bA17989E743c5F6cB21bF9697530B21e");
+pub const UNISWAP_V3: V3Deployment = V3Deployment {
+    factory: address!("1F98431c8aD98523631AE4a59f267346ea31F984"),
+    quoter: address!("61fFE014bA17989E743c5F6cB21bF9697530B21e"),
+    fee_tiers: &[100, 500, 3000, 10000],
+};
+
+pub const PANCAKESWAP_V3: V3Deployment = V3Deployment {
+    factory: address!("0BFbCF9fa4f9C56B0F40a671Ad40E0805A091865"),
+    quoter: address!("B048Bbc1Ee6b733FFfCFb9e9CeF7375518e25997"),
+    fee_tiers: &[100, 500, 2500, 10000],
+};
+
+pub const SUSHISWAP_V3: V3Deployment = V3Deployment {
+    factory: address!("bACEB8eC6b9355Dfc0269C18bac9d6E2Bdc29C4F"),
+    quoter: address!("64e8802FE490fa7cc61d3463958199161Bb608A7"),
+    fee_tiers: &[100, 500, 3000, 10000],
+};
+
+/// Deployment for a V3-family DEX (None for non-V3 DEXes)
+pub fn v3_deployment(dex: Dex) -> Option<&'static V3Deployment> {
+    match dex {
+        Dex::UniswapV3 => Some(&UNISWAP_V3),
+        Dex::PancakeSwapV3 => Some(&PANCAKESWAP_V3),
+        Dex::SushiswapV3 => Some(&SUSHISWAP_V3),
+        _ => None,
+    }
+}
+
+/// Fee tier to quote at: the pool's own fee is authoritative, the graph's
+/// fee a fallback, and the fork's factory must enable it
+fn quote_fee(deployment: &V3Deployment, dex: Dex, pool_fee: Option<u32>, graph_fee: u32) -> Result<u32> {
+    let fee = pool_fee.unwrap_or(graph_fee);
+    if !deployment.fee_tiers.contains(&fee) {
+        return Err(eyre!("Fee tier {} not enabled on {}", fee, dex));
+    }
+    Ok(fee)
+}
+
+/// Whether a factory's `getPool` answer is `pool`
+fn is_factory_pool(get_pool_output: &[u8], pool: Address) -> Result<bool> {
+    let factory_pool = IUniswapV3Factory::getPoolCall::abi_decode_returns(get_pool_output)
+        .map_err(|e| eyre!("Failed to decode getPool: {}", e))?;
+    Ok(factory_pool == pool)
+}
 
 /// UniV3 Quoter using Provider's eth_call
 ///
@@ -234,7 +304,11 @@ impl UniV3Quoter {
         debug!("Reserves cache invalidated");
     }
     
-    /// Quote a V3 swap using the official QuoterV2 contract
+    /// Quote a V3 swap using the fork's QuoterV2 contract
+    ///
+    /// The quoter selects a pool by (tokenIn, tokenOut, fee) on its own
+    /// factory, so the pool's on-chain fee is used and the pool is checked
+    /// against that factory before quoting.
     pub async fn quote_v3(
         &self,
         pool: Address,
@@ -242,12 +316,19 @@ impl UniV3Quoter {
         token_out: Address,
         amount_in: U256,
         fee: u32,
+        dex: Dex,
     ) -> Result<QuoteResult> {
         debug!(
-            "Quoting V3 swap: {} -> {} via {:?}, amount: {}",
-            token_in, token_out, pool, amount_in
+            "Quoting {} swap: {} -> {} via {:?}, amount: {}",
+            dex, token_in, token_out, pool, amount_in
         );
         
+        let deployment = v3_deployment(dex)
+            .ok_or_else(|| eyre!("{} is not a V3 fork", dex))?;
+        
+        let fee = quote_fee(deployment, dex, self.get_pool_fee(pool).await.ok(), fee)?;
+        self.verify_factory_pool(deployment, pool, token_in, token_out, fee).await?;
+        
         // Get token0 to determine direction
         let token0 = self.get_pool_token0(pool).await?;
         let zero_for_one = token_in == token0;
@@ -257,19 +338,20 @@ impl UniV3Quoter {
             tokenIn: token_in,
             tokenOut: token_out,
             amountIn: amount_in,
-            fee: fee.try_into().unwrap_or(3000u32).try_into().unwrap(),
+            fee: alloy_primitives::Uint::<24, 1>::from(fee),
             sqrtPriceLimitX96: alloy_primitives::Uint::<160, 3>::ZERO,
         };
         
         let calldata = IQuoterV2::quoteExactInputSingleCall { params }.abi_encode();
         
-        match self.call_contract(QUOTER_V2, calldata).await {
+        match self.call_contract(deployment.quoter, calldata).await {
             Ok(output) => {
                 // Decode the output
                 let decoded = IQuoterV2::quoteExactInputSingleCall::abi_decode_returns(&output)
                     .map_err(|e| eyre!("Failed to decode quoter output: {}", e))?;
                 
                 let gas: u64 = decoded.gasEstimate.to();
+                let ticks_crossed = decoded.initializedTicksCrossed;
                 
                 Ok(QuoteResult {
                     amount_in,
@@ -277,6 +359,7 @@ impl UniV3Quoter {
                     pool,
                     zero_for_one,
                     gas_estimate: gas,
+                    ticks_crossed,
                 })
             }
             Err(e) => {
@@ -330,6 +413,7 @@ impl UniV3Quoter {
             pool,
             zero_for_one,
             gas_estimate: 100_000, // V2 swaps are cheaper
+            ticks_crossed: 0,
         })
     }
 
@@ -434,6 +518,39 @@ impl UniV3Quoter {
         Ok(fee)
     }
 
+    /// Check that `pool` is the fork factory's pool for the pair and fee
+    /// (CACHED - immutable per pool)
+    async fn verify_factory_pool(
+        &self,
+        deployment: &V3Deployment,
+        pool: Address,
+        token_in: Address,
+        token_out: Address,
+        fee: u32,
+    ) -> Result<()> {
+        let cached = FACTORY_POOL_CACHE.read().unwrap().get(&pool).copied();
+        let matches = match cached {
+            Some(matches) => matches,
+            None => {
+                let calldata = IUniswapV3Factory::getPoolCall {
+                    tokenA: token_in,
+                    tokenB: token_out,
+                    fee: alloy_primitives::Uint::<24, 1>::from(fee),
+                }.abi_encode();
+                let output = self.call_contract(deployment.factory, calldata).await?;
+                let matches = is_factory_pool(&output, pool)?;
+                FACTORY_POOL_CACHE.write().unwrap().insert(pool, matches);
+                matches
+            }
+        };
+
+        if matches {
+            Ok(())
+        } else {
+            Err(eyre!("Pool {:?} is not the factory pool for fee {} at {:?}", pool, fee, deployment.factory))
+        }
+    }
+
     /// Get cache statistics for monitoring
     /// Returns (token0_count, fee_count, reserves_count, valid_reserves_count)
     pub fn cache_stats() -> (usize, usize, usize, usize) {
@@ -445,3 +562,46 @@ impl UniV3Quoter {
         (token0_count, fee_count, reserves_count, valid_reserves)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_v3_deployments_per_fork() {
+        let uni = v3_deployment(Dex::UniswapV3).unwrap();
+        let pancake = v3_deployment(Dex::PancakeSwapV3).unwrap();
+        let sushi = v3_deployment(Dex::SushiswapV3).unwrap();
+
+        assert_ne!(uni.quoter, pancake.quoter);
+        assert_ne!(uni.quoter, sushi.quoter);
+        assert_ne!(uni.factory, sushi.factory);
+        assert!(pancake.fee_tiers.contains(&2500));
+        assert!(!pancake.fee_tiers.contains(&3000));
+        assert!(v3_deployment(Dex::UniswapV2).is_none());
+    }
+
+    #[test]
+    fn test_fee_tier_selection() {
+        // The pool's fee wins over the graph's
+        assert_eq!(quote_fee(&PANCAKESWAP_V3, Dex::PancakeSwapV3, Some(2500), 3000).unwrap(), 2500);
+        assert_eq!(quote_fee(&UNISWAP_V3, Dex::UniswapV3, None, 500).unwrap(), 500);
+
+        // Tiers only another fork enables are rejected
+        assert!(quote_fee(&UNISWAP_V3, Dex::UniswapV3, Some(2500), 3000).is_err());
+        assert!(quote_fee(&PANCAKESWAP_V3, Dex::PancakeSwapV3, None, 3000).is_err());
+    }
+
+    #[test]
+    fn test_pool_from_another_factory_is_rejected() {
+        let pool = Address::repeat_byte(0x11);
+        let other = Address::repeat_byte(0x22);
+        let answer = |p: Address| IUniswapV3Factory::getPoolCall::abi_encode_returns(&p);
+
+        assert!(is_factory_pool(&answer(pool), pool).unwrap());
+        // Same pair and fee on this factory is a different pool, or none at all
+        assert!(!is_factory_pool(&answer(other), pool).unwrap());
+        assert!(!is_factory_pool(&answer(Address::ZERO), pool).unwrap());
+        assert!(is_factory_pool(&[], pool).is_err());
+    }
+}
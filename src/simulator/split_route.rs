@@ -0,0 +1,292 @@
+//! Split Routing - spread each hop over every pool for the pair
+//!
+//! A single pool caps how much a cycle can move before its price is gone.
+//! When a pair trades in several pools (V3 fee tiers, V2 forks, Curve), the
+//! hop input is allocated slice by slice to whichever pool gives the most
+//! extra output for it. Repeating that equalizes marginal prices across the
+//! pools, the optimal split for concave outputs.
+
+use alloy_primitives::{Address, U256};
+use eyre::{eyre, Result};
+use futures::future::join_all;
+use tracing::debug;
+
+use super::swap_simulator::{SwapResult, SwapSimulator};
+use crate::cartographer::{ArbitrageGraph, Dex};
+
+/// Basis points in 100%
+const BPS: u64 = 10_000;
+
+/// One pool that can fill a hop
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoolOption {
+    pub pool: Address,
+    pub dex: Dex,
+    pub fee: u32,
+}
+
+impl PoolOption {
+    /// Every pool trading `token_in` → `token_out` in the graph
+    pub fn for_pair(graph: &ArbitrageGraph, token_in: Address, token_out: Address) -> Vec<Self> {
+        graph.parallel_edges(token_in, token_out)
+            .into_iter()
+            .map(|e| Self { pool: e.pool_address, dex: e.dex, fee: e.fee })
+            .collect()
+    }
+}
+
+/// The options of hop `hop` of a cycle trading through `cycle_pools` that
+/// touch no other hop's pool nor a pool in `taken` (legs already chosen)
+///
+/// Every leg is quoted against unchanged reserves, so a pool traded twice in
+/// one cycle would count its liquidity twice.
+pub fn available_options(
+    options: &[PoolOption],
+    cycle_pools: &[Address],
+    hop: usize,
+    taken: &[Address],
+) -> Vec<PoolOption> {
+    options.iter()
+        .filter(|o| {
+            !taken.contains(&o.pool)
+                && !cycle_pools.iter().enumerate().any(|(i, p)| i != hop && *p == o.pool)
+        })
+        .copied()
+        .collect()
+}
+
+/// A share of a hop routed through one pool
+#[derive(Debug, Clone)]
+pub struct RouteLeg {
+    pub swap: SwapResult,
+    pub fee: u32,
+    /// Share of the hop input, in basis points (legs of a hop sum to 10_000)
+    pub share_bps: u16,
+}
+
+/// One hop of a route, possibly split across pools
+#[derive(Debug, Clone)]
+pub struct RouteHop {
+    pub token_in: Address,
+    pub token_out: Address,
+    pub legs: Vec<RouteLeg>,
+}
+
+impl RouteHop {
+    pub fn amount_in(&self) -> U256 {
+        self.legs.iter().map(|l| l.swap.amount_in).fold(U256::ZERO, |a, b| a + b)
+    }
+
+    pub fn amount_out(&self) -> U256 {
+        self.legs.iter().map(|l| l.swap.amount_out).fold(U256::ZERO, |a, b| a + b)
+    }
+}
+
+/// Executable plan for a cycle: every hop with its pool legs
+#[derive(Debug, Clone, Default)]
+pub struct SplitRoute {
+    pub hops: Vec<RouteHop>,
+}
+
+impl SplitRoute {
+    /// Whether any hop uses more than one pool
+    pub fn is_split(&self) -> bool {
+        self.hops.iter().any(|h| h.legs.len() > 1)
+    }
+
+    pub fn leg_count(&self) -> usize {
+        self.hops.iter().map(|h| h.legs.len()).sum()
+    }
+
+    /// Every pool a leg trades through
+    pub fn pools(&self) -> Vec<Address> {
+        self.hops.iter().flat_map(|h| h.legs.iter().map(|l| l.swap.pool)).collect()
+    }
+}
+
+/// Greedy marginal-price router
+#[derive(Debug, Clone)]
+pub struct SplitRouter {
+    /// Number of slices a hop input is allocated in
+    pub slices: u64,
+
+    /// Legs below this share are folded into the largest leg (not worth the gas)
+    pub min_leg_share_bps: u16,
+}
+
+impl Default for SplitRouter {
+    fn default() -> Self {
+        Self {
+            slices: 10,
+            min_leg_share_bps: 1_000,
+        }
+    }
+}
+
+impl SplitRouter {
+    /// Allocate `amount_in` of one hop across `options`
+    pub async fn split_hop(
+        &self,
+        simulator: &SwapSimulator,
+        token_in: Address,
+        token_out: Address,
+        options: &[PoolOption],
+        amount_in: U256,
+    ) -> Result<RouteHop> {
+        if options.is_empty() {
+            return Err(eyre!("No pools for hop"));
+        }
+
+        let amounts = allocate(self.slices, amount_in, options.len(), |i, amount| {
+            let option = options[i];
+            async move {
+                simulator.simulate_swap(option.pool, token_in, token_out, amount, option.fee, option.dex)
+                    .await
+                    .map(|s| s.amount_out)
+                    .ok()
+            }
+        }).await.ok_or_else(|| eyre!("All pools failed to quote"))?;
+
+        let amounts = fold_small_legs(amounts, amount_in, self.min_leg_share_bps);
+
+        // Final quotes for the kept legs
+        let kept: Vec<usize> = (0..options.len()).filter(|&i| amounts[i] > U256::ZERO).collect();
+        let quotes = join_all(kept.iter().map(|&i| {
+            let option = options[i];
+            simulator.simulate_swap(option.pool, token_in, token_out, amounts[i], option.fee, option.dex)
+        })).await;
+
+        let shares = shares_bps(&kept.iter().map(|&i| amounts[i]).collect::<Vec<_>>(), amount_in);
+        let mut legs = Vec::with_capacity(kept.len());
+        for ((&i, quote), share_bps) in kept.iter().zip(quotes).zip(shares) {
+            legs.push(RouteLeg {
+                swap: quote?,
+                fee: options[i].fee,
+                share_bps,
+            });
+        }
+
+        if legs.len() > 1 {
+            debug!(
+                "Split hop {:?} -> {:?} over {} pools: {:?}",
+                token_in, token_out, legs.len(),
+                legs.iter().map(|l| l.share_bps).collect::<Vec<_>>()
+            );
+        }
+
+        Ok(RouteHop { token_in, token_out, legs })
+    }
+}
+
+/// Greedy allocation: each slice goes to the option with the largest output
+/// gain. `quote(i, amount)` returns option `i`'s total output for `amount`.
+async fn allocate<F, Fut>(slices: u64, amount_in: U256, options: usize, mut quote: F) -> Option<Vec<U256>>
+where
+    F: FnMut(usize, U256) -> Fut,
+    Fut: std::future::Future<Output = Option<U256>>,
+{
+    let slices = slices.max(1);
+    let slice = amount_in / U256::from(slices);
+
+    let mut allocated = vec![U256::ZERO; options];
+    let mut outputs = vec![U256::ZERO; options];
+
+    for s in 0..slices {
+        let chunk = if s == slices - 1 {
+            amount_in - slice * U256::from(slices - 1)
+        } else {
+            slice
+        };
+        if chunk == U256::ZERO {
+            continue;
+        }
+
+        let quotes = join_all((0..options).map(|i| quote(i, allocated[i] + chunk))).await;
+
+        let best = quotes.iter()
+            .enumerate()
+            .filter_map(|(i, q)| q.map(|out| (i, out, out.saturating_sub(outputs[i]))))
+            .max_by_key(|(_, _, gain)| *gain)?;
+
+        allocated[best.0] += chunk;
+        outputs[best.0] = best.1;
+    }
+
+    Some(allocated)
+}
+
+/// Move legs below `min_share_bps` of the hop into the largest leg
+fn fold_small_legs(mut amounts: Vec<U256>, total: U256, min_share_bps: u16) -> Vec<U256> {
+    let Some(largest) = (0..amounts.len()).max_by_key(|&i| amounts[i]) else {
+        return amounts;
+    };
+    let threshold = total * U256::from(min_share_bps) / U256::from(BPS);
+
+    for i in 0..amounts.len() {
+        if i != largest && amounts[i] > U256::ZERO && amounts[i] < threshold {
+            let moved = amounts[i];
+            amounts[i] = U256::ZERO;
+            amounts[largest] += moved;
+        }
+    }
+    amounts
+}
+
+/// Basis-point shares of `total`; the last leg takes the rounding remainder
+fn shares_bps(amounts: &[U256], total: U256) -> Vec<u16> {
+    if total == U256::ZERO || amounts.is_empty() {
+        return vec![0; amounts.len()];
+    }
+
+    let mut shares: Vec<u16> = amounts.iter()
+        .map(|a| (*a * U256::from(BPS) / total).to::<u64>() as u16)
+        .collect();
+    let assigned: u64 = shares[..shares.len() - 1].iter().map(|s| *s as u64).sum();
+    let last = shares.len() - 1;
+    shares[last] = (BPS - assigned) as u16;
+    shares
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocation_equalizes_marginal_output() {
+        // Two constant-product pools, the second twice as deep
+        let reserves = [1_000_000u64, 2_000_000u64];
+        let quote = |i: usize, amount: U256| {
+            let r = U256::from(reserves[i]);
+            async move { Some(r * amount / (r + amount)) }
+        };
+
+        let amounts = tokio_test::block_on(allocate(30, U256::from(300_000u64), 2, quote)).unwrap();
+
+        // Optimal split is proportional to depth: 100k / 200k
+        assert_eq!(amounts[0] + amounts[1], U256::from(300_000u64));
+        assert_eq!(amounts[0], U256::from(100_000u64));
+        assert_eq!(shares_bps(&amounts, U256::from(300_000u64)), vec![3_333, 6_667]);
+    }
+
+    #[test]
+    fn test_parallel_pool_of_another_hop_is_excluded() {
+        let (a, b) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let option = |pool| PoolOption { pool, dex: Dex::UniswapV2, fee: 3000 };
+
+        // Hop 0 trades through `a`; `b` is the other hop's pool, listed as
+        // parallel because it trades the same pair
+        let options = [option(a), option(b)];
+        let available = available_options(&options, &[a, b], 0, &[]);
+        assert_eq!(available, vec![option(a)]);
+
+        // A pool an earlier leg already uses is excluded too
+        assert!(available_options(&options, &[a, b], 0, &[a]).iter().all(|o| o.pool != a));
+    }
+
+    #[test]
+    fn test_small_legs_are_folded() {
+        let amounts = vec![U256::from(950u64), U256::from(50u64)];
+        let folded = fold_small_legs(amounts, U256::from(1_000u64), 1_000);
+        assert_eq!(folded, vec![U256::from(1_000u64), U256::ZERO]);
+    }
+}
@@ -10,9 +10,13 @@ use alloy_provider::{Provider, ProviderBuilder};
 use eyre::Result;
 use tracing::{debug, info, warn};
 
+use std::collections::HashMap;
+
+use super::split_route::{available_options, PoolOption, RouteHop, RouteLeg, SplitRoute, SplitRouter};
 use super::UniV3Quoter;
 use crate::brain::ArbitrageCycle;
-use crate::cartographer::{Dex, PoolState, get_token_decimals};
+use crate::cartographer::{ArbitrageGraph, Dex, PoolState, get_token_decimals};
+use crate::swap_gas::SwapGasModel;
 
 /// Maximum gas estimate per swap to prevent unrealistic values
 const MAX_GAS_PER_SWAP: u64 = 500_000;
@@ -52,6 +56,7 @@ pub struct SwapResult {
     pub amount_in: U256,
     pub amount_out: U256,
     pub gas_used: u64,
+    pub ticks_crossed: u32,
     pub dex: Dex,
 }
 
@@ -72,6 +77,8 @@ pub struct ArbitrageSimulation {
     pub token_decimals: u8,
     pub liquidity_tier: LiquidityTier,
     pub input_usd: f64,
+    /// Per-hop pool legs (always set on success; split when a hop uses several pools)
+    pub route: Option<SplitRoute>,
 }
 
 impl ArbitrageSimulation {
@@ -87,14 +94,26 @@ impl ArbitrageSimulation {
     pub fn gross_profit_pct(&self) -> f64 {
         (self.return_multiplier() - 1.0) * 100.0
     }
+    
+    /// `usd` worth of the start token, at the live price the input was sized with
+    pub fn start_token_amount(&self, usd: f64) -> U256 {
+        if self.input_usd <= 0.0 {
+            return U256::ZERO;
+        }
+        let input_f64: f64 = self.input_amount.to_string().parse().unwrap_or(0.0);
+        U256::from((usd * input_f64 / self.input_usd) as u128)
+    }
 }
 
 /// Swap simulator using Provider-based eth_call
 pub struct SwapSimulator {
     rpc_url: String,
     quoter: UniV3Quoter,
+    gas_model: SwapGasModel,
+    parallel_pools: HashMap<(Address, Address), Vec<PoolOption>>,
     gas_price_gwei: f64,
     eth_price_usd: f64,
+    token_prices_usd: HashMap<Address, f64>,
 }
 
 impl SwapSimulator {
@@ -123,8 +142,11 @@ impl SwapSimulator {
         Ok(Self {
             rpc_url: rpc_url.to_string(),
             quoter,
+            gas_model: SwapGasModel::default(),
+            parallel_pools: HashMap::new(),
             gas_price_gwei,
             eth_price_usd: 3500.0,
+            token_prices_usd: HashMap::new(),
         })
     }
     
@@ -132,6 +154,37 @@ impl SwapSimulator {
         self.eth_price_usd = eth_price_usd;
     }
     
+    /// USD prices of the graph's tokens, for every start token conversion
+    pub fn set_token_prices(&mut self, token_prices_usd: HashMap<Address, f64>) {
+        self.token_prices_usd = token_prices_usd;
+    }
+    
+    /// Use a (shared, calibrated) gas model for swap and overhead gas
+    pub fn set_gas_model(&mut self, gas_model: SwapGasModel) {
+        self.gas_model = gas_model;
+    }
+    
+    /// Register the parallel pools of every hop of `cycles` for split routing
+    pub fn set_parallel_pools(&mut self, graph: &ArbitrageGraph, cycles: &[ArbitrageCycle]) {
+        for cycle in cycles {
+            for pair in cycle.path.windows(2) {
+                let key = (pair[0], pair[1]);
+                if self.parallel_pools.contains_key(&key) {
+                    continue;
+                }
+                let options = PoolOption::for_pair(graph, pair[0], pair[1]);
+                if options.len() > 1 {
+                    self.parallel_pools.insert(key, options);
+                }
+            }
+        }
+    }
+    
+    /// Whether any hop of the cycle can be split across pools
+    pub fn has_parallel_pools(&self, cycle: &ArbitrageCycle) -> bool {
+        cycle.path.windows(2).any(|p| self.parallel_pools.contains_key(&(p[0], p[1])))
+    }
+    
     pub fn set_gas_price(&mut self, gas_price_gwei: f64) {
         self.gas_price_gwei = gas_price_gwei.max(MIN_GAS_PRICE_GWEI);
     }
@@ -200,21 +253,18 @@ impl SwapSimulator {
         min_tier
     }
     
+    /// USD price of a token from the graph's live prices, 0 when the graph
+    /// can't price it (used for sizing and profit)
+    pub fn token_price_usd(&self, token: Address) -> f64 {
+        self.token_prices_usd.get(&token).copied().unwrap_or(0.0)
+    }
+    
     pub fn get_simulation_amount(&self, token: Address, target_usd: f64) -> U256 {
         let decimals = get_token_decimals(&token);
-        let addr_hex = format!("{:?}", token).to_lowercase();
-        
-        let token_price_usd = if addr_hex.contains("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2") {
-            self.eth_price_usd
-        } else if addr_hex.contains("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48") 
-               || addr_hex.contains("dac17f958d2ee523a2206206994597c13d831ec7")
-               || addr_hex.contains("6b175474e89094c44da98b954eedcdecb5be3830") {
-            1.0
-        } else if addr_hex.contains("2260fac5e5542a773aa44fbcfedf7c193bc2c599") {
-            95000.0
-        } else {
-            self.eth_price_usd
-        };
+        let token_price_usd = self.token_price_usd(token);
+        if token_price_usd <= 0.0 {
+            return U256::ZERO;
+        }
         
         let amount_float = (target_usd / token_price_usd) * 10_f64.powi(decimals as i32);
         
@@ -234,9 +284,10 @@ impl SwapSimulator {
         fee: u32,
         dex: Dex,
     ) -> Result<SwapResult> {
-        let quote = self.quoter.quote_v3(pool, token_in, token_out, amount_in, fee).await?;
+        let quote = self.quoter.quote_v3(pool, token_in, token_out, amount_in, fee, dex).await?;
         
-        let gas_used = quote.gas_estimate.min(MAX_GAS_PER_SWAP);
+        let gas_used = self.gas_model.swap_gas(dex, pool, quote.ticks_crossed)
+            .min(MAX_GAS_PER_SWAP);
         
         Ok(SwapResult {
             pool,
@@ -245,6 +296,7 @@ impl SwapSimulator {
             amount_in: quote.amount_in,
             amount_out: quote.amount_out,
             gas_used,
+            ticks_crossed: quote.ticks_crossed,
             dex,
         })
     }
@@ -265,11 +317,35 @@ impl SwapSimulator {
             token_out,
             amount_in: quote.amount_in,
             amount_out: quote.amount_out,
-            gas_used: quote.gas_estimate.min(MAX_GAS_PER_SWAP),
+            gas_used: self.gas_model.swap_gas(dex, pool, 0).min(MAX_GAS_PER_SWAP),
+            ticks_crossed: 0,
             dex,
         })
     }
     
+    /// Simulate one swap on any supported DEX
+    pub async fn simulate_swap(
+        &self,
+        pool: Address,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256,
+        fee: u32,
+        dex: Dex,
+    ) -> Result<SwapResult> {
+        match dex {
+            Dex::UniswapV3 | Dex::SushiswapV3 | Dex::PancakeSwapV3 => {
+                self.simulate_v3_swap(pool, token_in, token_out, amount_in, fee, dex).await
+            }
+            Dex::UniswapV2 | Dex::SushiswapV2 => {
+                self.simulate_v2_swap(pool, token_in, token_out, amount_in, dex).await
+            }
+            Dex::BalancerV2 | Dex::Curve => {
+                self.simulate_v2_swap(pool, token_in, token_out, amount_in, dex).await
+            }
+        }
+    }
+    
     /// Simulate a full arbitrage cycle
     pub async fn simulate_cycle(
         &self,
@@ -279,13 +355,44 @@ impl SwapSimulator {
         let liquidity_tier = self.get_cycle_liquidity_tier(cycle);
         let target_usd = liquidity_tier.recommended_amount_usd().min(max_input_usd);
         
+        self.simulate_cycle_at(cycle, target_usd).await
+    }
+    
+    /// Simulate a full arbitrage cycle with an exact input size (USD)
+    pub async fn simulate_cycle_at(
+        &self,
+        cycle: &ArbitrageCycle,
+        target_usd: f64,
+    ) -> ArbitrageSimulation {
+        self.simulate_route_at(cycle, target_usd, None).await
+    }
+    
+    /// Simulate a cycle with every hop split across its parallel pools
+    pub async fn simulate_split_cycle_at(
+        &self,
+        cycle: &ArbitrageCycle,
+        target_usd: f64,
+        router: &SplitRouter,
+    ) -> ArbitrageSimulation {
+        self.simulate_route_at(cycle, target_usd, Some(router)).await
+    }
+    
+    async fn simulate_route_at(
+        &self,
+        cycle: &ArbitrageCycle,
+        target_usd: f64,
+        router: Option<&SplitRouter>,
+    ) -> ArbitrageSimulation {
+        let liquidity_tier = self.get_cycle_liquidity_tier(cycle);
+        
         let start_token = cycle.path[0];
         let input_amount = self.get_simulation_amount(start_token, target_usd);
         let token_decimals = get_token_decimals(&start_token);
         
         let mut swaps = Vec::new();
+        let mut hops = Vec::with_capacity(cycle.pools.len());
         let mut current_amount = input_amount;
-        let mut total_gas: u64 = 50_000; // Base overhead
+        let mut total_gas: u64 = self.gas_model.overhead_gas();
         let mut last_error: Option<String> = None;
         
         debug!(
@@ -302,24 +409,35 @@ impl SwapSimulator {
             
             debug!("  Swap {}: {} -> {} via {:?} ({})", i + 1, token_in, token_out, pool, dex);
             
-            let result = match dex {
-                Dex::UniswapV3 | Dex::SushiswapV3 | Dex::PancakeSwapV3 => {
-                    self.simulate_v3_swap(pool, token_in, token_out, current_amount, fee, dex).await
-                }
-                Dex::UniswapV2 | Dex::SushiswapV2 => {
-                    self.simulate_v2_swap(pool, token_in, token_out, current_amount, dex).await
-                }
-                Dex::BalancerV2 | Dex::Curve => {
-                    self.simulate_v2_swap(pool, token_in, token_out, current_amount, dex).await
+            // Never split into a pool another hop or an earlier leg trades
+            let taken: Vec<Address> = hops.iter()
+                .flat_map(|h: &RouteHop| h.legs.iter().map(|l| l.swap.pool))
+                .collect();
+            let options = router
+                .and_then(|_| self.parallel_pools.get(&(token_in, token_out)))
+                .map(|options| available_options(options, &cycle.pools, i, &taken))
+                .filter(|options| options.len() > 1);
+            let result = match (router, options) {
+                (Some(router), Some(options)) => {
+                    router.split_hop(self, token_in, token_out, &options, current_amount).await
                 }
+                _ => self.simulate_swap(pool, token_in, token_out, current_amount, fee, dex).await
+                    .map(|swap| RouteHop {
+                        token_in,
+                        token_out,
+                        legs: vec![RouteLeg { swap, fee, share_bps: 10_000 }],
+                    }),
             };
             
             match result {
-                Ok(swap) => {
-                    debug!("    ✓ Out: {} (gas: {})", swap.amount_out, swap.gas_used);
-                    total_gas += swap.gas_used;
-                    current_amount = swap.amount_out;
-                    swaps.push(swap);
+                Ok(hop) => {
+                    current_amount = hop.amount_out();
+                    for leg in &hop.legs {
+                        debug!("    ✓ Out: {} (gas: {})", leg.swap.amount_out, leg.swap.gas_used);
+                        total_gas += leg.swap.gas_used;
+                        swaps.push(leg.swap.clone());
+                    }
+                    hops.push(hop);
                 }
                 Err(e) => {
                     let err_msg = format!("Swap {} failed: {}", i + 1, e);
@@ -330,7 +448,7 @@ impl SwapSimulator {
             }
         }
         
-        let simulation_success = last_error.is_none() && swaps.len() == cycle.pools.len();
+        let simulation_success = last_error.is_none() && hops.len() == cycle.pools.len();
         
         // Calculate gas cost
         let gas_cost_wei = U256::from((self.gas_price_gwei * 1e9) as u128) * U256::from(total_gas);
@@ -346,18 +464,7 @@ impl SwapSimulator {
             let decimal_factor = 10_f64.powi(token_decimals as i32);
             let profit_tokens = profit_in_token as f64 / decimal_factor;
             
-            let addr_hex = format!("{:?}", start_token).to_lowercase();
-            let token_price = if addr_hex.contains("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2") {
-                self.eth_price_usd
-            } else if addr_hex.contains("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48")
-                   || addr_hex.contains("dac17f958d2ee523a2206206994597c13d831ec7")
-                   || addr_hex.contains("6b175474e89094c44da98b954eedcdecb5be3830") {
-                1.0
-            } else if addr_hex.contains("2260fac5e5542a773aa44fbcfedf7c193bc2c599") {
-                95000.0
-            } else {
-                self.eth_price_usd
-            };
+            let token_price = self.token_price_usd(start_token);
             
             let gross_profit_usd = profit_tokens * token_price;
             let net_profit_usd = gross_profit_usd - gas_cost_usd;
@@ -387,6 +494,7 @@ impl SwapSimulator {
             token_decimals,
             liquidity_tier,
             input_usd: target_usd,
+            route: simulation_success.then_some(SplitRoute { hops }),
         }
     }
 }
@@ -0,0 +1,564 @@
+//! Swap Gas Model - measured gas per DEX, pool and tick crossing
+//!
+//! Replaces the fixed per-swap constants that used to live in the profit
+//! filter, the simulator and the flash loan builder. Estimates start from
+//! conservative defaults and are calibrated from:
+//! - `eth_estimateGas` + `debug_traceCall` on the real executor calldata
+//! - Receipts (+ `debug_traceTransaction`) of our own landed transactions
+//!
+//! Calibrated values are persisted to JSON so they survive restarts.
+
+use alloy_primitives::{Address, Bytes, TxHash};
+use alloy_provider::{Provider, ProviderBuilder};
+use alloy_rpc_types::TransactionRequest;
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tracing::{debug, info, warn};
+
+use crate::brain::ArbitrageCycle;
+use crate::cartographer::Dex;
+use crate::config::FlashLoanProvider;
+use crate::simulator::SplitRoute;
+
+// ============================================
+// CONSTANTS
+// ============================================
+
+/// Weight of a new sample in the moving average
+const EMA_ALPHA: f64 = 0.2;
+
+/// Gas of one initialized tick crossed by a V3 swap
+const DEFAULT_TICK_CROSS_GAS: u64 = 21_000;
+
+/// Headroom added on top of the estimate for the transaction gas limit
+const GAS_LIMIT_BUFFER_PCT: u64 = 25;
+
+/// Samples outside [estimate / 4, estimate * 4] are treated as mismatched traces
+const MAX_SAMPLE_DEVIATION: f64 = 4.0;
+
+/// Default gas of a single swap (excluding tick crossings), measured in the
+/// executor context (token transfers + callbacks included)
+fn default_swap_gas(dex: Dex) -> u64 {
+    match dex {
+        Dex::UniswapV3 | Dex::SushiswapV3 | Dex::PancakeSwapV3 => 95_000,
+        Dex::UniswapV2 | Dex::SushiswapV2 => 65_000,
+        Dex::BalancerV2 => 110_000,
+        Dex::Curve => 160_000,
+    }
+}
+
+/// Default fixed cost of an executor transaction (intrinsic gas, flash loan
+/// round trip, repayment)
+fn default_overhead_gas(provider: FlashLoanProvider) -> u64 {
+    match provider {
+        FlashLoanProvider::BalancerV2 => 85_000,
+        FlashLoanProvider::AaveV3 => 130_000,
+        FlashLoanProvider::UniswapV3 => 75_000,
+    }
+}
+
+/// Whether swaps on this DEX cross ticks
+fn is_tick_based(dex: Dex) -> bool {
+    matches!(dex, Dex::UniswapV3 | Dex::SushiswapV3 | Dex::PancakeSwapV3)
+}
+
+// ============================================
+// PERSISTED TABLE
+// ============================================
+
+/// Moving average of measured gas
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct GasStat {
+    gas: f64,
+    samples: u32,
+}
+
+impl GasStat {
+    fn record(&mut self, gas: u64) {
+        self.gas = if self.samples == 0 {
+            gas as f64
+        } else {
+            self.gas * (1.0 - EMA_ALPHA) + gas as f64 * EMA_ALPHA
+        };
+        self.samples = self.samples.saturating_add(1);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct GasTable {
+    /// Per-DEX swap gas (excluding tick crossings)
+    dexes: HashMap<Dex, GasStat>,
+
+    /// Per-pool swap gas (excluding tick crossings), preferred over the DEX average
+    pools: HashMap<Address, GasStat>,
+
+    /// Per flash loan provider transaction overhead
+    overhead: HashMap<FlashLoanProvider, GasStat>,
+
+    /// Gas per initialized tick crossed
+    #[serde(default = "default_tick_cross")]
+    tick_cross: GasStat,
+}
+
+fn default_tick_cross() -> GasStat {
+    GasStat { gas: DEFAULT_TICK_CROSS_GAS as f64, samples: 0 }
+}
+
+impl Default for GasTable {
+    fn default() -> Self {
+        Self {
+            dexes: HashMap::new(),
+            pools: HashMap::new(),
+            overhead: HashMap::new(),
+            tick_cross: default_tick_cross(),
+        }
+    }
+}
+
+// ============================================
+// SWAP GAS MODEL
+// ============================================
+
+/// Shared, calibrating gas model. Clones share the same table.
+#[derive(Debug, Clone)]
+pub struct SwapGasModel {
+    table: Arc<RwLock<GasTable>>,
+    provider: FlashLoanProvider,
+    path: Option<PathBuf>,
+}
+
+impl Default for SwapGasModel {
+    fn default() -> Self {
+        Self::new(FlashLoanProvider::BalancerV2)
+    }
+}
+
+impl SwapGasModel {
+    /// In-memory model with default estimates
+    pub fn new(provider: FlashLoanProvider) -> Self {
+        Self {
+            table: Arc::new(RwLock::new(GasTable::default())),
+            provider,
+            path: None,
+        }
+    }
+
+    /// Load a persisted model, falling back to defaults if the file is missing
+    /// or unreadable. Calibrations are saved back to the same path.
+    pub fn load(path: impl AsRef<Path>, provider: FlashLoanProvider) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let table = match std::fs::read_to_string(&path) {
+            Ok(json) => match serde_json::from_str::<GasTable>(&json) {
+                Ok(table) => {
+                    info!(
+                        "⛽ Loaded gas model: {} DEX / {} pool calibrations from {}",
+                        table.dexes.len(), table.pools.len(), path.display()
+                    );
+                    table
+                }
+                Err(e) => {
+                    warn!("Ignoring corrupt gas model {}: {}", path.display(), e);
+                    GasTable::default()
+                }
+            },
+            Err(_) => GasTable::default(),
+        };
+
+        Self {
+            table: Arc::new(RwLock::new(table)),
+            provider,
+            path: Some(path),
+        }
+    }
+
+    /// Persist the model (no-op for in-memory models)
+    pub fn save(&self) -> Result<()> {
+        let Some(ref path) = self.path else {
+            return Ok(());
+        };
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = {
+            let table = self.table.read().map_err(|_| eyre!("Gas model lock poisoned"))?;
+            serde_json::to_string_pretty(&*table)?
+        };
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    // --------------------------------------------
+    // Estimates
+    // --------------------------------------------
+
+    /// Gas of one swap through `pool` crossing `ticks_crossed` initialized ticks
+    pub fn swap_gas(&self, dex: Dex, pool: Address, ticks_crossed: u32) -> u64 {
+        let table = self.table.read().unwrap_or_else(|e| e.into_inner());
+        let base = Self::swap_base(&table, dex, pool);
+
+        if is_tick_based(dex) {
+            base + ticks_crossed as u64 * table.tick_cross.gas as u64
+        } else {
+            base
+        }
+    }
+
+    /// Fixed per-transaction overhead for the configured flash loan provider
+    pub fn overhead_gas(&self) -> u64 {
+        let table = self.table.read().unwrap_or_else(|e| e.into_inner());
+        Self::overhead_base(&table, self.provider)
+    }
+
+    /// Total gas of a cycle, with per-hop tick crossings if known
+    pub fn cycle_gas(&self, cycle: &ArbitrageCycle, ticks_crossed: &[u32]) -> u64 {
+        let swaps: u64 = cycle.dexes.iter()
+            .zip(cycle.pools.iter())
+            .enumerate()
+            .map(|(i, (dex, pool))| {
+                self.swap_gas(*dex, *pool, ticks_crossed.get(i).copied().unwrap_or(0))
+            })
+            .sum();
+
+        self.overhead_gas() + swaps
+    }
+
+    /// Gas limit for an executor transaction (estimate + safety buffer)
+    pub fn gas_limit(&self, cycle: &ArbitrageCycle, ticks_crossed: &[u32]) -> u64 {
+        self.cycle_gas(cycle, ticks_crossed) * (100 + GAS_LIMIT_BUFFER_PCT) / 100
+    }
+
+    /// Gas limit for a (possibly split) route: one swap per pool leg
+    pub fn route_gas_limit(&self, route: &SplitRoute) -> u64 {
+        let swaps: u64 = route.hops.iter()
+            .flat_map(|h| h.legs.iter())
+            .map(|l| self.swap_gas(l.swap.dex, l.swap.pool, l.swap.ticks_crossed))
+            .sum();
+        (self.overhead_gas() + swaps) * (100 + GAS_LIMIT_BUFFER_PCT) / 100
+    }
+
+    fn swap_base(table: &GasTable, dex: Dex, pool: Address) -> u64 {
+        table.pools.get(&pool)
+            .or_else(|| table.dexes.get(&dex))
+            .map(|s| s.gas as u64)
+            .unwrap_or_else(|| default_swap_gas(dex))
+    }
+
+    fn overhead_base(table: &GasTable, provider: FlashLoanProvider) -> u64 {
+        table.overhead.get(&provider)
+            .map(|s| s.gas as u64)
+            .unwrap_or_else(|| default_overhead_gas(provider))
+    }
+
+    // --------------------------------------------
+    // Calibration
+    // --------------------------------------------
+
+    /// Record a measured swap (total gas of the pool call frame)
+    ///
+    /// A swap that crossed ticks in a pool with a calibrated base also
+    /// calibrates the gas per tick: whatever exceeds the base is spread over
+    /// the ticks crossed.
+    pub fn record_swap(&self, dex: Dex, pool: Address, gas_used: u64, ticks_crossed: u32) {
+        if is_tick_based(dex) && ticks_crossed > 0 {
+            self.record_tick_crossings(pool, gas_used, ticks_crossed);
+        }
+        self.record_swap_base(dex, pool, gas_used, ticks_crossed);
+    }
+
+    /// Fit the gas per tick crossed from one measured swap
+    fn record_tick_crossings(&self, pool: Address, gas_used: u64, ticks_crossed: u32) {
+        let mut table = self.table.write().unwrap_or_else(|e| e.into_inner());
+
+        // Without a measured base for this pool the crossings can't be told
+        // apart from the swap itself
+        let Some(base) = table.pools.get(&pool).filter(|s| s.samples > 0).map(|s| s.gas) else {
+            return;
+        };
+        let per_tick = (gas_used as f64 - base) / ticks_crossed as f64;
+        if !within_deviation(per_tick, table.tick_cross.gas) {
+            debug!("Discarding tick crossing sample {:.0} for {:?} (expected ~{:.0})", per_tick, pool, table.tick_cross.gas);
+            return;
+        }
+
+        table.tick_cross.record(per_tick as u64);
+    }
+
+    /// Record the swap gas left after the tick crossings
+    fn record_swap_base(&self, dex: Dex, pool: Address, gas_used: u64, ticks_crossed: u32) {
+        let mut table = self.table.write().unwrap_or_else(|e| e.into_inner());
+
+        let ticks_gas = if is_tick_based(dex) {
+            ticks_crossed as u64 * table.tick_cross.gas as u64
+        } else {
+            0
+        };
+        let base = gas_used.saturating_sub(ticks_gas);
+
+        let expected = Self::swap_base(&table, dex, pool) as f64;
+        if !within_deviation(base as f64, expected) {
+            debug!("Discarding swap gas sample {} for {:?} (expected ~{})", base, pool, expected);
+            return;
+        }
+
+        table.pools.entry(pool).or_insert(GasStat { gas: 0.0, samples: 0 }).record(base);
+        table.dexes.entry(dex).or_insert(GasStat { gas: 0.0, samples: 0 }).record(base);
+    }
+
+    /// Record a measured transaction total when per-swap gas isn't available
+    ///
+    /// The difference to the current estimate is spread proportionally over
+    /// the overhead and every hop.
+    pub fn record_total(&self, cycle: &ArbitrageCycle, ticks_crossed: &[u32], gas_used: u64) {
+        let predicted = self.cycle_gas(cycle, ticks_crossed);
+        if predicted == 0 || !within_deviation(gas_used as f64, predicted as f64) {
+            debug!("Discarding tx gas sample {} (predicted {})", gas_used, predicted);
+            return;
+        }
+        let ratio = gas_used as f64 / predicted as f64;
+
+        for (i, (dex, pool)) in cycle.dexes.iter().zip(cycle.pools.iter()).enumerate() {
+            let ticks = ticks_crossed.get(i).copied().unwrap_or(0);
+            // A scaled estimate says nothing about the crossings on their own
+            let scaled = (self.swap_gas(*dex, *pool, ticks) as f64 * ratio) as u64;
+            self.record_swap_base(*dex, *pool, scaled, ticks);
+        }
+
+        let overhead = (self.overhead_gas() as f64 * ratio) as u64;
+        self.record_overhead(overhead);
+    }
+
+    /// Record the measured fixed overhead of a transaction
+    pub fn record_overhead(&self, gas_used: u64) {
+        let mut table = self.table.write().unwrap_or_else(|e| e.into_inner());
+        table.overhead
+            .entry(self.provider)
+            .or_insert(GasStat { gas: 0.0, samples: 0 })
+            .record(gas_used);
+    }
+
+    /// Record a `callTracer` trace of an executor transaction
+    ///
+    /// Each hop is matched (in order) to the first call frame into its pool;
+    /// whatever isn't attributed to swaps is overhead. Falls back to
+    /// `record_total` if a hop can't be matched (e.g. Balancer vault swaps).
+    pub fn record_trace(
+        &self,
+        cycle: &ArbitrageCycle,
+        ticks_crossed: &[u32],
+        total_gas: u64,
+        trace: &serde_json::Value,
+    ) {
+        let mut frames = Vec::new();
+        collect_call_frames(trace, &mut frames);
+
+        let mut matched = Vec::with_capacity(cycle.pools.len());
+        let mut cursor = 0;
+        for pool in &cycle.pools {
+            match frames[cursor..].iter().position(|(to, _)| to == pool) {
+                Some(offset) => {
+                    matched.push(frames[cursor + offset].1);
+                    cursor += offset + 1;
+                }
+                None => break,
+            }
+        }
+
+        if matched.len() != cycle.pools.len() {
+            debug!("Trace matched {}/{} hops, using tx total", matched.len(), cycle.pools.len());
+            self.record_total(cycle, ticks_crossed, total_gas);
+            return;
+        }
+
+        for (i, gas) in matched.iter().enumerate() {
+            let ticks = ticks_crossed.get(i).copied().unwrap_or(0);
+            self.record_swap(cycle.dexes[i], cycle.pools[i], *gas, ticks);
+        }
+        self.record_overhead(total_gas.saturating_sub(matched.iter().sum()));
+    }
+
+    /// Calibrate against the real executor calldata via `eth_estimateGas`,
+    /// refined with `debug_traceCall` when the node supports it
+    pub async fn calibrate_with_call(
+        &self,
+        rpc_url: &str,
+        from: Address,
+        to: Address,
+        calldata: &Bytes,
+        cycle: &ArbitrageCycle,
+        ticks_crossed: &[u32],
+    ) -> Result<u64> {
+        let provider = ProviderBuilder::new()
+            .on_http(rpc_url.parse()?);
+
+        let tx = TransactionRequest::default()
+            .from(from)
+            .to(to)
+            .input(calldata.clone().into());
+
+        let gas_used = provider.estimate_gas(tx.clone()).await
+            .map_err(|e| eyre!("Gas estimation failed: {}", e))?;
+
+        let trace: Result<serde_json::Value, _> = provider.raw_request(
+            "debug_traceCall".into(),
+            (tx, "latest", serde_json::json!({ "tracer": "callTracer" })),
+        ).await;
+
+        match trace {
+            Ok(trace) => self.record_trace(cycle, ticks_crossed, gas_used, &trace),
+            Err(e) => {
+                debug!("debug_traceCall unavailable ({}), calibrating from total", e);
+                self.record_total(cycle, ticks_crossed, gas_used);
+            }
+        }
+
+        debug!("⛽ Calibrated from eth_estimateGas: {} gas ({} hops)", gas_used, cycle.hop_count());
+        self.save()?;
+        Ok(gas_used)
+    }
+
+    /// Calibrate from the receipt of one of our landed executor transactions,
+    /// refined with `debug_traceTransaction` when the node supports it
+    pub async fn calibrate_from_receipt(
+        &self,
+        rpc_url: &str,
+        tx_hash: TxHash,
+        gas_used: u64,
+        cycle: &ArbitrageCycle,
+        ticks_crossed: &[u32],
+    ) -> Result<()> {
+        let provider = ProviderBuilder::new()
+            .on_http(rpc_url.parse()?);
+
+        let trace: Option<serde_json::Value> = provider.raw_request(
+            "debug_traceTransaction".into(),
+            (tx_hash, serde_json::json!({ "tracer": "callTracer" })),
+        ).await.ok();
+        self.record_receipt(cycle, ticks_crossed, gas_used, trace.as_ref());
+
+        debug!("⛽ Calibrated from receipt {}: {} gas", tx_hash, gas_used);
+        self.save()
+    }
+
+    /// Record a landed transaction's gas, per swap when its call trace is known
+    pub fn record_receipt(
+        &self,
+        cycle: &ArbitrageCycle,
+        ticks_crossed: &[u32],
+        gas_used: u64,
+        trace: Option<&serde_json::Value>,
+    ) {
+        match trace {
+            Some(trace) => self.record_trace(cycle, ticks_crossed, gas_used, trace),
+            None => self.record_total(cycle, ticks_crossed, gas_used),
+        }
+    }
+}
+
+/// Whether a sample is plausibly measuring the same thing as the estimate
+fn within_deviation(sample: f64, expected: f64) -> bool {
+    sample >= expected / MAX_SAMPLE_DEVIATION && sample <= expected * MAX_SAMPLE_DEVIATION
+}
+
+/// Flatten a `callTracer` frame tree into (to, gasUsed) in execution order
+fn collect_call_frames(frame: &serde_json::Value, out: &mut Vec<(Address, u64)>) {
+    let to = frame.get("to")
+        .and_then(|v| v.as_str())
+        .and_then(|s| s.parse::<Address>().ok());
+    let gas_used = frame.get("gasUsed")
+        .and_then(|v| v.as_str())
+        .and_then(|s| u64::from_str_radix(s.trim_start_matches("0x"), 16).ok());
+
+    if let (Some(to), Some(gas_used)) = (to, gas_used) {
+        out.push((to, gas_used));
+    }
+
+    if let Some(calls) = frame.get("calls").and_then(|v| v.as_array()) {
+        for call in calls {
+            collect_call_frames(call, out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_hop_cycle() -> ArbitrageCycle {
+        let weth = Address::repeat_byte(1);
+        let usdc = Address::repeat_byte(2);
+        ArbitrageCycle {
+            path: vec![weth, usdc, weth],
+            pools: vec![Address::repeat_byte(0xa1), Address::repeat_byte(0xa2)],
+            dexes: vec![Dex::UniswapV3, Dex::UniswapV2],
+            total_weight: 0.0,
+            expected_return: 1.0,
+            prices: vec![1.0, 1.0],
+            fees: vec![500, 3000],
+        }
+    }
+
+    #[test]
+    fn test_estimates_and_tick_crossings() {
+        let model = SwapGasModel::new(FlashLoanProvider::BalancerV2);
+        let cycle = two_hop_cycle();
+
+        let base = model.cycle_gas(&cycle, &[]);
+        assert_eq!(base, 85_000 + 95_000 + 65_000);
+
+        // Tick crossings only apply to V3-style hops
+        assert_eq!(model.cycle_gas(&cycle, &[2, 5]), base + 2 * DEFAULT_TICK_CROSS_GAS);
+        assert!(model.gas_limit(&cycle, &[]) > base);
+    }
+
+    #[test]
+    fn test_record_trace_calibrates_pools_and_overhead() {
+        let model = SwapGasModel::new(FlashLoanProvider::BalancerV2);
+        let cycle = two_hop_cycle();
+
+        let trace = serde_json::json!({
+            "to": "0xeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee",
+            "gasUsed": "0x30d40", // 200_000
+            "calls": [
+                { "to": "0xa1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1", "gasUsed": "0x1adb0" }, // 110_000
+                { "to": "0xa2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2", "gasUsed": "0xc350" }   //  50_000
+            ]
+        });
+        model.record_trace(&cycle, &[0, 0], 200_000, &trace);
+
+        assert_eq!(model.swap_gas(Dex::UniswapV3, cycle.pools[0], 0), 110_000);
+        assert_eq!(model.swap_gas(Dex::UniswapV2, cycle.pools[1], 0), 50_000);
+        assert_eq!(model.overhead_gas(), 40_000);
+        assert_eq!(model.cycle_gas(&cycle, &[]), 200_000);
+    }
+
+    #[test]
+    fn test_landed_receipt_recalibrates() {
+        let model = SwapGasModel::new(FlashLoanProvider::BalancerV2);
+        let cycle = two_hop_cycle();
+        let base = model.cycle_gas(&cycle, &[]);
+
+        // Without a trace the receipt's total is spread over the hops
+        model.record_receipt(&cycle, &[0, 0], base * 12 / 10, None);
+        assert!(model.cycle_gas(&cycle, &[]) > base);
+        assert!(model.swap_gas(Dex::UniswapV2, cycle.pools[1], 0) > 65_000);
+    }
+
+    #[test]
+    fn test_tick_crossings_calibrate_per_tick_gas() {
+        let model = SwapGasModel::new(FlashLoanProvider::BalancerV2);
+        let pool = Address::repeat_byte(0xa1);
+
+        // No base for the pool yet: the crossings keep the default and the
+        // rest becomes the pool's base
+        model.record_swap(Dex::UniswapV3, pool, 130_000, 2);
+        let base = model.swap_gas(Dex::UniswapV3, pool, 0);
+        assert_eq!(base, 130_000 - 2 * DEFAULT_TICK_CROSS_GAS);
+
+        model.record_swap(Dex::UniswapV3, pool, base + 3 * 15_000, 3);
+        assert_eq!(model.swap_gas(Dex::UniswapV3, pool, 1) - model.swap_gas(Dex::UniswapV3, pool, 0), 15_000);
+    }
+}
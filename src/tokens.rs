@@ -25,7 +25,7 @@ pub struct Token {
 }
 
 /// Token categories for filtering and analysis
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum TokenCategory {
     /// Primary base tokens (WETH, USDC, USDT, DAI)
     BaseStable,
@@ -65,6 +65,33 @@ pub enum TokenCategory {
     RWA,
 }
 
+impl TokenCategory {
+    /// Parse a category name (case-insensitive, e.g. "meme", "ai_compute")
+    pub fn from_name(name: &str) -> Option<Self> {
+        let normalized: String = name.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+        
+        match normalized.as_str() {
+            "basestable" => Some(TokenCategory::BaseStable),
+            "basevolatile" => Some(TokenCategory::BaseVolatile),
+            "yieldbearing" => Some(TokenCategory::YieldBearing),
+            "algostable" => Some(TokenCategory::AlgoStable),
+            "basketbacked" => Some(TokenCategory::BasketBacked),
+            "liquidstaking" | "lsd" => Some(TokenCategory::LiquidStaking),
+            "governance" => Some(TokenCategory::Governance),
+            "meme" => Some(TokenCategory::Meme),
+            "defi" => Some(TokenCategory::DeFi),
+            "aicompute" | "ai" => Some(TokenCategory::AICompute),
+            "gaming" => Some(TokenCategory::Gaming),
+            "restaking" => Some(TokenCategory::Restaking),
+            "rwa" => Some(TokenCategory::RWA),
+            _ => None,
+        }
+    }
+}
+
 // ============================================
 // BASE TOKENS (High Liquidity Starting Points)
 // ============================================